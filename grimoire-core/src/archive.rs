@@ -0,0 +1,123 @@
+//! Read-only `.grimoire` archives: a whole pattern library in one file
+//!
+//! An archive starts with a magic line and a one-line JSON index, followed by a
+//! zstd-compressed tar of the pattern files. Reading one archive is much faster
+//! than opening thousands of small files on a network drive.
+
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+pub const ARCHIVE_EXTENSION: &str = "grimoire";
+const MAGIC: &str = "GRIMOIRE-ARCHIVE 1";
+const ZSTD_LEVEL: i32 = 19;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveIndex {
+    pub created: String,
+    pub files: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+/// A pattern file read from an archive
+#[derive(Debug)]
+pub struct ArchiveFile {
+    /// Path of the file as if the archive were a directory
+    pub path: PathBuf,
+    pub content: String,
+    pub modified: Option<SystemTime>,
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext == ARCHIVE_EXTENSION)
+}
+
+/// Pack the pattern files of a directory into an archive
+pub fn build(dir: &Path, output: &Path, config: &Config) -> Result<ArchiveIndex> {
+    let config = config.with_ignore_file(dir);
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read patterns directory {:?}", dir))?
+        .flat_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| config.is_pattern_file(p))
+        .collect();
+    files.sort();
+
+    let mut tar = tar::Builder::new(zstd::Encoder::new(Vec::new(), ZSTD_LEVEL)?);
+    let mut entries = Vec::with_capacity(files.len());
+    for path in &files {
+        let name = path
+            .file_name()
+            .context("pattern file without a name")?
+            .to_string_lossy()
+            .to_string();
+        tar.append_path_with_name(path, &name)?;
+        entries.push(IndexEntry {
+            name,
+            size: fs::metadata(path)?.len(),
+        });
+    }
+    let compressed = tar.into_inner()?.finish()?;
+
+    let index = ArchiveIndex {
+        created: chrono::Utc::now().to_rfc3339(),
+        files: entries,
+    };
+    let mut out =
+        File::create(output).with_context(|| format!("Failed to create archive {:?}", output))?;
+    writeln!(out, "{}", MAGIC)?;
+    writeln!(out, "{}", serde_json::to_string(&index)?)?;
+    out.write_all(&compressed)?;
+    Ok(index)
+}
+
+/// Read every pattern file in an archive
+pub fn read(path: &Path) -> Result<(ArchiveIndex, Vec<ArchiveFile>)> {
+    let file = File::open(path).with_context(|| format!("Failed to open archive {:?}", path))?;
+    let mut reader = BufReader::new(file);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if line.trim_end() != MAGIC {
+        anyhow::bail!("{:?} is not a grimoire archive", path);
+    }
+    line.clear();
+    reader.read_line(&mut line)?;
+    let index: ArchiveIndex = serde_json::from_str(&line)
+        .with_context(|| format!("Invalid archive index in {:?}", path))?;
+
+    let mut files = Vec::with_capacity(index.files.len());
+    let mut tar = tar::Archive::new(zstd::Decoder::with_buffer(reader)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_path_buf();
+        let modified = entry
+            .header()
+            .mtime()
+            .ok()
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .with_context(|| format!("{:?} in {:?} is not valid UTF-8", name, path))?;
+        files.push(ArchiveFile {
+            path: path.join(name),
+            content,
+            modified,
+        });
+    }
+    Ok((index, files))
+}
@@ -0,0 +1,190 @@
+//! Comparison of the library with an earlier revision, as a CI gate
+//!
+//! Other tools and documents refer to patterns by name, ID, argument and
+//! section. The check lists the changes that break such references: patterns
+//! removed or renamed without deprecation, metadata dropped, and `[[links]]`,
+//! `extends:`, `prerequisites:` or `see_also:` that no longer resolve.
+
+use std::{fmt, path::Path};
+
+use crate::{
+    library::{Library, Pattern},
+    links,
+};
+
+/// Frontmatter key marking a pattern as deprecated, so it can be removed
+/// or renamed. Either `true` or a note such as the replacement to use
+pub const DEPRECATED_KEY: &str = "deprecated";
+
+/// A change that breaks references to the library
+#[derive(Debug)]
+pub enum Problem {
+    Removed {
+        pattern: String,
+        /// The file is still there but no longer parses
+        unparseable: bool,
+    },
+    Renamed {
+        pattern: String,
+        to: String,
+    },
+    LostMetadata {
+        pattern: String,
+        fields: Vec<String>,
+    },
+    BrokenLink {
+        pattern: String,
+        reference: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Removed { pattern, unparseable: true } => write!(
+                f,
+                "- {}: its file no longer parses, check the frontmatter",
+                pattern
+            ),
+            Problem::Removed { pattern, .. } => write!(
+                f,
+                "- {}: removed without being marked `{}` first",
+                pattern, DEPRECATED_KEY
+            ),
+            Problem::Renamed { pattern, to } => write!(
+                f,
+                "~ {}: renamed to '{}' without keeping '{}' as an alias",
+                pattern, to, pattern
+            ),
+            Problem::LostMetadata { pattern, fields } => {
+                write!(f, "~ {}: lost {}", pattern, fields.join(", "))
+            }
+            Problem::BrokenLink { pattern, reference, reason } => {
+                write!(f, "! {}: {} no longer resolves ({})", pattern, reference, reason)
+            }
+        }
+    }
+}
+
+fn is_deprecated(pattern: &Pattern) -> bool {
+    match pattern.metadata.extra.get(DEPRECATED_KEY) {
+        Some(serde_yaml::Value::Bool(deprecated)) => *deprecated,
+        Some(serde_yaml::Value::String(note)) => !note.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// The current version of a pattern: the one with its ID, or else its name or alias
+fn current_version<'a>(current: &'a Library, pattern: &Pattern) -> Option<&'a Pattern> {
+    pattern
+        .metadata
+        .id
+        .as_ref()
+        .and_then(|id| current.patterns.iter().find(|p| p.metadata.id.as_ref() == Some(id)))
+        .or_else(|| current.find_pattern(&pattern.metadata.pattern))
+}
+
+/// Metadata the earlier version had and the current one lacks
+fn lost_metadata(before: &Pattern, after: &Pattern) -> Vec<String> {
+    let (before, after) = (&before.metadata, &after.metadata);
+    let mut lost = Vec::new();
+    match (&before.id, &after.id) {
+        (Some(_), None) => lost.push("id".to_string()),
+        (Some(old), Some(new)) if old != new => lost.push(format!("id {} (now {})", old, new)),
+        _ => {}
+    }
+    if !before.frameworks.is_empty() && after.frameworks.is_empty() {
+        lost.push("framework".to_string());
+    }
+    if before.description.is_some() && after.description.is_none() {
+        lost.push("description".to_string());
+    }
+    if !before.tags.is_empty() && after.tags.is_empty() {
+        lost.push("tags".to_string());
+    }
+    for argument in &before.arguments {
+        if !after.arguments.iter().any(|a| a.name == argument.name) {
+            lost.push(format!("argument '{}'", argument.name));
+        }
+    }
+    lost
+}
+
+/// Every reference a pattern makes: `[[links]]`, its `extends:` base, its prerequisites and
+/// `see_also` patterns, each with why it doesn't resolve in `library`, if it doesn't
+fn references(library: &Library, pattern: &Pattern) -> Vec<(String, Option<String>)> {
+    let mut references: Vec<(String, Option<String>)> = links::parse_links(&pattern.content)
+        .into_iter()
+        .map(|link| {
+            let written = format!("{}[[{}]]", if link.embed { "!" } else { "" }, link.reference());
+            (written, library.resolve_link(&link).err())
+        })
+        .collect();
+    let named = pattern
+        .metadata
+        .extends
+        .iter()
+        .map(|name| ("extends", name))
+        .chain(pattern.metadata.prerequisites.iter().map(|name| ("prerequisite", name)))
+        .chain(pattern.metadata.see_also.iter().map(|name| ("see also", name)));
+    for (kind, name) in named {
+        let unresolved = library
+            .find_pattern(name)
+            .is_none()
+            .then(|| format!("pattern '{}' not found", name));
+        references.push((format!("{} '{}'", kind, name), unresolved));
+    }
+    references
+}
+
+/// The changes from `reference` to `current` that break references to the library.
+/// `patterns_dir` holds the current files, to tell removed patterns from unparseable ones
+pub fn check(current: &Library, reference: &Library, patterns_dir: &Path) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for before in &reference.patterns {
+        let name = &before.metadata.pattern;
+        let Some(after) = current_version(current, before) else {
+            if !is_deprecated(before) {
+                let unparseable = before
+                    .filepath
+                    .file_name()
+                    .is_some_and(|file| patterns_dir.join(file).exists());
+                problems.push(Problem::Removed { pattern: name.clone(), unparseable });
+            }
+            continue;
+        };
+        if current.find_pattern(name).is_none() && !is_deprecated(before) {
+            problems.push(Problem::Renamed {
+                pattern: name.clone(),
+                to: after.metadata.pattern.clone(),
+            });
+        }
+        let fields = lost_metadata(before, after);
+        if !fields.is_empty() {
+            problems.push(Problem::LostMetadata { pattern: name.clone(), fields });
+        }
+    }
+
+    // Links that resolved before, or were added since, and don't resolve now
+    for pattern in &current.patterns {
+        let before = current_version(reference, pattern).map(|p| references(reference, p));
+        for (written, unresolved) in references(current, pattern) {
+            let Some(reason) = unresolved else {
+                continue;
+            };
+            let already_broken = before
+                .iter()
+                .flatten()
+                .any(|(was, unresolved)| *was == written && unresolved.is_some());
+            if !already_broken {
+                problems.push(Problem::BrokenLink {
+                    pattern: pattern.metadata.pattern.clone(),
+                    reference: written,
+                    reason,
+                });
+            }
+        }
+    }
+    problems
+}
@@ -0,0 +1,719 @@
+//! Server configuration, loaded from `grimoire.toml`
+
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::library::{self, PatternFormat, PatternMetadata};
+
+pub const ENV_PATTERNS_DIR: &str = "PATTERNS_DIR";
+pub const ENV_CONFIG: &str = "GRIMOIRE_CONFIG";
+pub const ENV_READ_ONLY: &str = "GRIMOIRE_READ_ONLY";
+pub const CONFIG_FILE_NAME: &str = "grimoire.toml";
+/// File in a patterns directory listing more file names to skip, one glob per line
+pub const IGNORE_FILE: &str = ".grimoireignore";
+/// Category of the patterns served as MCP prompts unless configured otherwise
+pub const DEFAULT_PROMPT_CATEGORY: &str = "prompt";
+/// Extensions of the files loaded as patterns unless configured otherwise
+pub const DEFAULT_EXTENSIONS: &[&str] = &["md"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Directory containing the pattern files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patterns_dir: Option<PathBuf>,
+    /// Namespace the patterns directory's patterns are listed under, none when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Glob patterns for file names that are never loaded as patterns
+    pub ignore: Vec<String>,
+    /// Extensions of the files loaded as patterns (md, markdown, mdx or txt), `md` when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Vec<String>>,
+    /// Read-only `.grimoire` archives mounted alongside the patterns directory
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub archives: Vec<PathBuf>,
+    /// More pattern directories or archives served alongside the patterns directory
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<Mount>,
+    /// Environment variables that `${VAR}` placeholders in pattern bodies may expand to
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_env: Vec<String>,
+    /// Directory export_flashcards may write files to, by name. Clients can't write files
+    /// when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export_dir: Option<PathBuf>,
+    /// Category of the patterns served as MCP prompts, `prompt` when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_category: Option<String>,
+    /// Hide every tool that changes anything, for serving a shared, curated library
+    pub read_only: bool,
+    pub limits: Limits,
+    pub defaults: Defaults,
+    pub validation: Validation,
+    /// Embedding backend for semantic search, disabled when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embeddings: Option<EmbeddingsConfig>,
+    pub roots: Roots,
+    pub ranking: Ranking,
+    pub stdio: Stdio,
+    pub http: Http,
+    pub tcp: Tcp,
+    pub auth: Auth,
+    pub rate_limit: RateLimit,
+    pub logging: Logging,
+    /// Teams served their own libraries by the HTTP transports, instead of `patterns_dir`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tenants: Vec<Tenant>,
+}
+
+/// A pattern directory or archive served alongside the patterns directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mount {
+    pub path: PathBuf,
+    /// Prefix of the mounted patterns' names, e.g. `team` for `team/axum-errors`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+/// Service that turns pattern text into vectors
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingBackend {
+    /// Ollama's `/api/embed` endpoint, usually a local model
+    Ollama,
+    /// OpenAI-compatible `/embeddings` endpoint
+    OpenAi,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingsConfig {
+    pub backend: EmbeddingBackend,
+    /// Embedding model name, e.g. "nomic-embed-text" or "text-embedding-3-small"
+    pub model: String,
+    /// Base URL of the service, defaulting to the backend's usual address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Environment variable holding the API key, for backends that need one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+}
+
+/// Values create_pattern falls back to when the caller leaves a field out
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Defaults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub framework: Option<String>,
+    /// Written as the `author` of created patterns that don't name one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Tags added to every created pattern, besides the caller's
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Fields the caller must always provide (e.g. "framework"), so agents ask the user instead of guessing
+    pub required: Vec<String>,
+}
+
+/// Placeholder values agents use for "no value", treated as absent
+const EMPTY_VALUES: &[&str] = &["", "none", "n/a", "na", "null", "nil", "-", "unknown"];
+
+/// The value, or `None` if it is blank or a placeholder like "none"
+pub fn normalize_optional(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !EMPTY_VALUES.contains(&v.to_lowercase().as_str()))
+}
+
+impl Defaults {
+    /// Resolve a field from the caller's value, falling back to the default unless the field is required
+    pub fn resolve(
+        &self,
+        field: &str,
+        value: Option<String>,
+        default: Option<&String>,
+    ) -> Result<Option<String>, String> {
+        match normalize_optional(value) {
+            Some(value) => Ok(Some(value)),
+            None if self.required.iter().any(|r| r == field) => Err(format!(
+                "{} is required, ask the user which {} to use",
+                field, field
+            )),
+            None => Ok(default.cloned()),
+        }
+    }
+}
+
+/// House rules create_pattern holds new patterns to, on top of the limits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Validation {
+    /// Categories patterns may have, any when empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// Fewest tags a pattern may have
+    pub min_tags: usize,
+    /// Words pattern names may not contain, matched against the parts between `-` and `_`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub banned_words: Vec<String>,
+}
+
+impl Validation {
+    /// Every rule the normalized metadata breaks, described for the caller to fix
+    pub fn violations(&self, metadata: &PatternMetadata) -> Vec<String> {
+        let mut violations = Vec::new();
+        let categories = library::normalize_all(&self.categories);
+        if !categories.is_empty() && !categories.contains(&metadata.category) {
+            violations.push(format!(
+                "category '{}' is not allowed, use one of: {}",
+                metadata.category,
+                categories.join(", ")
+            ));
+        }
+        if metadata.tags.len() < self.min_tags {
+            violations.push(format!(
+                "at least {} tags are required, got {}",
+                self.min_tags,
+                metadata.tags.len()
+            ));
+        }
+        let banned: Vec<String> = self
+            .banned_words
+            .iter()
+            .map(|word| word.trim())
+            .filter(|word| {
+                metadata
+                    .pattern
+                    .split(['-', '_'])
+                    .any(|part| part.eq_ignore_ascii_case(word))
+            })
+            .map(|word| format!("'{}'", word))
+            .collect();
+        if !banned.is_empty() {
+            violations.push(format!("the name must not contain {}", banned.join(", ")));
+        }
+        violations
+    }
+}
+
+/// Pattern directories discovered under the filesystem roots the MCP client shares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Roots {
+    /// Ask the client for its roots and load the pattern directories found under them
+    pub enabled: bool,
+    /// Load only the discovered directories, not the patterns directory, while any are found
+    pub replace: bool,
+    /// Names of the directories that hold patterns
+    pub dirs: Vec<String>,
+}
+
+impl Default for Roots {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            replace: false,
+            dirs: vec!["patterns".into(), ".grimoire".into()],
+        }
+    }
+}
+
+/// How search results are ranked beyond their relevance to the query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ranking {
+    /// Share of the search score that comes from pattern quality, from 0.0 (ignored) to 1.0
+    pub quality_weight: f64,
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Self { quality_weight: 0.2 }
+    }
+}
+
+/// The stdio transport, serving the client that started the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stdio {
+    /// Serve over stdio unless `--transport` says otherwise
+    pub enabled: bool,
+}
+
+impl Default for Stdio {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// The HTTP transports, serving many clients from one process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Http {
+    /// Serve streamable HTTP unless `--transport` says otherwise
+    pub enabled: bool,
+    /// Serve HTTP+SSE, for older web clients, on the same address unless `--transport` says
+    /// otherwise
+    pub sse: bool,
+    /// Address and port to listen on
+    pub listen: String,
+    /// Seconds between keep-alive pings on idle event streams, so proxies don't close them
+    pub keep_alive_secs: u64,
+    /// Serve HTTPS with this certificate instead of plain HTTP
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Tls>,
+}
+
+/// Certificate and private key the HTTP transports terminate TLS with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tls {
+    /// PEM file with the certificate chain, the server's certificate first
+    pub cert: PathBuf,
+    /// PEM file with the private key
+    pub key: PathBuf,
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sse: false,
+            listen: "127.0.0.1:7422".into(),
+            keep_alive_secs: 15,
+            tls: None,
+        }
+    }
+}
+
+impl Http {
+    pub fn keep_alive(&self) -> Duration {
+        Duration::from_secs(self.keep_alive_secs.max(1))
+    }
+}
+
+/// The plain TCP transport, for tooling that can't speak HTTP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tcp {
+    /// Serve over TCP unless `--transport` says otherwise
+    pub enabled: bool,
+    /// Address and port to listen on
+    pub listen: String,
+}
+
+impl Default for Tcp {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:7423".into(),
+        }
+    }
+}
+
+/// A log file kept alongside stderr, which stdio clients often swallow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Logging {
+    /// File log events are appended to as JSON lines, stderr only when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Size the file may grow to before it is rotated to `<file>.1`
+    pub max_bytes: u64,
+    /// Rotated files kept besides the current one, the oldest deleted first
+    pub keep: usize,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            file: None,
+            max_bytes: 10 * 1024 * 1024,
+            keep: 5,
+        }
+    }
+}
+
+/// Bearer tokens clients of the network transports must present, when any are configured
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Auth {
+    pub tokens: Vec<Token>,
+}
+
+/// What a token lets its client do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    /// Search and read patterns, but not change anything on the server
+    Read,
+    /// Use every tool
+    #[default]
+    Write,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Token {
+    /// The token itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Environment variable holding the token, to keep it out of the config file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+    #[serde(default)]
+    pub access: Access,
+}
+
+impl Auth {
+    /// Every configured token with its access
+    pub fn resolve(&self) -> Result<Vec<(String, Access)>> {
+        let mut tokens = Vec::new();
+        for token in &self.tokens {
+            let value = match (&token.token, &token.token_env) {
+                (Some(value), None) => value.clone(),
+                (None, Some(env)) => std::env::var(env)
+                    .with_context(|| format!("Auth token variable {} is not set", env))?,
+                _ => anyhow::bail!("Each [[auth.tokens]] entry needs either token or token_env"),
+            };
+            if value.trim().is_empty() {
+                anyhow::bail!("Empty auth token, generate one with e.g. `openssl rand -hex 32`");
+            }
+            tokens.push((value.trim().to_string(), token.access));
+        }
+        Ok(tokens)
+    }
+}
+
+/// Tool calls each network client may make, counted per address. Stdio clients are
+/// never limited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    /// Tool calls per minute, 0 for no limit
+    pub calls_per_minute: u32,
+    /// Calls per minute to tools that write, 0 for no limit beyond `calls_per_minute`
+    pub writes_per_minute: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            calls_per_minute: 120,
+            writes_per_minute: 20,
+        }
+    }
+}
+
+/// A team with its own library, served by the HTTP transports under `/<name>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tenant {
+    /// Name, used as the path prefix the tenant's clients connect under
+    pub name: String,
+    /// Directory containing the tenant's pattern files
+    pub patterns_dir: PathBuf,
+    /// Bearer tokens the tenant's clients authenticate with
+    pub keys: Vec<String>,
+}
+
+impl Tenant {
+    /// Check that the name can be used in a URL path and that clients have a key to use
+    pub fn validate(&self) -> Result<()> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name {
+            anyhow::bail!(
+                "Invalid tenant name '{}', use only letters, digits, '-' and '_'",
+                self.name
+            );
+        }
+        if self.keys.iter().all(|k| k.trim().is_empty()) {
+            anyhow::bail!(
+                "Tenant '{}' has no keys, its library would be open to anyone",
+                self.name
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// Number of entries returned by search and list tools when the caller sets no limit
+    pub default_limit: usize,
+    /// Maximum number of entries returned by search and list tools
+    pub max_results: usize,
+    /// Maximum size of a pattern body accepted by create_pattern
+    pub max_content_bytes: usize,
+    /// Seconds a tool call may run before it is aborted, 0 for no limit
+    pub request_timeout_secs: u64,
+    /// Bulk operations changing more files than this need the caller to confirm the exact count
+    pub bulk_confirm_threshold: usize,
+    /// Entries per page of the MCP resources and prompts lists
+    pub list_page_size: usize,
+    /// Seconds to wait for tool calls in progress when the server is asked to stop
+    pub shutdown_grace_secs: u64,
+    /// Largest HTTP request body or TCP message accepted from network clients
+    pub max_request_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            default_limit: 20,
+            max_results: 50,
+            max_content_bytes: 1024 * 1024,
+            request_timeout_secs: 30,
+            bulk_confirm_threshold: 5,
+            list_page_size: 100,
+            shutdown_grace_secs: 10,
+            max_request_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+impl Limits {
+    pub fn request_timeout(&self) -> Option<Duration> {
+        (self.request_timeout_secs > 0).then(|| Duration::from_secs(self.request_timeout_secs))
+    }
+
+    pub fn shutdown_grace(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_secs)
+    }
+}
+
+impl Config {
+    /// Default config location, `$XDG_CONFIG_HOME/grimoire-mcp/grimoire.toml` or `~/.config/...`
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+            .map(|d| d.join("grimoire-mcp").join(CONFIG_FILE_NAME))
+    }
+
+    /// Config path from `GRIMOIRE_CONFIG`, falling back to the default location
+    pub fn path() -> Option<PathBuf> {
+        std::env::var_os(ENV_CONFIG)
+            .map(PathBuf::from)
+            .or_else(Self::default_path)
+    }
+
+    /// Read a config file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {:?}", path))?;
+        let mut config: Self =
+            toml::from_str(&content).with_context(|| format!("Invalid config file {:?}", path))?;
+        config
+            .check()
+            .with_context(|| format!("Invalid config file {:?}", path))?;
+        Ok(config)
+    }
+
+    /// Check the values serde can't, normalizing namespaces written as `team/` to `team`
+    fn check(&mut self) -> Result<()> {
+        for extension in self.extensions.iter().flatten() {
+            if PatternFormat::from_extension(extension).is_none() {
+                let supported: Vec<&str> =
+                    PatternFormat::EXTENSIONS.iter().map(|(ext, _)| *ext).collect();
+                anyhow::bail!(
+                    "Unsupported pattern file extension '{}', use {}",
+                    extension,
+                    supported.join(", ")
+                );
+            }
+        }
+        let namespaces = std::iter::once(&mut self.namespace)
+            .chain(self.mounts.iter_mut().map(|m| &mut m.namespace))
+            .flatten();
+        for namespace in namespaces {
+            *namespace = namespace.trim().trim_end_matches('/').to_string();
+            let valid = !namespace.is_empty()
+                && namespace
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                anyhow::bail!(
+                    "Invalid namespace '{}', use only letters, digits, '-' and '_'",
+                    namespace
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the config if one exists, with `PATTERNS_DIR` and `GRIMOIRE_READ_ONLY` taking
+    /// precedence over the file
+    pub fn load() -> Result<Self> {
+        Self::load_from(None)
+    }
+
+    /// Load the config from `path`, which must exist, or else like [`Config::load`]
+    pub fn load_from(path: Option<&Path>) -> Result<Self> {
+        let path = path.map(Path::to_path_buf);
+        let mut config = match path.or_else(|| Self::path().filter(|p| p.exists())) {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
+        };
+        if let Some(dir) = std::env::var_os(ENV_PATTERNS_DIR) {
+            config.patterns_dir = Some(PathBuf::from(dir));
+        }
+        if let Ok(value) = std::env::var(ENV_READ_ONLY) {
+            config.read_only = match value.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "" | "0" | "false" | "no" | "off" => false,
+                _ => anyhow::bail!("{} must be true or false, not '{}'", ENV_READ_ONLY, value),
+            };
+        }
+        Ok(config)
+    }
+
+    /// Recommended settings for a fresh config file
+    pub fn recommended(patterns_dir: Option<PathBuf>) -> Self {
+        Self {
+            patterns_dir,
+            ignore: vec!["README.md".into(), "TEMPLATE.md".into(), ".*".into()],
+            namespace: None,
+            extensions: None,
+            archives: Vec::new(),
+            mounts: Vec::new(),
+            allowed_env: Vec::new(),
+            export_dir: None,
+            prompt_category: None,
+            read_only: false,
+            limits: Limits::default(),
+            defaults: Defaults::default(),
+            validation: Validation::default(),
+            embeddings: None,
+            roots: Roots::default(),
+            ranking: Ranking::default(),
+            stdio: Stdio::default(),
+            http: Http::default(),
+            tcp: Tcp::default(),
+            auth: Auth::default(),
+            rate_limit: RateLimit::default(),
+            logging: Logging::default(),
+            tenants: Vec::new(),
+        }
+    }
+
+    /// The config a tenant's library is served with: this one, with the tenant's patterns
+    /// directory and without the mounts, which could be written to by every tenant
+    pub fn for_tenant(&self, tenant: &Tenant) -> Self {
+        Self {
+            patterns_dir: Some(tenant.patterns_dir.clone()),
+            namespace: None,
+            mounts: Vec::new(),
+            tenants: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// The settings to switch to when the config file changes to `new`. Those only read at
+    /// startup, such as the transports and embeddings, are kept from `self`, and the names
+    /// of the ones that differ are returned so the caller can say a restart is needed
+    pub fn live_update(&self, new: Config) -> (Config, Vec<&'static str>) {
+        let value = |config: &Config| serde_json::to_value(config).unwrap_or_default();
+        let (running, wanted) = (value(self), value(&new));
+        let restart = [
+            "patterns_dir",
+            "embeddings",
+            "stdio",
+            "http",
+            "tcp",
+            "auth",
+            "rate_limit",
+            "logging",
+            "tenants",
+        ]
+        .into_iter()
+        .filter(|key| running.get(key) != wanted.get(key))
+        .collect();
+        let config = Config {
+            patterns_dir: self.patterns_dir.clone(),
+            embeddings: self.embeddings.clone(),
+            stdio: self.stdio.clone(),
+            http: self.http.clone(),
+            tcp: self.tcp.clone(),
+            auth: self.auth.clone(),
+            rate_limit: self.rate_limit.clone(),
+            logging: self.logging.clone(),
+            tenants: self.tenants.clone(),
+            ..new
+        };
+        (config, restart)
+    }
+
+    /// Category of the patterns served as MCP prompts
+    pub fn prompt_category(&self) -> &str {
+        self.prompt_category
+            .as_deref()
+            .unwrap_or(DEFAULT_PROMPT_CATEGORY)
+    }
+
+    /// The format of a file loaded as a pattern, `None` unless it has one of the configured
+    /// extensions
+    pub fn pattern_format(&self, path: &Path) -> Option<PatternFormat> {
+        let extension = path.extension()?.to_string_lossy();
+        let extension = extension.as_ref();
+        let accepted = match &self.extensions {
+            Some(extensions) => extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension)),
+            None => DEFAULT_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(extension)),
+        };
+        accepted.then(|| PatternFormat::from_extension(extension)).flatten()
+    }
+
+    /// Extensions of the files loaded as patterns, without a leading dot
+    pub fn pattern_extensions(&self) -> Vec<&str> {
+        match &self.extensions {
+            Some(extensions) => extensions.iter().map(|e| e.trim_start_matches('.')).collect(),
+            None => DEFAULT_EXTENSIONS.to_vec(),
+        }
+    }
+
+    /// Whether a file is loaded as a pattern: it has an accepted extension and isn't ignored
+    pub fn is_pattern_file(&self, path: &Path) -> bool {
+        self.pattern_format(path).is_some()
+            && !path
+                .file_name()
+                .is_some_and(|n| self.is_ignored(&n.to_string_lossy()))
+    }
+
+    /// This config with the rules of `dir`'s ignore file, if it has one, added to `ignore`.
+    /// Blank lines and lines starting with `#` are skipped
+    pub fn with_ignore_file(&self, dir: &Path) -> Cow<'_, Config> {
+        let Ok(content) = fs::read_to_string(dir.join(IGNORE_FILE)) else {
+            return Cow::Borrowed(self);
+        };
+        let mut config = self.clone();
+        config.ignore.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+        Cow::Owned(config)
+    }
+
+    /// Whether a file should be skipped when loading patterns
+    pub fn is_ignored(&self, file_name: &str) -> bool {
+        self.ignore
+            .iter()
+            .filter_map(|rule| glob::Pattern::new(rule).ok())
+            .any(|rule| rule.matches(file_name))
+    }
+}
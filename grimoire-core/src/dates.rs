@@ -0,0 +1,246 @@
+//! Human-friendly dates for search filters
+//!
+//! Agents phrase time constraints naturally, so date filters accept relative
+//! expressions such as `30d`, `2 weeks ago` or `last quarter` as well as ISO
+//! dates, all resolved against the current time.
+
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveDate, TimeZone};
+
+/// A calendar period, for `this month` and `last quarter`
+#[derive(Debug, Clone, Copy)]
+enum Period {
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+/// An amount of time counted back from now, for `30d` and `2 weeks ago`
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+fn period(word: &str) -> Option<Period> {
+    match word {
+        "week" => Some(Period::Week),
+        "month" => Some(Period::Month),
+        "quarter" => Some(Period::Quarter),
+        "year" => Some(Period::Year),
+        _ => None,
+    }
+}
+
+fn unit(word: &str) -> Option<Unit> {
+    match word.strip_suffix('s').unwrap_or(word) {
+        "h" | "hr" | "hour" => Some(Unit::Hour),
+        "d" | "day" => Some(Unit::Day),
+        "w" | "wk" | "week" => Some(Unit::Week),
+        "m" | "mo" | "month" => Some(Unit::Month),
+        "q" | "quarter" => Some(Unit::Quarter),
+        "y" | "yr" | "year" => Some(Unit::Year),
+        _ => None,
+    }
+}
+
+/// Local midnight at the start of a day
+fn start_of_day(date: NaiveDate) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).earliest()
+}
+
+/// Start of the period containing `now`, or of the one before it
+fn period_start(period: Period, now: DateTime<Local>, previous: bool) -> Option<DateTime<Local>> {
+    let today = now.date_naive();
+    let (start, length) = match period {
+        Period::Week => {
+            let monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+            return start_of_day(if previous { monday - Duration::days(7) } else { monday });
+        }
+        Period::Month => (today.with_day(1)?, 1),
+        Period::Quarter => (
+            NaiveDate::from_ymd_opt(today.year(), (today.month0() / 3) * 3 + 1, 1)?,
+            3,
+        ),
+        Period::Year => (NaiveDate::from_ymd_opt(today.year(), 1, 1)?, 12),
+    };
+    let start = if previous {
+        start.checked_sub_months(Months::new(length))?
+    } else {
+        start
+    };
+    start_of_day(start)
+}
+
+/// `count` units before `now`
+fn ago(count: u32, unit: Unit, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    match unit {
+        Unit::Hour => now.checked_sub_signed(Duration::hours(count.into())),
+        Unit::Day => now.checked_sub_signed(Duration::days(count.into())),
+        Unit::Week => now.checked_sub_signed(Duration::weeks(count.into())),
+        Unit::Month => now.checked_sub_months(Months::new(count)),
+        Unit::Quarter => now.checked_sub_months(Months::new(count.checked_mul(3)?)),
+        Unit::Year => now.checked_sub_months(Months::new(count.checked_mul(12)?)),
+    }
+}
+
+/// Split `30d`, `30 days` or `30days` into the count and unit word
+fn split_amount(expr: &str) -> Option<(u32, &str)> {
+    let digits = expr.find(|c: char| !c.is_ascii_digit())?;
+    let count = expr[..digits].parse().ok()?;
+    Some((count, expr[digits..].trim()))
+}
+
+/// The moment a date expression refers to
+///
+/// Accepts ISO dates (`2024-05-01`) and timestamps, `now`, `today`, `yesterday`,
+/// amounts of time back from now (`30d`, `6 months`, `2 weeks ago`, `past 90 days`)
+/// and calendar periods (`this month`, `last quarter`), which resolve to when the
+/// period began.
+pub fn parse(expr: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let text = expr.trim().to_lowercase();
+    let text = text.strip_suffix(" ago").unwrap_or(&text).trim();
+
+    let parsed = match text {
+        "now" => Some(now),
+        "today" => start_of_day(now.date_naive()),
+        "yesterday" => start_of_day(now.date_naive() - Duration::days(1)),
+        _ => {
+            let words: Vec<&str> = text.split_whitespace().collect();
+            match words[..] {
+                ["this", word] => period(word).and_then(|p| period_start(p, now, false)),
+                ["last" | "past", word] if period(word).is_some() => {
+                    period(word).and_then(|p| period_start(p, now, true))
+                }
+                _ => {
+                    let amount = ["last ", "past "]
+                        .iter()
+                        .find_map(|prefix| text.strip_prefix(prefix))
+                        .unwrap_or(text);
+                    split_amount(amount)
+                        .and_then(|(count, word)| ago(count, unit(word)?, now))
+                        .or_else(|| {
+                            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                                .ok()
+                                .and_then(start_of_day)
+                        })
+                        .or_else(|| {
+                            DateTime::parse_from_rfc3339(expr.trim())
+                                .ok()
+                                .map(|t| t.with_timezone(&Local))
+                        })
+                }
+            }
+        }
+    };
+    parsed.ok_or_else(|| {
+        format!(
+            "Unrecognized date '{}', use e.g. 2024-05-01, 30d, 2 weeks ago or last quarter",
+            expr
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Wednesday afternoon
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 5, 15, 14, 30, 0).unwrap()
+    }
+
+    fn midnight(year: i32, month: u32, day: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn split_amounts() {
+        let cases = [
+            ("30d", Some((30, "d"))),
+            ("30 days", Some((30, "days"))),
+            ("30days", Some((30, "days"))),
+            ("2  weeks", Some((2, "weeks"))),
+            ("days", None),
+            ("30", None),
+            ("4294967296d", None),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(split_amount(expr), expected, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn unit_spellings() {
+        let cases = [
+            ("1h", now() - Duration::hours(1)),
+            ("3 hrs", now() - Duration::hours(3)),
+            ("2 hours ago", now() - Duration::hours(2)),
+            ("30d", now() - Duration::days(30)),
+            ("1 day", now() - Duration::days(1)),
+            ("past 90 days", now() - Duration::days(90)),
+            ("2w", now() - Duration::weeks(2)),
+            ("2 wks", now() - Duration::weeks(2)),
+            ("2 weeks ago", now() - Duration::weeks(2)),
+            ("6m", Local.with_ymd_and_hms(2023, 11, 15, 14, 30, 0).unwrap()),
+            ("6 mos", Local.with_ymd_and_hms(2023, 11, 15, 14, 30, 0).unwrap()),
+            ("last 6 months", Local.with_ymd_and_hms(2023, 11, 15, 14, 30, 0).unwrap()),
+            ("1q", Local.with_ymd_and_hms(2024, 2, 15, 14, 30, 0).unwrap()),
+            ("2 quarters", Local.with_ymd_and_hms(2023, 11, 15, 14, 30, 0).unwrap()),
+            ("1y", Local.with_ymd_and_hms(2023, 5, 15, 14, 30, 0).unwrap()),
+            ("2 YRS AGO", Local.with_ymd_and_hms(2022, 5, 15, 14, 30, 0).unwrap()),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(parse(expr, now()), Ok(expected), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn named_days_and_periods() {
+        let cases = [
+            ("now", now()),
+            ("today", midnight(2024, 5, 15)),
+            ("yesterday", midnight(2024, 5, 14)),
+            ("this week", midnight(2024, 5, 13)),
+            ("last week", midnight(2024, 5, 6)),
+            ("this month", midnight(2024, 5, 1)),
+            ("last month", midnight(2024, 4, 1)),
+            ("this quarter", midnight(2024, 4, 1)),
+            ("last quarter", midnight(2024, 1, 1)),
+            ("this year", midnight(2024, 1, 1)),
+            ("past year", midnight(2023, 1, 1)),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(parse(expr, now()), Ok(expected), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn iso_dates_and_timestamps() {
+        assert_eq!(parse("2024-03-01", now()), Ok(midnight(2024, 3, 1)));
+        assert_eq!(parse(" 2024-03-01 ", now()), Ok(midnight(2024, 3, 1)));
+        let timestamp = DateTime::parse_from_rfc3339("2024-03-01T09:26:53Z").unwrap();
+        assert_eq!(parse("2024-03-01T09:26:53Z", now()), Ok(timestamp.with_timezone(&Local)));
+        assert!(parse("2024-02-30", now()).is_err());
+        assert!(parse("2024-3-1T", now()).is_err());
+    }
+
+    #[test]
+    fn overflowing_amounts_are_errors() {
+        for expr in ["4294967295 weeks", "4294967295 hours", "2000000000 quarters", "4294967295y"] {
+            assert!(parse(expr, now()).is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn unknown_expressions_are_errors() {
+        for expr in ["", "soon", "30 fortnights", "this decade", "last", "ago"] {
+            let error = parse(expr, now()).unwrap_err();
+            assert!(error.starts_with("Unrecognized date"), "{}", expr);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Dependencies imported by the code blocks of a pattern
+//!
+//! Fenced code blocks are read according to their language tag: `use` and
+//! `extern crate` in Rust, `[dependencies]` tables in TOML, `import`/`from` in
+//! Python, `import`/`require` in JavaScript and TypeScript, and `import` in Go.
+//! Only the package is kept (`tokio` for `use tokio::sync::Mutex`), and standard
+//! library and relative imports are left out.
+
+use std::collections::BTreeSet;
+
+/// Rust path roots that aren't crates
+const RUST_BUILTINS: &[&str] = &["std", "core", "alloc", "crate", "self", "super", "Self"];
+
+/// The name dependencies are looked up by: lowercase, with `-` and `_` treated alike
+/// as Cargo and PyPI do
+pub fn normalize(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+/// Every dependency imported by the fenced code blocks of a markdown body
+pub fn imports(body: &str) -> BTreeSet<String> {
+    let mut found = BTreeSet::new();
+    let mut block: Option<(String, Vec<&str>)> = None;
+    for line in body.lines() {
+        let Some(fence) = line.trim_start().strip_prefix("```") else {
+            if let Some((_, lines)) = &mut block {
+                lines.push(line);
+            }
+            continue;
+        };
+        match block.take() {
+            Some((language, lines)) => found.extend(block_imports(&language, &lines)),
+            None => {
+                let language = fence.split_whitespace().next().unwrap_or_default();
+                block = Some((language.to_lowercase(), Vec::new()));
+            }
+        }
+    }
+    found
+}
+
+fn block_imports(language: &str, lines: &[&str]) -> Vec<String> {
+    match language {
+        "rust" | "rs" => lines.iter().filter_map(|l| rust_import(l)).collect(),
+        "toml" => toml_dependencies(lines),
+        "python" | "py" => lines.iter().flat_map(|l| python_imports(l)).collect(),
+        "javascript" | "js" | "jsx" | "mjs" | "typescript" | "ts" | "tsx" => {
+            lines.iter().filter_map(|l| js_import(l)).collect()
+        }
+        "go" | "golang" => go_imports(lines),
+        _ => Vec::new(),
+    }
+}
+
+/// The crate of a `use` or `extern crate` line
+fn rust_import(line: &str) -> Option<String> {
+    let mut line = line.trim();
+    if let Some(rest) = line.strip_prefix("pub") {
+        // `pub use`, `pub(crate) use`
+        line = rest.split_once("use ").map(|(_, path)| path)?;
+    } else if let Some(rest) = line.strip_prefix("extern crate ") {
+        line = rest;
+    } else {
+        line = line.strip_prefix("use ")?;
+    }
+    let name = line
+        .trim_start_matches("::")
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()?;
+    (!name.is_empty() && !RUST_BUILTINS.contains(&name)).then(|| normalize(name))
+}
+
+/// Keys of the `[dependencies]` tables of a Cargo manifest
+fn toml_dependencies(lines: &[&str]) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut in_dependencies = false;
+    for line in lines.iter().map(|l| l.trim()) {
+        if let Some(table) = line.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            let mut parts = table.rsplitn(2, '.');
+            let last = parts.next().unwrap_or_default();
+            in_dependencies = last.ends_with("dependencies");
+            // `[dependencies.serde]` names a dependency itself
+            if parts.next().is_some_and(|parent| parent.ends_with("dependencies")) {
+                in_dependencies = false;
+                found.push(normalize(last));
+            }
+        } else if in_dependencies && let Some((key, _)) = line.split_once('=') {
+            let key = key.trim().trim_matches('"');
+            if !key.is_empty() && !key.starts_with('#') {
+                found.push(normalize(key));
+            }
+        }
+    }
+    found
+}
+
+/// Top-level packages of an `import` or `from ... import` line, skipping relative imports
+fn python_imports(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let modules: Vec<&str> = if let Some(rest) = line.strip_prefix("from ") {
+        rest.split_whitespace().next().into_iter().collect()
+    } else if let Some(rest) = line.strip_prefix("import ") {
+        rest.split(',').filter_map(|m| m.split_whitespace().next()).collect()
+    } else {
+        Vec::new()
+    };
+    modules
+        .into_iter()
+        .filter(|m| !m.starts_with('.'))
+        .filter_map(|m| m.split('.').next())
+        .filter(|m| !m.is_empty())
+        .map(normalize)
+        .collect()
+}
+
+/// The package named by a module specifier, unless it is relative or a Node builtin
+fn js_package(specifier: &str) -> Option<String> {
+    if specifier.is_empty() || specifier.starts_with(['.', '/', '#']) || specifier.starts_with("node:")
+    {
+        return None;
+    }
+    let mut segments = specifier.split('/');
+    let first = segments.next()?;
+    if first.starts_with('@') {
+        Some(format!("{}/{}", first, segments.next()?))
+    } else {
+        Some(first.to_string())
+    }
+}
+
+/// The first quoted string on a line
+fn quoted(line: &str) -> Option<&str> {
+    let start = line.find(['\'', '"', '`'])?;
+    let quote = line[start..].chars().next()?;
+    let rest = &line[start + 1..];
+    Some(&rest[..rest.find(quote)?])
+}
+
+/// The package of an `import ... from`, `export ... from`, `import '...'` or `require(...)` line
+fn js_import(line: &str) -> Option<String> {
+    let line = line.trim();
+    let specifier = if let Some(index) = line.find("require(") {
+        quoted(&line[index..])?
+    } else if line.starts_with("import ") || line.starts_with("export ") {
+        match line.rfind(" from ") {
+            Some(index) => quoted(&line[index..])?,
+            None if line.starts_with("import ") => quoted(line)?,
+            None => return None,
+        }
+    } else if line.starts_with('}') && line.contains(" from ") {
+        // The end of a multi-line `import { ... } from '...'`
+        quoted(&line[line.rfind(" from ")?..])?
+    } else {
+        return None;
+    };
+    js_package(specifier)
+}
+
+/// Import paths of a Go file outside the standard library, whose paths have no domain
+fn go_imports(lines: &[&str]) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut in_block = false;
+    for line in lines.iter().map(|l| l.trim()) {
+        let path = if in_block {
+            if line.starts_with(')') {
+                in_block = false;
+                continue;
+            }
+            quoted(line)
+        } else if let Some(rest) = line.strip_prefix("import") {
+            if rest.trim_start().starts_with('(') {
+                in_block = true;
+                continue;
+            }
+            quoted(rest)
+        } else {
+            None
+        };
+        if let Some(path) = path.filter(|p| p.split('/').next().is_some_and(|d| d.contains('.'))) {
+            found.push(path.to_string());
+        }
+    }
+    found
+}
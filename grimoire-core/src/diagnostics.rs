@@ -0,0 +1,290 @@
+//! Self-diagnostics run at startup and by the `server_status` tool
+//!
+//! A misconfigured server used to either refuse to start or quietly serve an
+//! empty library. Instead each thing the server depends on is checked, and
+//! every problem comes with a concrete step to fix it.
+
+use std::{fmt, fs, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    archive,
+    config::{Config, EmbeddingBackend},
+    embeddings::{EMBEDDINGS_FILE, OPENAI_KEY_ENV},
+    feedback::FEEDBACK_FILE,
+    git::{self, GIT_CACHE_FILE},
+    library::Library,
+};
+
+/// File briefly created to check that the patterns directory is writable
+const WRITE_CHECK_FILE: &str = ".grimoire-write-check";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Ok,
+    /// Some features won't work
+    Warning,
+    /// The library can't be served as configured
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Ok => "ok",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// The outcome of one check
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    /// What was checked, e.g. "patterns directory"
+    pub check: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// What to do about it, for warnings and errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remedy: Option<String>,
+}
+
+impl Diagnostic {
+    fn ok(check: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity: Severity::Ok,
+            message: message.into(),
+            remedy: None,
+        }
+    }
+
+    fn problem(
+        check: &'static str,
+        severity: Severity,
+        message: impl Into<String>,
+        remedy: impl Into<String>,
+    ) -> Self {
+        Self {
+            check,
+            severity,
+            message: message.into(),
+            remedy: Some(remedy.into()),
+        }
+    }
+}
+
+/// Name of the check on the patterns directory
+const DIRECTORY_CHECK: &str = "patterns directory";
+
+/// The error keeping the patterns directory, or archive, from being read, if any. Cheap
+/// enough for frequent readiness probes
+pub fn unreadable(dir: &Path) -> Option<Diagnostic> {
+    if archive::is_archive(dir) {
+        return (!dir.is_file()).then(|| {
+            Diagnostic::problem(
+                DIRECTORY_CHECK,
+                Severity::Error,
+                format!("archive {} does not exist", dir.display()),
+                "Point PATTERNS_DIR or patterns_dir at an existing .grimoire archive",
+            )
+        });
+    }
+    let e = fs::read_dir(dir).err()?;
+    let remedy = if dir.exists() {
+        format!("Give the server's user read access, e.g. `chmod u+rx {}`", dir.display())
+    } else {
+        format!(
+            "Create it with `mkdir -p {}`, or point PATTERNS_DIR or patterns_dir at your patterns",
+            dir.display()
+        )
+    };
+    Some(Diagnostic::problem(
+        DIRECTORY_CHECK,
+        Severity::Error,
+        format!("{} can't be read: {}", dir.display(), e),
+        remedy,
+    ))
+}
+
+/// Whether files can be created in the directory
+fn writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(WRITE_CHECK_FILE);
+    fs::write(&probe, b"").and_then(|_| fs::remove_file(&probe))
+}
+
+/// Whether the directory can be read, and written to unless it is an archive
+pub fn check_directory(dir: &Path) -> Vec<Diagnostic> {
+    if let Some(problem) = unreadable(dir) {
+        return vec![problem];
+    }
+    if archive::is_archive(dir) {
+        let message = format!("{} is a read-only archive", dir.display());
+        return vec![Diagnostic::ok(DIRECTORY_CHECK, message)];
+    }
+
+    vec![match writable(dir) {
+        Ok(()) => {
+            Diagnostic::ok(DIRECTORY_CHECK, format!("{} is readable and writable", dir.display()))
+        }
+        Err(e) => Diagnostic::problem(
+            DIRECTORY_CHECK,
+            Severity::Warning,
+            format!(
+                "{} is read-only ({}), so tools that write patterns will fail",
+                dir.display(),
+                e
+            ),
+            format!("Give the server's user write access, e.g. `chmod u+w {}`", dir.display()),
+        ),
+    }]
+}
+
+/// Whether each mounted directory or archive can be read, and each directory written to
+fn check_mounts(config: &Config) -> Vec<Diagnostic> {
+    const CHECK: &str = "mounts";
+    config
+        .mounts
+        .iter()
+        .map(|mount| match unreadable(&mount.path) {
+            Some(problem) => Diagnostic::problem(
+                CHECK,
+                Severity::Warning,
+                format!("{}, so its patterns are missing", problem.message),
+                "Fix the mount's path in the config, or give the server's user read access",
+            ),
+            None if archive::is_archive(&mount.path) => {
+                Diagnostic::ok(CHECK, format!("{} is a read-only archive", mount.path.display()))
+            }
+            None if writable(&mount.path).is_err() => Diagnostic::problem(
+                CHECK,
+                Severity::Warning,
+                format!(
+                    "{} is read-only, so tools can't change its patterns",
+                    mount.path.display()
+                ),
+                format!(
+                    "Give the server's user write access, e.g. `chmod u+w {}`",
+                    mount.path.display()
+                ),
+            ),
+            None => {
+                let namespace = mount.namespace.as_deref().unwrap_or("no namespace");
+                let message = format!("{} is mounted ({})", mount.path.display(), namespace);
+                Diagnostic::ok(CHECK, message)
+            }
+        })
+        .collect()
+}
+
+/// Whether the cache files that exist hold valid JSON
+fn check_caches(dir: &Path) -> Vec<Diagnostic> {
+    const CHECK: &str = "caches";
+    let invalid: Vec<String> = [EMBEDDINGS_FILE, FEEDBACK_FILE, GIT_CACHE_FILE]
+        .into_iter()
+        .map(|file| dir.join(file))
+        .filter(|path| {
+            fs::read_to_string(path)
+                .is_ok_and(|json| serde_json::from_str::<serde_json::Value>(&json).is_err())
+        })
+        .map(|path| path.display().to_string())
+        .collect();
+    if invalid.is_empty() {
+        return vec![Diagnostic::ok(CHECK, "cache files are valid or not created yet")];
+    }
+    vec![Diagnostic::problem(
+        CHECK,
+        Severity::Warning,
+        format!("{} can't be read and are ignored", invalid.join(", ")),
+        "Delete them and restart the server to rebuild them. Feedback votes in feedback.json \
+         are lost unless the file can be fixed by hand",
+    )]
+}
+
+/// Whether git can read the history of a library kept in a repository
+fn check_git(dir: &Path) -> Vec<Diagnostic> {
+    const CHECK: &str = "git";
+    let in_repository = dir.ancestors().any(|d| d.join(".git").exists());
+    match (git::version(), git::head(dir)) {
+        (Some(_), Some(head)) => vec![Diagnostic::ok(
+            CHECK,
+            format!("timestamps come from the git history at {}", head),
+        )],
+        (Some(_), None) if in_repository => vec![Diagnostic::problem(
+            CHECK,
+            Severity::Warning,
+            "the repository has no commits or can't be read, so file times are used",
+            "Commit the patterns, and check `git status` works for the server's user (see \
+             `git config --global --add safe.directory` if the directory has another owner)",
+        )],
+        (None, _) if in_repository => vec![Diagnostic::problem(
+            CHECK,
+            Severity::Warning,
+            "git is not installed, so file times are used and `check` can't run",
+            "Install git and make sure it is on the server's PATH",
+        )],
+        _ => vec![Diagnostic::ok(CHECK, "not a git repository, file times are used")],
+    }
+}
+
+/// Whether every pattern was embedded when semantic search is configured
+fn check_embeddings(config: &Config, library: &Library) -> Vec<Diagnostic> {
+    const CHECK: &str = "embeddings";
+    let Some(embeddings) = &config.embeddings else {
+        return vec![Diagnostic::ok(CHECK, "not configured, semantic search is off")];
+    };
+    if embeddings.backend == EmbeddingBackend::OpenAi {
+        let key_env = embeddings.api_key_env.as_deref().unwrap_or(OPENAI_KEY_ENV);
+        if std::env::var_os(key_env).is_none() {
+            return vec![Diagnostic::problem(
+                CHECK,
+                Severity::Warning,
+                format!("{} is not set, so semantic search is unavailable", key_env),
+                format!("Set {} in the server's environment to the API key", key_env),
+            )];
+        }
+    }
+    if library.embeddings.len() < library.patterns.len() {
+        let remedy = match embeddings.backend {
+            EmbeddingBackend::Ollama => format!(
+                "Check Ollama is running and pull the model with `ollama pull {}`, then restart. \
+                 The server log has the error",
+                embeddings.model
+            ),
+            EmbeddingBackend::OpenAi => format!(
+                "Check the url and that the model '{}' exists for your account, then restart. \
+                 The server log has the error",
+                embeddings.model
+            ),
+        };
+        return vec![Diagnostic::problem(
+            CHECK,
+            Severity::Warning,
+            format!(
+                "model '{}' could not embed the patterns, semantic search is unavailable",
+                embeddings.model
+            ),
+            remedy,
+        )];
+    }
+    vec![Diagnostic::ok(
+        CHECK,
+        format!("{} patterns embedded with '{}'", library.embeddings.len(), embeddings.model),
+    )]
+}
+
+/// Check everything serving `library` from `patterns_dir` depends on
+pub fn run(config: &Config, patterns_dir: &Path, library: &Library) -> Vec<Diagnostic> {
+    let mut diagnostics = check_directory(patterns_dir);
+    let readable = diagnostics.iter().all(|d| d.severity != Severity::Error);
+    if readable && !archive::is_archive(patterns_dir) {
+        diagnostics.extend(check_caches(patterns_dir));
+        diagnostics.extend(check_git(patterns_dir));
+    }
+    diagnostics.extend(check_mounts(config));
+    diagnostics.extend(check_embeddings(config, library));
+    diagnostics
+}
@@ -0,0 +1,216 @@
+//! Vector embeddings of patterns, used for semantic search
+//!
+//! Vectors come from a configurable embedding service and are cached in the
+//! patterns directory keyed by a hash of the embedded text, so restarting the
+//! server only embeds patterns that changed.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::{EmbeddingBackend, EmbeddingsConfig};
+
+/// Where embeddings are cached, relative to the patterns directory
+pub const EMBEDDINGS_FILE: &str = ".grimoire/embeddings.json";
+
+const OLLAMA_URL: &str = "http://localhost:11434";
+const OPENAI_URL: &str = "https://api.openai.com/v1";
+/// Environment variable the OpenAI API key is read from unless configured otherwise
+pub const OPENAI_KEY_ENV: &str = "OPENAI_API_KEY";
+/// Texts sent to the embedding service per request
+const BATCH_SIZE: usize = 32;
+
+/// Something that turns texts into vectors
+pub trait Embedder: std::fmt::Debug + Send + Sync {
+    /// Identifies the model, so cached vectors from another model are not mixed in
+    fn model(&self) -> &str;
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Embedder for the configured backend
+pub fn embedder(config: &EmbeddingsConfig) -> Box<dyn Embedder> {
+    match config.backend {
+        EmbeddingBackend::Ollama => Box::new(Ollama {
+            url: config.url.clone().unwrap_or_else(|| OLLAMA_URL.to_string()),
+            model: config.model.clone(),
+        }),
+        EmbeddingBackend::OpenAi => Box::new(OpenAi {
+            url: config.url.clone().unwrap_or_else(|| OPENAI_URL.to_string()),
+            model: config.model.clone(),
+            api_key: std::env::var(config.api_key_env.as_deref().unwrap_or(OPENAI_KEY_ENV)).ok(),
+        }),
+    }
+}
+
+#[derive(Debug)]
+struct Ollama {
+    url: String,
+    model: String,
+}
+
+impl Embedder for Ollama {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        #[derive(Deserialize)]
+        struct Response {
+            embeddings: Vec<Vec<f32>>,
+        }
+
+        let url = format!("{}/api/embed", self.url.trim_end_matches('/'));
+        let response: Response = ureq::post(&url)
+            .send_json(json!({ "model": self.model, "input": texts }))
+            .with_context(|| format!("Embedding request to {} failed", url))?
+            .body_mut()
+            .read_json()?;
+        Ok(response.embeddings)
+    }
+}
+
+#[derive(Debug)]
+struct OpenAi {
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl Embedder for OpenAi {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        #[derive(Deserialize)]
+        struct Response {
+            data: Vec<Item>,
+        }
+        #[derive(Deserialize)]
+        struct Item {
+            index: usize,
+            embedding: Vec<f32>,
+        }
+
+        let url = format!("{}/embeddings", self.url.trim_end_matches('/'));
+        let mut request = ureq::post(&url);
+        if let Some(key) = &self.api_key {
+            request = request.header("Authorization", &format!("Bearer {}", key));
+        }
+        let mut response: Response = request
+            .send_json(json!({ "model": self.model, "input": texts }))
+            .with_context(|| format!("Embedding request to {} failed", url))?
+            .body_mut()
+            .read_json()?;
+        response.data.sort_by_key(|item| item.index);
+        Ok(response.data.into_iter().map(|item| item.embedding).collect())
+    }
+}
+
+/// Stable FNV-1a hash of the embedded text, used as the cache key
+fn text_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Cached vectors for one embedding model
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EmbeddingStore {
+    #[serde(skip)]
+    path: PathBuf,
+    model: String,
+    vectors: BTreeMap<String, Vec<f32>>,
+}
+
+impl EmbeddingStore {
+    /// Load cached vectors, starting empty if there are none yet
+    pub fn load(patterns_dir: &Path) -> Self {
+        let path = patterns_dir.join(EMBEDDINGS_FILE);
+        let mut store: Self = fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| {
+                serde_json::from_str(&json)
+                    .inspect_err(|e| {
+                        tracing::warn!("Ignoring invalid embeddings file {:?}: {}", path, e)
+                    })
+                    .ok()
+            })
+            .unwrap_or_default();
+        store.path = path;
+        store
+    }
+
+    /// Vectors for the texts, embedding only those not cached yet, in the same order
+    ///
+    /// Vectors no longer used by any text are dropped from the cache.
+    pub fn embed_all(&mut self, embedder: &dyn Embedder, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if self.model != embedder.model() {
+            self.model = embedder.model().to_string();
+            self.vectors.clear();
+        }
+
+        let hashes: Vec<String> = texts.iter().map(|t| text_hash(t)).collect();
+        let missing: Vec<(&String, &String)> = hashes
+            .iter()
+            .zip(texts)
+            .filter(|(hash, _)| !self.vectors.contains_key(*hash))
+            .collect();
+        if !missing.is_empty() {
+            tracing::info!("Embedding {} patterns with {}", missing.len(), self.model);
+        }
+        for batch in missing.chunks(BATCH_SIZE) {
+            let batch_texts: Vec<String> = batch.iter().map(|(_, t)| (*t).clone()).collect();
+            let vectors = embedder.embed(&batch_texts)?;
+            if vectors.len() != batch.len() {
+                anyhow::bail!(
+                    "Embedding service returned {} vectors for {} texts",
+                    vectors.len(),
+                    batch.len()
+                );
+            }
+            for ((hash, _), vector) in batch.iter().zip(vectors) {
+                self.vectors.insert((*hash).clone(), vector);
+            }
+        }
+
+        self.vectors.retain(|hash, _| hashes.contains(hash));
+        if !missing.is_empty() {
+            self.save();
+        }
+        Ok(hashes.iter().map(|h| self.vectors[h].clone()).collect())
+    }
+
+    fn save(&self) {
+        let written = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&self.path, serde_json::to_string(self)?));
+        if let Err(e) = written {
+            tracing::warn!("Failed to cache embeddings in {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Cosine similarity between two vectors, 0.0 when either is empty or zero
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0_f64, 0.0_f64, 0.0_f64);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (*x as f64, *y as f64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
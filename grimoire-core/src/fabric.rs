@@ -0,0 +1,155 @@
+//! Import and export of Fabric patterns
+//!
+//! Fabric (github.com/danielmiessler/fabric) keeps each pattern in a directory
+//! named after it, holding the prompt as `system.md` and sometimes a `user.md`.
+//! Imported patterns become prompt patterns with an optional `{{input}}`
+//! argument at the end, standing for the text Fabric appends to the prompt.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::library::{
+    INITIAL_VERSION, Library, Pattern, PatternMetadata, TemplateArgument, new_id, render_pattern,
+    timestamp_now, validate_pattern_name, without_usage_entries,
+};
+
+const SYSTEM_FILE: &str = "system.md";
+const USER_FILE: &str = "user.md";
+/// Heading the contents of `user.md` are kept under, so they can be exported again
+const USER_HEADING: &str = "## User message";
+/// Tag added to imported patterns
+const FABRIC_TAG: &str = "fabric";
+const INPUT_ARGUMENT: &str = "input";
+const INPUT_PLACEHOLDER: &str = "{{input}}";
+
+/// What an import or export wrote and what it left out
+#[derive(Debug, Default)]
+pub struct Transfer {
+    pub written: Vec<PathBuf>,
+    /// Patterns left out, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+/// The pattern file for a Fabric pattern directory
+fn imported(name: &str, category: &str, system: &str, user: Option<&str>) -> String {
+    let mut body = system.trim().to_string();
+    if let Some(user) = user.map(str::trim).filter(|u| !u.is_empty()) {
+        body.push_str(&format!("\n\n{}\n\n{}", USER_HEADING, user));
+    }
+    body.push_str(&format!("\n\n{}", INPUT_PLACEHOLDER));
+
+    let metadata = PatternMetadata {
+        pattern: name.to_string(),
+        id: Some(new_id()),
+        created: Some(timestamp_now()),
+        updated: Some(timestamp_now()),
+        version: Some(INITIAL_VERSION.to_string()),
+        category: category.to_string(),
+        frameworks: Vec::new(),
+        description: None,
+        author: None,
+        maturity: None,
+        difficulty: None,
+        projects: Vec::new(),
+        tags: vec![FABRIC_TAG.to_string()],
+        aliases: Vec::new(),
+        prerequisites: Vec::new(),
+        see_also: Vec::new(),
+        extends: None,
+        priority: 0,
+        draft: false,
+        publish: false,
+        published_url: None,
+        flashcards: Vec::new(),
+        arguments: vec![TemplateArgument {
+            name: INPUT_ARGUMENT.to_string(),
+            description: Some("Text to run the pattern on".to_string()),
+            required: false,
+        }],
+        extra: BTreeMap::new(),
+    };
+    render_pattern(&metadata, &body)
+}
+
+/// Write a pattern file to `patterns_dir` for each Fabric pattern directory in `fabric_dir`.
+/// Existing files are only replaced with `overwrite`
+pub fn import(
+    fabric_dir: &Path,
+    patterns_dir: &Path,
+    category: &str,
+    overwrite: bool,
+) -> Result<Transfer> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(fabric_dir)
+        .with_context(|| format!("Failed to read Fabric patterns directory {:?}", fabric_dir))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.join(SYSTEM_FILE).is_file())
+        .collect();
+    dirs.sort();
+
+    let mut transfer = Transfer::default();
+    for dir in dirs {
+        let name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if let Err(e) = validate_pattern_name(&name) {
+            transfer.skipped.push((name, e));
+            continue;
+        }
+        let path = patterns_dir.join(format!("{}.md", name));
+        if path.exists() && !overwrite {
+            transfer.skipped.push((name, "a pattern file with this name exists".to_string()));
+            continue;
+        }
+        let system = fs::read_to_string(dir.join(SYSTEM_FILE))
+            .with_context(|| format!("Failed to read {:?}", dir.join(SYSTEM_FILE)))?;
+        let user = fs::read_to_string(dir.join(USER_FILE)).ok();
+        fs::write(&path, imported(&name, category, &system, user.as_deref()))
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        transfer.written.push(path);
+    }
+    Ok(transfer)
+}
+
+/// The `system.md` and `user.md` contents for a pattern: its composed body with embeds
+/// expanded, without the "Used in" section or the trailing `{{input}}` placeholder
+fn exported(library: &Library, pattern: &Pattern) -> (String, Option<String>) {
+    let mut visited = vec![pattern.metadata.pattern.clone()];
+    let body = library.expand_embeds(&library.composed_body(pattern), &mut visited);
+    let body = without_usage_entries(&body);
+    let body = body.trim_end();
+    let body = body.strip_suffix(INPUT_PLACEHOLDER).unwrap_or(body).trim_end();
+    match body.split_once(&format!("\n{}\n", USER_HEADING)) {
+        Some((system, user)) => (
+            format!("{}\n", system.trim_end()),
+            Some(format!("{}\n", user.trim())),
+        ),
+        None => (format!("{}\n", body), None),
+    }
+}
+
+/// Write each pattern to `output` as a Fabric pattern directory
+pub fn export(library: &Library, patterns: &[&Pattern], output: &Path) -> Result<Transfer> {
+    let mut transfer = Transfer::default();
+    for pattern in patterns {
+        let name = &pattern.metadata.pattern;
+        if pattern.metadata.draft {
+            transfer.skipped.push((name.clone(), "still a draft".to_string()));
+            continue;
+        }
+        let dir = output.join(name);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        let (system, user) = exported(library, pattern);
+        let path = dir.join(SYSTEM_FILE);
+        fs::write(&path, system).with_context(|| format!("Failed to write {:?}", path))?;
+        if let Some(user) = user {
+            let path = dir.join(USER_FILE);
+            fs::write(&path, user).with_context(|| format!("Failed to write {:?}", path))?;
+        }
+        transfer.written.push(dir);
+    }
+    Ok(transfer)
+}
@@ -0,0 +1,79 @@
+//! Usefulness feedback reported by agents, used to boost search ranking
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Where feedback is stored, relative to the patterns directory
+pub const FEEDBACK_FILE: &str = ".grimoire/feedback.json";
+
+/// Votes a pattern needs before feedback moves its ranking noticeably
+const PRIOR_VOTES: f64 = 5.0;
+/// Largest relative change feedback can make to a search score
+const MAX_BOOST: f64 = 0.5;
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Votes {
+    pub useful: u32,
+    pub not_useful: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeedbackStore {
+    #[serde(skip)]
+    path: PathBuf,
+    patterns: BTreeMap<String, Votes>,
+}
+
+impl FeedbackStore {
+    /// Load stored feedback, starting empty if there is none yet
+    pub fn load(patterns_dir: &Path) -> Self {
+        let path = patterns_dir.join(FEEDBACK_FILE);
+        let mut store: Self = fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| {
+                serde_json::from_str(&json)
+                    .inspect_err(|e| {
+                        tracing::warn!("Ignoring invalid feedback file {:?}: {}", path, e)
+                    })
+                    .ok()
+            })
+            .unwrap_or_default();
+        store.path = path;
+        store
+    }
+
+    /// Add votes for the given patterns and persist them
+    pub fn record(&mut self, useful: &[String], not_useful: &[String]) -> io::Result<()> {
+        for name in useful {
+            self.patterns.entry(name.clone()).or_default().useful += 1;
+        }
+        for name in not_useful {
+            self.patterns.entry(name.clone()).or_default().not_useful += 1;
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn votes(&self, name: &str) -> Votes {
+        self.patterns.get(name).copied().unwrap_or_default()
+    }
+
+    /// Multiplier applied to a pattern's search score, 1.0 without feedback
+    ///
+    /// The net vote ratio is smoothed towards zero for patterns with few votes,
+    /// and capped so feedback never outweighs relevance entirely.
+    pub fn boost(&self, name: &str) -> f64 {
+        let Votes { useful, not_useful } = self.votes(name);
+        let net = useful as f64 - not_useful as f64;
+        let total = (useful + not_useful) as f64;
+        1.0 + MAX_BOOST * net / (total + PRIOR_VOTES)
+    }
+}
@@ -0,0 +1,136 @@
+//! Flashcards generated from patterns, exported as Anki-importable TSV
+
+use serde::{Deserialize, Serialize};
+
+/// A hand-written question and answer from a pattern's `flashcards:` frontmatter
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Flashcard {
+    pub question: String,
+    pub answer: String,
+}
+
+/// A card ready for export, with markdown text on both sides
+#[derive(Debug)]
+pub struct Card {
+    pub front: String,
+    pub back: String,
+    pub tags: Vec<String>,
+}
+
+/// The first paragraph of prose in the body, skipping headings and code
+pub fn summary(body: &str) -> Option<String> {
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+            if !paragraph.is_empty() {
+                break;
+            }
+        } else if in_fence || trimmed.starts_with('#') {
+            continue;
+        } else if trimmed.is_empty() {
+            if !paragraph.is_empty() {
+                break;
+            }
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    (!paragraph.is_empty()).then(|| paragraph.join(" "))
+}
+
+/// The longest fenced code block in the body, fences included
+pub fn key_code_block(body: &str) -> Option<String> {
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for line in body.lines() {
+        let is_fence = line.trim_start().starts_with("```");
+        match current.as_mut() {
+            Some(block) => {
+                block.push(line);
+                if is_fence {
+                    blocks.extend(current.take());
+                }
+            }
+            None if is_fence => current = Some(vec![line]),
+            None => {}
+        }
+    }
+    blocks
+        .into_iter()
+        .max_by_key(|b| b.iter().map(|l| l.len()).sum::<usize>())
+        .map(|b| b.join("\n"))
+}
+
+/// Cards for a pattern: its custom flashcards, or name/summary and summary/code cards
+pub fn cards_for(
+    name: &str,
+    body: &str,
+    custom: &[Flashcard],
+    tags: &[String],
+) -> Vec<Card> {
+    let card = |front: String, back: String| Card {
+        front,
+        back,
+        tags: tags.to_vec(),
+    };
+    if !custom.is_empty() {
+        return custom
+            .iter()
+            .map(|c| card(c.question.clone(), c.answer.clone()))
+            .collect();
+    }
+
+    let Some(summary) = summary(body) else {
+        return Vec::new();
+    };
+    let mut cards = vec![card(format!("What is the {} pattern?", name), summary.clone())];
+    if let Some(code) = key_code_block(body) {
+        cards.push(card(
+            format!("{}: {}\n\nHow is it implemented?", name, summary),
+            code,
+        ));
+    }
+    cards
+}
+
+/// Escape markdown text into a single-line HTML field
+fn html_field(text: &str) -> String {
+    let mut html = String::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            html.push_str(if in_fence { "</code></pre>" } else { "<pre><code>" });
+            in_fence = !in_fence;
+            continue;
+        }
+        let escaped = line
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('\t', "    ");
+        html.push_str(&escaped);
+        html.push_str("<br>");
+    }
+    if in_fence {
+        html.push_str("</code></pre>");
+    }
+    html.trim_end_matches("<br>").to_string()
+}
+
+/// Render cards as a tab-separated file Anki can import directly
+pub fn to_tsv(cards: &[Card]) -> String {
+    let mut tsv = String::from("#separator:tab\n#html:true\n#tags column:3\n");
+    for card in cards {
+        let tags: Vec<String> = card.tags.iter().map(|t| t.replace(' ', "_")).collect();
+        tsv.push_str(&format!(
+            "{}\t{}\t{}\n",
+            html_field(&card.front),
+            html_field(&card.back),
+            tags.join(" ")
+        ));
+    }
+    tsv
+}
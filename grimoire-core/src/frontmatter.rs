@@ -0,0 +1,209 @@
+//! Targeted edits to hand-written frontmatter
+//!
+//! Serializing the metadata again would lose the comments, key order and
+//! formatting people write by hand. Instead only the entries whose values
+//! changed are replaced, and everything else is kept exactly as written.
+
+use std::path::Path;
+
+use crate::library::{Pattern, PatternMetadata, render_pattern};
+
+/// Keys the metadata also accepts under another name, with the name it is rendered under
+const ALIASES: &[(&str, &str)] = &[("frameworks", "framework"), ("requires", "prerequisites")];
+
+/// The name a key is rendered under
+fn canonical(key: &str) -> &str {
+    ALIASES.iter().find(|(alias, _)| *alias == key).map_or(key, |(_, name)| name)
+}
+
+/// A top-level frontmatter key and the lines holding its value, or a comment or blank line
+struct Entry<'a> {
+    key: Option<&'a str>,
+    lines: Vec<&'a str>,
+}
+
+/// The key a line starts, if it starts one
+fn key_of(line: &str) -> Option<&str> {
+    let (key, _) = line.split_once(':')?;
+    let valid = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then_some(key)
+}
+
+/// Split frontmatter into entries. Indented lines and block sequence items belong to the key above
+fn entries(yaml: &str) -> Vec<Entry<'_>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for line in yaml.lines() {
+        let continues = line.starts_with([' ', '\t']) || line.starts_with('-');
+        match entries.last_mut() {
+            Some(entry) if continues && entry.key.is_some() => entry.lines.push(line),
+            _ => entries.push(Entry {
+                key: key_of(line),
+                lines: vec![line],
+            }),
+        }
+    }
+    entries
+}
+
+/// Each key's lines, in the order `render_pattern` writes them
+fn blocks(rendered: &str) -> Vec<(String, String)> {
+    let yaml = frontmatter(rendered).unwrap_or_default();
+    entries(yaml)
+        .into_iter()
+        .filter_map(|e| Some((e.key?.to_string(), e.lines.join("\n"))))
+        .collect()
+}
+
+/// The frontmatter of a pattern file, between the `---` lines
+fn frontmatter(content: &str) -> Option<&str> {
+    let rest = content.strip_prefix("---\n")?;
+    rest.split_once("\n---\n").map(|(yaml, _)| yaml)
+}
+
+/// A trailing `# comment` on a single-line entry, kept when its value is replaced.
+/// Lines with quotes are skipped, as the `#` may be inside a quoted value
+fn trailing_comment(line: &str) -> Option<&str> {
+    let start = line[..line.find(" #")?].trim_end().len();
+    (!line[..start].contains(['"', '\''])).then(|| &line[start..])
+}
+
+/// Render a pattern file like `render_pattern`, but as an edit of the `original` file:
+/// entries whose values are unchanged keep their comments, order and formatting, changed
+/// entries are replaced in place and new ones are added at the end of the frontmatter
+pub fn update(original: &str, metadata: &PatternMetadata, body: &str) -> String {
+    let rendered = render_pattern(metadata, body);
+    let (Some(yaml), Some(old)) = (frontmatter(original), Pattern::parse(original, Path::new("")))
+    else {
+        return rendered;
+    };
+    let old_blocks = blocks(&render_pattern(&old.metadata, ""));
+    let mut new_blocks = blocks(&rendered);
+
+    let mut lines: Vec<String> = Vec::new();
+    for entry in entries(yaml) {
+        let Some(key) = entry.key.map(canonical) else {
+            lines.extend(entry.lines.iter().map(|l| l.to_string()));
+            continue;
+        };
+        let old = old_blocks.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+        let new = new_blocks
+            .iter()
+            .position(|(k, _)| k == key)
+            .map(|i| new_blocks.remove(i).1);
+        if old == new.as_ref() {
+            lines.extend(entry.lines.iter().map(|l| l.to_string()));
+            continue;
+        }
+        let Some(new) = new else { continue };
+        match (&entry.lines[..], new.contains('\n')) {
+            ([line], false) => {
+                lines.push(format!("{}{}", new, trailing_comment(line).unwrap_or_default()))
+            }
+            _ => lines.push(new),
+        }
+    }
+    lines.extend(new_blocks.into_iter().map(|(_, block)| block));
+
+    format!("---\n{}\n---\n\n{}\n", lines.join("\n"), body.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "\
+---
+# House style: keep the name first
+pattern: axum-errors
+category: rust # the language, not the framework
+framework: axum
+tags:
+  - errors   # most searched
+  - http
+description: \"Map errors # to responses\"
+custom_key: kept
+---
+
+Body
+";
+
+    fn parse(content: &str) -> Pattern {
+        Pattern::parse(content, Path::new("")).unwrap()
+    }
+
+    /// The file after changing the metadata of `original` with `edit`
+    fn edited(original: &str, edit: impl FnOnce(&mut PatternMetadata)) -> String {
+        let mut pattern = parse(original);
+        edit(&mut pattern.metadata);
+        update(original, &pattern.metadata, &pattern.content)
+    }
+
+    #[test]
+    fn unchanged_metadata_keeps_the_file() {
+        assert_eq!(edited(ORIGINAL, |_| {}), ORIGINAL);
+    }
+
+    #[test]
+    fn changed_values_keep_their_comments_and_place() {
+        let updated = edited(ORIGINAL, |m| {
+            m.category = "go".to_string();
+            m.tags = vec!["errors".to_string()];
+        });
+        assert_eq!(
+            updated,
+            ORIGINAL
+                .replace("category: rust #", "category: go #")
+                .replace("tags:\n  - errors   # most searched\n  - http", "tags: [errors]")
+        );
+    }
+
+    #[test]
+    fn hashes_inside_quotes_are_not_comments() {
+        let updated = edited(ORIGINAL, |m| m.description = Some("Map errors".to_string()));
+        assert!(updated.contains("\ndescription: Map errors\n"), "{}", updated);
+    }
+
+    #[test]
+    fn removed_keys_are_dropped_and_new_ones_appended() {
+        let updated = edited(ORIGINAL, |m| {
+            m.frameworks.clear();
+            m.version = Some("1.0.1".to_string());
+        });
+        assert!(!updated.contains("\nframework:"), "{}", updated);
+        assert!(updated.contains("custom_key: kept\nversion: 1.0.1\n---"), "{}", updated);
+        assert_eq!(parse(&updated).metadata.version.as_deref(), Some("1.0.1"));
+    }
+
+    #[test]
+    fn alias_keys_are_not_duplicated() {
+        let original = "---\npattern: a\ncategory: rust\n\
+                        requires: [b]\nframeworks: [axum, sqlx]\n---\n\nBody\n";
+        assert_eq!(edited(original, |_| {}), original);
+        let updated = edited(original, |m| m.prerequisites.push("c".to_string()));
+        assert_eq!(updated, original.replace("requires: [b]", "prerequisites: [b, c]"));
+        assert_eq!(parse(&updated).metadata.prerequisites, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn new_body_replaces_the_old() {
+        let mut pattern = parse(ORIGINAL);
+        pattern.metadata.category = "go".to_string();
+        let updated = update(ORIGINAL, &pattern.metadata, "New body\n\n");
+        assert!(updated.ends_with("---\n\nNew body\n"), "{}", updated);
+    }
+
+    #[test]
+    fn files_without_frontmatter_are_rendered_from_scratch() {
+        let metadata = parse(ORIGINAL).metadata;
+        assert_eq!(
+            update("no frontmatter\n", &metadata, "Body"),
+            render_pattern(&metadata, "Body")
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Pattern timestamps derived from git history
+//!
+//! File times are unreliable in a cloned library (every file looks created at
+//! clone time), so when the patterns directory is a git repository the first
+//! and last commit touching each file are used instead. The history walk is
+//! cached per `HEAD` commit, so it only runs again after new commits.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// Where the timestamp cache is stored, relative to the patterns directory
+pub const GIT_CACHE_FILE: &str = ".grimoire/git-timestamps.json";
+
+/// Commit times of a file, in seconds since the Unix epoch
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FileDates {
+    pub created: u64,
+    pub updated: u64,
+}
+
+impl FileDates {
+    pub fn created(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created)
+    }
+
+    pub fn updated(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.updated)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    head: String,
+    /// File paths relative to the patterns directory
+    files: BTreeMap<String, FileDates>,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Walk the history once, newest commit first, recording the first and last commit of each file
+fn walk_history(dir: &Path) -> Option<BTreeMap<String, FileDates>> {
+    let log = git(
+        dir,
+        &["log", "--relative", "--no-renames", "--format=%x00%ct", "--name-only", "--", "."],
+    )?;
+
+    let mut files: BTreeMap<String, FileDates> = BTreeMap::new();
+    for commit in log.split('\0').filter(|c| !c.trim().is_empty()) {
+        let mut lines = commit.lines();
+        let Some(time) = lines.next().and_then(|t| t.trim().parse::<u64>().ok()) else {
+            continue;
+        };
+        for file in lines.map(str::trim).filter(|l| !l.is_empty()) {
+            files
+                .entry(file.to_string())
+                .and_modify(|dates| dates.created = time)
+                .or_insert(FileDates {
+                    created: time,
+                    updated: time,
+                });
+        }
+    }
+    Some(files)
+}
+
+/// The installed git's version, `None` if git can't be run
+pub fn version() -> Option<String> {
+    let output = Command::new("git").arg("--version").output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The abbreviated commit checked out in the repository holding `dir`, `None` outside a
+/// repository or before its first commit
+pub fn head(dir: &Path) -> Option<String> {
+    git(dir, &["rev-parse", "--short", "HEAD"]).map(|head| head.trim().to_string())
+}
+
+/// The pattern files directly in `dir` as of revision `rev`, with their contents
+pub fn read_revision(dir: &Path, rev: &str, config: &Config) -> Result<Vec<(String, String)>> {
+    let commit = format!("{}^{{commit}}", rev);
+    if git(dir, &["rev-parse", "--verify", "--quiet", &commit]).is_none() {
+        anyhow::bail!("'{}' is not a revision of the git repository at {:?}", rev, dir);
+    }
+    let listing = git(dir, &["ls-tree", "--name-only", rev, "--", "."])
+        .with_context(|| format!("Failed to list the files at {}", rev))?;
+    let config = config.with_ignore_file(dir);
+    listing
+        .lines()
+        .filter(|file| config.is_pattern_file(Path::new(file)))
+        .map(|file| {
+            let content = git(dir, &["show", &format!("{}:./{}", rev, file)])
+                .with_context(|| format!("Failed to read {} at {}", file, rev))?;
+            Ok((file.to_string(), content))
+        })
+        .collect()
+}
+
+/// Commit dates of every file in the patterns directory, `None` unless it is inside a git repository
+pub fn file_dates(patterns_dir: &Path) -> Option<BTreeMap<String, FileDates>> {
+    let head = git(patterns_dir, &["rev-parse", "HEAD"])?.trim().to_string();
+    let cache_path = patterns_dir.join(GIT_CACHE_FILE);
+    if let Some(cache) = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|json| serde_json::from_str::<Cache>(&json).ok())
+        .filter(|c| c.head == head)
+    {
+        return Some(cache.files);
+    }
+
+    tracing::info!("Reading pattern timestamps from git history at {}", head);
+    let cache = Cache {
+        head,
+        files: walk_history(patterns_dir)?,
+    };
+    let written = cache_path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&cache_path, serde_json::to_string(&cache)?));
+    if let Err(e) = written {
+        tracing::warn!("Failed to cache git timestamps in {:?}: {}", cache_path, e);
+    }
+    Some(cache.files)
+}
@@ -0,0 +1,51 @@
+//! `${VAR}` and `${VAR:-default}` placeholders in pattern bodies
+//!
+//! Placeholders are expanded when a pattern is retrieved, so shared patterns can
+//! refer to environment-specific values like a registry URL or default region.
+//! Only variables on the configured allow-list are ever read.
+//!
+//! Prompt patterns also take `{{name}}` arguments, filled in from the caller's values.
+
+use std::{collections::BTreeMap, sync::LazyLock};
+
+use regex::{Captures, Regex};
+
+static PLACEHOLDER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").expect("valid placeholder regex")
+});
+
+static ARGUMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}").expect("valid argument regex")
+});
+
+/// Expand placeholders for allowed variables, leaving everything else as written
+///
+/// An unset variable falls back to its default, or stays as written without one.
+pub fn expand(body: &str, allowed: &[String]) -> String {
+    if allowed.is_empty() {
+        return body.to_string();
+    }
+    PLACEHOLDER_RE
+        .replace_all(body, |c: &Captures| {
+            let name = &c[1];
+            if !allowed.iter().any(|a| a == name) {
+                return c[0].to_string();
+            }
+            match (std::env::var(name), c.get(2)) {
+                (Ok(value), _) if !value.is_empty() => value,
+                (_, Some(default)) => default.as_str().to_string(),
+                (Ok(value), None) => value,
+                (Err(_), None) => c[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Replace `{{name}}` with the value given for `name`, leaving arguments without one as written
+pub fn fill(body: &str, values: &BTreeMap<String, String>) -> String {
+    ARGUMENT_RE
+        .replace_all(body, |c: &Captures| {
+            values.get(&c[1]).cloned().unwrap_or_else(|| c[0].to_string())
+        })
+        .into_owned()
+}
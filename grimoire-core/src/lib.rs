@@ -0,0 +1,26 @@
+//! Pattern loading, indexing, searching and validation for grimoire pattern libraries
+//!
+//! The MCP server is a thin layer over this crate, so other front ends (a CLI,
+//! an editor plugin, a web UI) can load and search the same libraries.
+
+pub mod archive;
+pub mod check;
+pub mod config;
+pub mod dates;
+pub mod dependencies;
+pub mod diagnostics;
+pub mod embeddings;
+pub mod fabric;
+pub mod feedback;
+pub mod flashcards;
+pub mod frontmatter;
+pub mod git;
+pub mod interpolate;
+pub mod library;
+pub mod links;
+pub mod publish;
+pub mod quality;
+pub mod query;
+pub mod repair;
+pub mod roots;
+pub mod search;
@@ -0,0 +1,1166 @@
+//! Pattern files and the in-memory library built from them
+//!
+//! A pattern is a markdown file (or MDX or plain text) with YAML frontmatter. The [`Library`] holds
+//! every loaded pattern together with the search indexes and embeddings built
+//! over them, and resolves references, embeds and `extends:` chains between them.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive,
+    config::Config,
+    dependencies,
+    embeddings::{EmbeddingStore, Embedder},
+    flashcards::Flashcard,
+    git, interpolate,
+    links::{self, Link},
+    search::{self, Fields, SearchIndex},
+};
+
+/// The loaded patterns and everything built from them
+///
+/// Servers hand out snapshots, so a reload can build a new library alongside
+/// the old one and searches never see a partially built index.
+#[derive(Debug, Default)]
+pub struct Library {
+    pub patterns: Vec<Pattern>,
+    pub index: SearchIndex,
+    /// Index over fenced code blocks only, for code_only searches
+    pub code_index: SearchIndex,
+    /// Vector for each loaded pattern, empty when embeddings are unavailable
+    pub embeddings: Vec<Vec<f32>>,
+    /// Patterns (by index) whose code blocks import each dependency
+    pub dependencies: BTreeMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pattern {
+    pub metadata: PatternMetadata,
+    pub content: String,
+    pub filepath: PathBuf,
+    #[serde(default)]
+    pub created: Option<SystemTime>,
+    #[serde(default)]
+    pub updated: Option<SystemTime>,
+    /// Loaded from a read-only archive rather than a file on disk
+    #[serde(default)]
+    pub read_only: bool,
+    /// Markup the file is written in, detected from its extension
+    #[serde(default)]
+    pub format: PatternFormat,
+    /// Namespace of the directory or archive the pattern was loaded from, if it has one
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+/// Markup of a pattern file, for handling that differs between formats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternFormat {
+    #[default]
+    Markdown,
+    /// Markdown with JSX components
+    Mdx,
+    /// Plain text, with frontmatter like any other pattern
+    Text,
+}
+
+impl PatternFormat {
+    /// Every extension a pattern file can have, without the dot, and its format
+    pub const EXTENSIONS: &[(&str, PatternFormat)] = &[
+        ("md", PatternFormat::Markdown),
+        ("markdown", PatternFormat::Markdown),
+        ("mdx", PatternFormat::Mdx),
+        ("txt", PatternFormat::Text),
+    ];
+
+    /// The format of files with this extension, with or without the dot, in any case
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let extension = extension.trim_start_matches('.');
+        Self::EXTENSIONS
+            .iter()
+            .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+            .map(|(_, format)| *format)
+    }
+
+    /// The format of a file, from its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_extension(&path.extension()?.to_string_lossy())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatternMetadata {
+    pub pattern: String,
+    /// ULID assigned when the pattern is created, unique even across machines that sync patterns
+    #[serde(default)]
+    pub id: Option<String>,
+    /// When the pattern was created, an RFC 3339 timestamp or a date, ahead of git and file times
+    #[serde(default)]
+    pub created: Option<String>,
+    /// When the pattern was last written, bumped by every tool that writes it
+    #[serde(default)]
+    pub updated: Option<String>,
+    /// Semantic version as MAJOR.MINOR.PATCH, bumped each time create_pattern overwrites the
+    /// pattern
+    #[serde(default)]
+    pub version: Option<String>,
+    pub category: String,
+    /// Frameworks the pattern is tied to, written as one name or a list
+    #[serde(rename = "framework", alias = "frameworks", default, deserialize_with = "one_or_many")]
+    pub frameworks: Vec<String>,
+    /// One-line summary of what the pattern is for
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Who wrote the pattern
+    #[serde(default)]
+    pub author: Option<String>,
+    /// How proven the pattern is, e.g. "draft", "tested" or "battle-tested"
+    #[serde(default)]
+    pub maturity: Option<String>,
+    /// How hard the pattern is to apply, e.g. "beginner", "intermediate" or "advanced"
+    #[serde(default)]
+    pub difficulty: Option<String>,
+    #[serde(default)]
+    pub projects: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Patterns to read before this one, in any order, also written as `requires`
+    #[serde(default, alias = "requires")]
+    pub prerequisites: Vec<String>,
+    /// Patterns worth reading alongside this one, not followed when collecting prerequisites
+    #[serde(default)]
+    pub see_also: Vec<String>,
+    /// Base pattern whose sections this one inherits, overriding or adding to them
+    #[serde(default)]
+    pub extends: Option<String>,
+    /// Curator-set ranking boost, higher values rank canonical patterns nearer the top
+    #[serde(default)]
+    pub priority: i64,
+    /// Unpolished pattern, e.g. a capture_note scratch pad, not yet promoted to a full pattern
+    #[serde(default)]
+    pub draft: bool,
+    /// Included when exporting the public patterns with `export-published`
+    #[serde(default)]
+    pub publish: bool,
+    /// Canonical public URL of the pattern, used to link to it from other published patterns
+    #[serde(default)]
+    pub published_url: Option<String>,
+    /// Hand-written study cards, used instead of generated ones when exporting flashcards
+    #[serde(default)]
+    pub flashcards: Vec<Flashcard>,
+    /// Values a prompt pattern takes, filled in for `{{name}}` in its body
+    #[serde(default)]
+    pub arguments: Vec<TemplateArgument>,
+    /// Any other frontmatter keys, kept so they stay searchable and survive rewrites
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// An argument declared in a pattern's frontmatter
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TemplateArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+}
+
+/// The scalars in a frontmatter value, joined into searchable text
+fn yaml_text(value: &serde_yaml::Value) -> String {
+    use serde_yaml::Value;
+    let join = |items: Vec<String>| {
+        items
+            .into_iter()
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Sequence(items) => join(items.iter().map(yaml_text).collect()),
+        Value::Mapping(map) => join(map.values().map(yaml_text).collect()),
+        Value::Tagged(tagged) => yaml_text(&tagged.value),
+    }
+}
+
+/// Canonical form of a taxonomy value (category, framework or tag), so "Rust " matches "rust"
+pub fn normalize(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+/// Accept either a single string or a list of strings
+pub fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(value)) => vec![value],
+        Some(OneOrMany::Many(values)) => values,
+        None => Vec::new(),
+    })
+}
+
+/// Normalize a list of taxonomy values, dropping blanks and duplicates
+pub fn normalize_all(values: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(values.len());
+    for value in values.iter().map(|v| normalize(v)) {
+        if !value.is_empty() && !normalized.contains(&value) {
+            normalized.push(value);
+        }
+    }
+    normalized
+}
+
+impl PatternMetadata {
+    /// Normalize the taxonomy fields in place
+    pub fn normalize(&mut self) {
+        self.category = normalize(&self.category);
+        self.frameworks = normalize_all(&self.frameworks);
+        self.maturity = self.maturity.as_deref().map(normalize).filter(|m| !m.is_empty());
+        self.difficulty = self.difficulty.as_deref().map(normalize).filter(|d| !d.is_empty());
+        self.tags = normalize_all(&self.tags);
+    }
+
+    /// The `created` date, if it is set and valid
+    pub fn created_time(&self) -> Option<SystemTime> {
+        self.created.as_deref().and_then(parse_timestamp)
+    }
+
+    /// The `updated` date, if it is set and valid
+    pub fn updated_time(&self) -> Option<SystemTime> {
+        self.updated.as_deref().and_then(parse_timestamp)
+    }
+}
+
+/// The current time as written to `created` and `updated`, e.g. `2025-03-14T09:26:53Z`
+pub fn timestamp_now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// The time a `created` or `updated` value stands for: an RFC 3339 timestamp, or a date
+/// meaning its start in UTC
+fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.into());
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().into())
+}
+
+impl Pattern {
+    /// Parse a pattern from the content of its file
+    pub fn parse(content: &str, path: &Path) -> Option<Pattern> {
+        let rest = content.strip_prefix("---\n")?;
+        let mut parts = rest.splitn(2, "\n---\n");
+        let yaml = parts.next()?;
+        let body = parts.next()?.trim();
+        let mut metadata: PatternMetadata = serde_yaml::from_str(yaml).ok()?;
+        metadata.normalize();
+
+        Some(Pattern {
+            created: metadata.created_time(),
+            updated: metadata.updated_time(),
+            metadata,
+            content: body.to_string(),
+            filepath: path.to_path_buf(),
+            read_only: false,
+            format: PatternFormat::from_path(path).unwrap_or_default(),
+            namespace: None,
+        })
+    }
+
+    /// Why `parse` rejects `content`, `None` when it doesn't
+    pub fn parse_error(content: &str) -> Option<String> {
+        let Some(rest) = content.strip_prefix("---\n") else {
+            return Some("the file doesn't start with a `---` frontmatter line".into());
+        };
+        let Some((yaml, _)) = rest.split_once("\n---\n") else {
+            return Some("the frontmatter has no closing `---` line".into());
+        };
+        serde_yaml::from_str::<PatternMetadata>(yaml)
+            .err()
+            .map(|e| format!("invalid frontmatter: {}", e))
+    }
+
+    /// Read and parse a pattern file, `None` if it is unreadable or has no valid frontmatter
+    pub fn load(path: &Path) -> Option<Pattern> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut pattern = Pattern::parse(&content, path)?;
+        let file_metadata = fs::metadata(path).ok();
+        // The frontmatter's dates come first, then the file's
+        pattern.updated =
+            pattern.updated.or_else(|| file_metadata.as_ref().and_then(|m| m.modified().ok()));
+        // Not every filesystem records creation time
+        pattern.created = pattern
+            .created
+            .or_else(|| file_metadata.and_then(|m| m.created().ok()))
+            .or(pattern.updated);
+        Some(pattern)
+    }
+
+    /// The body with its `{{name}}` arguments filled in from `values`. Optional arguments
+    /// left out are dropped, missing required ones are an error naming them
+    pub fn fill_arguments(
+        &self,
+        body: &str,
+        values: &BTreeMap<String, String>,
+    ) -> Result<String, String> {
+        let missing: Vec<&str> = self
+            .metadata
+            .arguments
+            .iter()
+            .filter(|a| a.required && !values.contains_key(&a.name))
+            .map(|a| a.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("Missing required arguments: {}", missing.join(", ")));
+        }
+
+        let mut values = values.clone();
+        for argument in &self.metadata.arguments {
+            values.entry(argument.name.clone()).or_default();
+        }
+        Ok(interpolate::fill(body, &values))
+    }
+
+    /// The name prefixed with the namespace, like `team/axum-errors`, for telling apart
+    /// patterns of the same name
+    pub fn qualified_name(&self) -> Cow<'_, str> {
+        match &self.namespace {
+            Some(namespace) => Cow::Owned(format!("{}/{}", namespace, self.metadata.pattern)),
+            None => Cow::Borrowed(&self.metadata.pattern),
+        }
+    }
+
+    /// Whether the pattern was loaded under this namespace
+    pub fn in_namespace(&self, namespace: &str) -> bool {
+        self.namespace
+            .as_deref()
+            .is_some_and(|n| n.eq_ignore_ascii_case(namespace.trim_end_matches('/')))
+    }
+
+    /// Whether the pattern is known by this name, either directly or through an alias
+    pub fn answers_to(&self, name: &str) -> bool {
+        self.metadata.pattern.eq_ignore_ascii_case(name)
+            || self.metadata.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the pattern's author is, or has a name containing, `author`, ignoring case
+    pub fn by_author(&self, author: &str) -> bool {
+        let author = author.trim().to_lowercase();
+        self.metadata
+            .author
+            .as_deref()
+            .is_some_and(|a| a.to_lowercase().contains(&author))
+    }
+
+    /// Whether the pattern was used in the project
+    pub fn used_in(&self, project: &str) -> bool {
+        let project = normalize(project);
+        self.metadata.projects.iter().any(|p| normalize(p) == project)
+    }
+
+    /// Searchable frontmatter besides the name and tags: framework, description, author,
+    /// maturity, difficulty, projects and custom keys
+    pub fn metadata_fields(&self) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        if !self.metadata.frameworks.is_empty() {
+            fields.push(("framework".to_string(), self.metadata.frameworks.join(" ")));
+        }
+        if let Some(description) = &self.metadata.description {
+            fields.push(("description".to_string(), description.clone()));
+        }
+        if let Some(author) = &self.metadata.author {
+            fields.push(("author".to_string(), author.clone()));
+        }
+        if let Some(maturity) = &self.metadata.maturity {
+            fields.push(("maturity".to_string(), maturity.clone()));
+        }
+        if let Some(difficulty) = &self.metadata.difficulty {
+            fields.push(("difficulty".to_string(), difficulty.clone()));
+        }
+        if !self.metadata.projects.is_empty() {
+            fields.push(("projects".to_string(), self.metadata.projects.join(" ")));
+        }
+        for (key, value) in &self.metadata.extra {
+            let text = yaml_text(value);
+            if !text.is_empty() {
+                fields.push((key.clone(), text));
+            }
+        }
+        fields
+    }
+
+    /// The text of every searchable field, labelled by field name
+    pub fn search_fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            (
+                "name".to_string(),
+                format!("{} {}", self.metadata.pattern, self.metadata.aliases.join(" ")),
+            ),
+            ("tags".to_string(), self.metadata.tags.join(" ")),
+        ];
+        fields.extend(self.metadata_fields());
+        fields.push(("body".to_string(), self.content.clone()));
+        fields
+    }
+
+    /// Whether both patterns describe the same logical pattern (same name, or one aliases the
+    /// other, in the same namespace)
+    pub fn is_same_as(&self, other: &Pattern) -> bool {
+        let same_name =
+            self.answers_to(&other.metadata.pattern) || other.answers_to(&self.metadata.pattern);
+        self.namespace == other.namespace && same_name
+    }
+}
+
+/// The namespace and name of a `namespace/name` reference, or no namespace for a plain name
+pub fn split_namespace(name: &str) -> (Option<&str>, &str) {
+    match name.split_once('/') {
+        Some((namespace, name)) => (Some(namespace), name),
+        None => (None, name),
+    }
+}
+
+/// A new collision-resistant pattern ID
+pub fn new_id() -> String {
+    ulid::Ulid::new().to_string()
+}
+
+/// An ID for an existing pattern, from its creation time and its file's place in the
+/// library (namespace and file name), so it comes out the same on every machine and checkout
+/// that agree on those
+pub fn derived_id(pattern: &Pattern) -> String {
+    let created_ms = pattern
+        .created
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as u64);
+    let file_name = pattern.filepath.file_name().unwrap_or_default().to_string_lossy();
+    let key = format!("{}/{}", pattern.namespace.as_deref().unwrap_or_default(), file_name);
+    let [high, low] = [0u8, 1].map(|seed| fnv1a(seed, key.as_bytes()));
+    ulid::Ulid::from_parts(created_ms, (u128::from(high) << 64) | u128::from(low)).to_string()
+}
+
+/// 64-bit FNV-1a of a seed byte followed by `bytes`. Unlike `DefaultHasher`, its output is
+/// fixed across platforms and Rust releases
+fn fnv1a(seed: u8, bytes: &[u8]) -> u64 {
+    std::iter::once(seed).chain(bytes.iter().copied()).fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// A short fingerprint of some content, stable across runs, to tell when it changed
+pub fn content_hash(content: &str) -> String {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// An HTML comment naming where a chunk of content came from, for citing and refreshing it
+pub fn provenance_marker(pattern: &Pattern, section: Option<&str>, content: &str) -> String {
+    let id = pattern.metadata.id.clone().unwrap_or_else(|| derived_id(pattern));
+    let section = section.map(|s| format!(" section=\"{}\"", s)).unwrap_or_default();
+    format!(
+        "<!-- grimoire:source pattern=\"{}\" id=\"{}\"{} hash=\"{}\" -->",
+        pattern.metadata.pattern,
+        id,
+        section,
+        content_hash(content)
+    )
+}
+
+/// Closes the chunk opened by a `provenance_marker`
+pub const PROVENANCE_END: &str = "<!-- /grimoire:source -->";
+
+/// What clashing patterns share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConflictKind {
+    Name,
+    Id,
+}
+
+impl std::fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConflictKind::Name => "name",
+            ConflictKind::Id => "id",
+        })
+    }
+}
+
+/// Patterns sharing a name or ID, typically created separately on machines that sync through git
+#[derive(Debug)]
+pub struct Conflict<'a> {
+    pub kind: ConflictKind,
+    /// The shared name or ID
+    pub key: String,
+    /// The clashing patterns, the one to keep first
+    pub patterns: Vec<&'a Pattern>,
+}
+
+/// Heading of the section listing the projects a pattern was used in
+const USED_IN_HEADING: &str = "## Used in";
+/// Version of new patterns, and the one patterns without a `version` are taken to have
+pub const INITIAL_VERSION: &str = "1.0.0";
+/// How deep `![[...]]` embeds are expanded inside each other
+const MAX_EMBED_DEPTH: usize = 4;
+
+/// Render a pattern file from its metadata and body
+pub fn render_pattern(metadata: &PatternMetadata, body: &str) -> String {
+    let list = |key: &str, values: &[String]| {
+        if values.is_empty() {
+            String::new()
+        } else {
+            format!("{}: [{}]\n", key, values.join(", "))
+        }
+    };
+    // A single framework stays a plain value, as most patterns have one
+    let framework = match metadata.frameworks.as_slice() {
+        [] => String::new(),
+        [framework] => format!("framework: {}\n", framework),
+        frameworks => list("framework", frameworks),
+    };
+    let priority = if metadata.priority == 0 {
+        String::new()
+    } else {
+        format!("priority: {}\n", metadata.priority)
+    };
+    let flashcards = if metadata.flashcards.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "flashcards:\n{}",
+            serde_yaml::to_string(&metadata.flashcards).unwrap_or_default()
+        )
+    };
+
+    let mut out = format!("---\npattern: {}\n", metadata.pattern);
+    if let Some(id) = &metadata.id {
+        out.push_str(&format!("id: {}\n", id));
+    }
+    if let Some(created) = &metadata.created {
+        out.push_str(&format!("created: {}\n", created));
+    }
+    if let Some(updated) = &metadata.updated {
+        out.push_str(&format!("updated: {}\n", updated));
+    }
+    if let Some(version) = &metadata.version {
+        out.push_str(&format!("version: {}\n", version));
+    }
+    out.push_str(&format!("category: {}\n", metadata.category));
+    out.push_str(&framework);
+    if let Some(description) = &metadata.description {
+        // Quoted when needed, as summaries often contain colons
+        let value = serde_yaml::to_string(description).unwrap_or_default();
+        out.push_str(&format!("description: {}\n", value.trim_end()));
+    }
+    if let Some(author) = &metadata.author {
+        let value = serde_yaml::to_string(author).unwrap_or_default();
+        out.push_str(&format!("author: {}\n", value.trim_end()));
+    }
+    if let Some(maturity) = &metadata.maturity {
+        out.push_str(&format!("maturity: {}\n", maturity));
+    }
+    if let Some(difficulty) = &metadata.difficulty {
+        out.push_str(&format!("difficulty: {}\n", difficulty));
+    }
+    out.push_str(&list("projects", &metadata.projects));
+    out.push_str(&list("tags", &metadata.tags));
+    out.push_str(&list("aliases", &metadata.aliases));
+    out.push_str(&list("prerequisites", &metadata.prerequisites));
+    out.push_str(&list("see_also", &metadata.see_also));
+    if let Some(extends) = &metadata.extends {
+        out.push_str(&format!("extends: {}\n", extends));
+    }
+    out.push_str(&priority);
+    if metadata.draft {
+        out.push_str("draft: true\n");
+    }
+    if metadata.publish {
+        out.push_str("publish: true\n");
+    }
+    if let Some(url) = &metadata.published_url {
+        out.push_str(&format!("published_url: {}\n", url));
+    }
+    if !metadata.extra.is_empty() {
+        out.push_str(&serde_yaml::to_string(&metadata.extra).unwrap_or_default());
+    }
+    out.push_str(&flashcards);
+    if !metadata.arguments.is_empty() {
+        out.push_str(&format!(
+            "arguments:\n{}",
+            serde_yaml::to_string(&metadata.arguments).unwrap_or_default()
+        ));
+    }
+    out.push_str(&format!("---\n\n{}\n", body.trim_end()));
+    out
+}
+
+/// The body without its "Used in" section, which names the projects that used the pattern
+pub fn without_usage_entries(body: &str) -> String {
+    let lines: Vec<&str> = body.trim_end().lines().collect();
+    let Some(start) = lines.iter().position(|l| l.trim() == USED_IN_HEADING) else {
+        return body.to_string();
+    };
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| l.starts_with('#'))
+        .map_or(lines.len(), |i| start + 1 + i);
+    let kept: Vec<&str> = lines[..start].iter().chain(&lines[end..]).copied().collect();
+    kept.join("\n").trim_end().to_string()
+}
+
+/// Append a dated entry to the body's "Used in" section, creating the section if needed
+pub fn add_usage_entry(body: &str, date: NaiveDate, project: &str, note: Option<&str>) -> String {
+    let entry = match note {
+        Some(note) => format!("- {}: {} - {}", date, project, note),
+        None => format!("- {}: {}", date, project),
+    };
+
+    let mut lines: Vec<&str> = body.trim_end().lines().collect();
+    let Some(start) = lines.iter().position(|l| l.trim() == USED_IN_HEADING) else {
+        return format!("{}\n\n{}\n\n{}", body.trim_end(), USED_IN_HEADING, entry);
+    };
+
+    // Insert after the last entry of the section, before the next heading
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| l.starts_with('#'))
+        .map_or(lines.len(), |i| start + 1 + i);
+    let insert_at = lines[start + 1..end]
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(start + 1, |i| start + 2 + i);
+    if insert_at == start + 1 {
+        lines.insert(insert_at, "");
+        lines.insert(insert_at + 1, &entry);
+    } else {
+        lines.insert(insert_at, &entry);
+    }
+    lines.join("\n")
+}
+
+impl Library {
+    /// Load every pattern and build the search indexes and embeddings over them
+    pub fn load(config: &Config, patterns_dir: &Path, embedder: Option<&dyn Embedder>) -> Self {
+        Self::load_dirs(config, patterns_dir, &[patterns_dir.to_path_buf()], embedder)
+    }
+
+    /// Like `load`, but reading the patterns from each of `dirs` (directories or archives)
+    /// instead of only the patterns directory, which still holds the embeddings cache
+    pub fn load_dirs(
+        config: &Config,
+        patterns_dir: &Path,
+        dirs: &[PathBuf],
+        embedder: Option<&dyn Embedder>,
+    ) -> Self {
+        let patterns = Self::load_patterns(config, dirs);
+        let embeddings = embedder
+            .map(|e| Self::build_embeddings(e, patterns_dir, &patterns))
+            .unwrap_or_default();
+        let library = Self {
+            embeddings,
+            ..Self::from_patterns(patterns)
+        };
+        for conflict in library.conflicts() {
+            tracing::warn!(
+                "{} patterns share the {} '{}': {:?}",
+                conflict.patterns.len(),
+                conflict.kind,
+                conflict.key,
+                conflict.patterns.iter().map(|p| &p.filepath).collect::<Vec<_>>()
+            );
+        }
+        library
+    }
+
+    /// A library of already parsed patterns, with its indexes but no embeddings
+    pub fn from_patterns(patterns: Vec<Pattern>) -> Self {
+        Self {
+            index: Self::build_index(&patterns),
+            code_index: Self::build_code_index(&patterns),
+            dependencies: Self::build_dependencies(&patterns),
+            embeddings: Vec::new(),
+            patterns,
+        }
+    }
+
+    /// Check the indexes and embeddings line up with the patterns, so a broken build is never swapped in
+    pub fn validate(&self) -> Result<(), String> {
+        let count = self.patterns.len();
+        for (name, indexed) in [
+            ("search index", self.index.doc_count()),
+            ("code index", self.code_index.doc_count()),
+        ] {
+            if indexed != count {
+                return Err(format!("{} covers {} of {} patterns", name, indexed, count));
+            }
+        }
+        if !self.embeddings.is_empty() && self.embeddings.len() != count {
+            return Err(format!(
+                "{} embeddings for {} patterns",
+                self.embeddings.len(),
+                count
+            ));
+        }
+        Ok(())
+    }
+
+    /// Groups of patterns sharing a name within a namespace, or an ID. The pattern to keep
+    /// comes first in each: read-only ones, which can't be changed, then the oldest
+    pub fn conflicts(&self) -> Vec<Conflict<'_>> {
+        let mut groups: BTreeMap<(ConflictKind, Cow<str>), Vec<&Pattern>> = BTreeMap::new();
+        for pattern in &self.patterns {
+            groups
+                .entry((ConflictKind::Name, pattern.qualified_name()))
+                .or_default()
+                .push(pattern);
+            if let Some(id) = &pattern.metadata.id {
+                groups.entry((ConflictKind::Id, Cow::Borrowed(id))).or_default().push(pattern);
+            }
+        }
+        groups
+            .into_iter()
+            .filter(|(_, patterns)| patterns.len() > 1)
+            .map(|((kind, key), mut patterns)| {
+                patterns.sort_by_key(|p| (!p.read_only, p.created.is_none(), p.created));
+                Conflict {
+                    kind,
+                    key: key.to_string(),
+                    patterns,
+                }
+            })
+            .collect()
+    }
+
+    /// Names of the patterns added, removed and changed in `newer`
+    pub fn diff(&self, newer: &Library) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let rendered = |library: &Library| -> BTreeMap<String, String> {
+            library
+                .patterns
+                .iter()
+                .map(|p| {
+                    (
+                        p.metadata.pattern.clone(),
+                        render_pattern(&p.metadata, &p.content),
+                    )
+                })
+                .collect()
+        };
+        let (old, new) = (rendered(self), rendered(newer));
+        let added = new.keys().filter(|n| !old.contains_key(*n)).cloned().collect();
+        let removed = old.keys().filter(|n| !new.contains_key(*n)).cloned().collect();
+        let changed = new
+            .iter()
+            .filter(|(n, text)| old.get(*n).is_some_and(|old| old != *text))
+            .map(|(n, _)| n.clone())
+            .collect();
+        (added, removed, changed)
+    }
+
+    /// The indexed name of a dependency and the patterns importing it, matching names
+    /// case-insensitively and with `-` and `_` alike
+    pub fn dependency(&self, name: &str) -> Option<(&str, Vec<&Pattern>)> {
+        let name = dependencies::normalize(name);
+        let (key, indexes) = self
+            .dependencies
+            .iter()
+            .find(|(key, _)| dependencies::normalize(key) == name)?;
+        Some((key, indexes.iter().map(|&i| &self.patterns[i]).collect()))
+    }
+
+    /// Find a pattern by exact name, then case-insensitive name, then alias. A
+    /// `namespace/name` only finds patterns in that namespace, a plain name the first
+    /// match in any, the patterns directory's coming first
+    pub fn find_pattern(&self, name: &str) -> Option<&Pattern> {
+        let (namespace, name) = split_namespace(name);
+        let candidates = || {
+            self.patterns
+                .iter()
+                .filter(move |p| namespace.is_none_or(|n| p.in_namespace(n)))
+        };
+        candidates()
+            .find(|p| p.metadata.pattern == name)
+            .or_else(|| candidates().find(|p| p.metadata.pattern.eq_ignore_ascii_case(name)))
+            .or_else(|| candidates().find(|p| p.answers_to(name)))
+    }
+
+    /// Every pattern a plain name (or alias) refers to, one per namespace, for telling the
+    /// caller about the ones `find_pattern` didn't pick
+    pub fn namesakes(&self, name: &str) -> Vec<&Pattern> {
+        let mut namesakes: Vec<&Pattern> = Vec::new();
+        for pattern in self.patterns.iter().filter(|p| p.answers_to(name)) {
+            if !namesakes.iter().any(|p| p.namespace == pattern.namespace) {
+                namesakes.push(pattern);
+            }
+        }
+        namesakes
+    }
+
+    /// Content a `[[name#section]]` reference points to
+    pub fn resolve_link(&self, link: &Link) -> Result<&str, String> {
+        let target = self
+            .find_pattern(&link.target)
+            .ok_or_else(|| format!("pattern '{}' not found", link.target))?;
+        match &link.section {
+            Some(section) => links::extract_section(&target.content, section)
+                .ok_or_else(|| format!("section '{}' not found", section)),
+            None => Ok(&target.content),
+        }
+    }
+
+    /// The pattern body merged over the bodies of the patterns it `extends:`
+    ///
+    /// Bases that don't resolve, or that would form a cycle, end the chain.
+    pub fn composed_body<'a>(&'a self, pattern: &'a Pattern) -> Cow<'a, str> {
+        let mut chain = vec![pattern];
+        while let Some(base) = chain
+            .last()
+            .and_then(|p| p.metadata.extends.as_deref())
+            .and_then(|name| self.find_pattern(name))
+        {
+            if chain.iter().any(|p| p.metadata.pattern == base.metadata.pattern) {
+                break;
+            }
+            chain.push(base);
+        }
+        if chain.len() == 1 {
+            return Cow::Borrowed(&pattern.content);
+        }
+
+        let root = chain.pop().map(|p| p.content.clone()).unwrap_or_default();
+        let body = chain
+            .iter()
+            .rev()
+            .fold(root, |body, child| links::merge_sections(&body, &child.content));
+        Cow::Owned(body)
+    }
+
+    /// Replace `![[name#section]]` embeds with the referenced content, guarding against cycles
+    pub fn expand_embeds(&self, body: &str, visited: &mut Vec<String>) -> String {
+        self.expand(body, visited, false)
+    }
+
+    /// Like `expand_embeds`, but wraps each embedded chunk in provenance markers
+    pub fn expand_embeds_with_provenance(&self, body: &str, visited: &mut Vec<String>) -> String {
+        self.expand(body, visited, true)
+    }
+
+    fn expand(&self, body: &str, visited: &mut Vec<String>, provenance: bool) -> String {
+        let mut expanded = String::with_capacity(body.len());
+        let mut last = 0;
+        for link in links::parse_links(body).into_iter().filter(|l| l.embed) {
+            expanded.push_str(&body[last..link.span.start]);
+            last = link.span.end;
+
+            let reference = link.reference();
+            match self.resolve_link(&link) {
+                Ok(content) if !visited.contains(&reference) && visited.len() < MAX_EMBED_DEPTH => {
+                    visited.push(reference);
+                    let source = provenance
+                        .then(|| self.find_pattern(&link.target))
+                        .flatten();
+                    if let Some(source) = source {
+                        let section = link.section.as_deref();
+                        expanded.push_str(&provenance_marker(source, section, content));
+                        expanded.push('\n');
+                    }
+                    expanded.push_str(&self.expand(content, visited, provenance));
+                    if source.is_some() {
+                        expanded.push('\n');
+                        expanded.push_str(PROVENANCE_END);
+                    }
+                    visited.pop();
+                }
+                // Leave unresolvable or cyclic embeds as written
+                _ => expanded.push_str(&body[link.span.clone()]),
+            }
+        }
+        expanded.push_str(&body[last..]);
+        expanded
+    }
+
+    /// Every prerequisite of the goal, each after its own prerequisites, ending with the goal
+    ///
+    /// Prerequisites that don't resolve are returned separately. A cycle is an
+    /// error listing the patterns that form it.
+    pub fn prerequisite_chain<'a>(
+        &'a self,
+        goal: &'a Pattern,
+    ) -> Result<(Vec<&'a Pattern>, Vec<String>), Vec<String>> {
+        fn visit<'a>(
+            library: &'a Library,
+            pattern: &'a Pattern,
+            stack: &mut Vec<String>,
+            chain: &mut Vec<&'a Pattern>,
+            missing: &mut Vec<String>,
+        ) -> Result<(), Vec<String>> {
+            let name = &pattern.metadata.pattern;
+            if chain.iter().any(|p| &p.metadata.pattern == name) {
+                return Ok(());
+            }
+            if let Some(start) = stack.iter().position(|n| n == name) {
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(name.clone());
+                return Err(cycle);
+            }
+
+            stack.push(name.clone());
+            for prerequisite in &pattern.metadata.prerequisites {
+                match library.find_pattern(prerequisite) {
+                    Some(p) => visit(library, p, stack, chain, missing)?,
+                    None if !missing.contains(prerequisite) => missing.push(prerequisite.clone()),
+                    None => {}
+                }
+            }
+            stack.pop();
+            chain.push(pattern);
+            Ok(())
+        }
+
+        let (mut chain, mut missing) = (Vec::new(), Vec::new());
+        visit(self, goal, &mut Vec::new(), &mut chain, &mut missing)?;
+        Ok((chain, missing))
+    }
+
+    /// List the patterns a pattern requires and suggests, and whether they resolve
+    pub fn dependencies_summary(&self, pattern: &Pattern) -> String {
+        let list = |title: &str, names: &[String]| {
+            let lines: Vec<String> = names
+                .iter()
+                .map(|name| match self.find_pattern(name) {
+                    Some(_) => format!("- {}", name),
+                    None => format!("- {} (not found)", name),
+                })
+                .collect();
+            if lines.is_empty() {
+                String::new()
+            } else {
+                format!("\n{}:\n{}", title, lines.join("\n"))
+            }
+        };
+        let metadata = &pattern.metadata;
+        let lists =
+            list("Requires", &metadata.prerequisites) + &list("See also", &metadata.see_also);
+        if lists.is_empty() {
+            String::new()
+        } else {
+            format!("\n\n---{}", lists)
+        }
+    }
+
+    /// List the references in a body and whether they resolve
+    pub fn references_summary(&self, body: &str) -> String {
+        let lines: Vec<String> = links::parse_links(body)
+            .iter()
+            .map(|link| match self.resolve_link(link) {
+                Ok(_) => format!("- [[{}]]", link.reference()),
+                Err(e) => format!("- [[{}]] (unresolved: {})", link.reference(), e),
+            })
+            .collect();
+
+        if lines.is_empty() {
+            String::new()
+        } else {
+            format!("\n\n---\nReferences:\n{}", lines.join("\n"))
+        }
+    }
+
+    /// Load the patterns packed in a read-only archive
+    fn load_archive(config: &Config, path: &Path) -> Vec<Pattern> {
+        let files = match archive::read(path) {
+            Ok((_, files)) => files,
+            Err(e) => {
+                tracing::error!("Failed to load archive {:?}: {:#}", path, e);
+                return Vec::new();
+            }
+        };
+
+        files
+            .into_iter()
+            .filter(|f| config.is_pattern_file(&f.path))
+            .filter_map(|f| {
+                let mut pattern = Pattern::parse(&f.content, &f.path)?;
+                pattern.created = pattern.created.or(f.modified);
+                pattern.updated = pattern.updated.or(f.modified);
+                pattern.read_only = true;
+                Some(pattern)
+            })
+            .collect()
+    }
+
+    /// Load patterns from each directory (or archive), then any additional archives and
+    /// mounts. The patterns directory's and the mounts' patterns get their namespaces
+    pub fn load_patterns(config: &Config, dirs: &[PathBuf]) -> Vec<Pattern> {
+        let load = |path: &Path, namespace: Option<&String>| {
+            let mut patterns = if archive::is_archive(path) {
+                Self::load_archive(config, path)
+            } else {
+                Self::load_dir(config, path)
+            };
+            for pattern in &mut patterns {
+                pattern.namespace = namespace.cloned();
+            }
+            patterns
+        };
+        let mut patterns = Vec::new();
+        for dir in dirs {
+            let own = config.patterns_dir.as_ref() == Some(dir);
+            patterns.extend(load(dir, config.namespace.as_ref().filter(|_| own)));
+        }
+        for path in &config.archives {
+            patterns.extend(Self::load_archive(config, path));
+        }
+        for mount in &config.mounts {
+            patterns.extend(load(&mount.path, mount.namespace.as_ref()));
+        }
+        patterns
+    }
+
+    /// Load patterns from the provided directory
+    fn load_dir(config: &Config, patterns_dir: &Path) -> Vec<Pattern> {
+        let config = config.with_ignore_file(patterns_dir);
+        let mut patterns: Vec<Pattern> = fs::read_dir(patterns_dir)
+            .inspect_err(|e| {
+                tracing::error!("Failed to read patterns directory {:?}: {}", patterns_dir, e)
+            })
+            .ok()
+            .into_iter()
+            .flatten()              // Extract good ReadDir
+            .flat_map(|e| e.ok())   // Convet Result<DireEntry, Err> to DirEntry
+            .filter(|e| config.is_pattern_file(&e.path()))
+            .filter_map(|e| {
+                let pattern = Pattern::load(&e.path());
+                if pattern.is_none() {
+                    tracing::warn!(
+                        "Skipping {:?}: missing or invalid frontmatter, see repair_pattern",
+                        e.path()
+                    );
+                }
+                pattern
+            })
+            .collect();
+
+        // Commit dates survive a clone, unlike file times
+        if let Some(dates) = git::file_dates(patterns_dir) {
+            for pattern in &mut patterns {
+                let committed = pattern
+                    .filepath
+                    .file_name()
+                    .and_then(|n| dates.get(n.to_string_lossy().as_ref()));
+                if let Some(committed) = committed {
+                    let metadata = &pattern.metadata;
+                    pattern.created = metadata.created_time().or(Some(committed.created()));
+                    pattern.updated = metadata.updated_time().or(Some(committed.updated()));
+                }
+            }
+        }
+        patterns
+    }
+
+    /// Build the search index over the loaded patterns
+    fn build_index(patterns: &[Pattern]) -> SearchIndex {
+        let metadata: Vec<Vec<String>> = patterns
+            .iter()
+            .map(|p| p.metadata_fields().into_iter().map(|(_, text)| text).collect())
+            .collect();
+        SearchIndex::build(patterns.iter().zip(&metadata).map(|(p, metadata)| Fields {
+            name: &p.metadata.pattern,
+            aliases: &p.metadata.aliases,
+            tags: &p.metadata.tags,
+            metadata,
+            body: &p.content,
+        }))
+    }
+
+    /// Build the search index over the code blocks of the loaded patterns
+    fn build_code_index(patterns: &[Pattern]) -> SearchIndex {
+        let code: Vec<String> = patterns.iter().map(|p| search::code_blocks(&p.content)).collect();
+        SearchIndex::build(code.iter().map(|body| Fields {
+            name: "",
+            aliases: &[],
+            tags: &[],
+            metadata: &[],
+            body,
+        }))
+    }
+
+    /// Index the dependencies imported by the code blocks of the loaded patterns
+    fn build_dependencies(patterns: &[Pattern]) -> BTreeMap<String, Vec<usize>> {
+        let mut index: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, pattern) in patterns.iter().enumerate() {
+            for dependency in dependencies::imports(&pattern.content) {
+                index.entry(dependency).or_default().push(i);
+            }
+        }
+        index
+    }
+
+    /// Text embedded for a pattern: its name, tags and body
+    fn embedding_text(pattern: &Pattern) -> String {
+        format!(
+            "{}\n{}\n{}",
+            pattern.metadata.pattern,
+            pattern.metadata.tags.join(", "),
+            pattern.content
+        )
+    }
+
+    /// Embed every pattern not cached yet
+    fn build_embeddings(
+        embedder: &dyn Embedder,
+        patterns_dir: &Path,
+        patterns: &[Pattern],
+    ) -> Vec<Vec<f32>> {
+        let texts: Vec<String> = patterns.iter().map(Self::embedding_text).collect();
+        EmbeddingStore::load(patterns_dir)
+            .embed_all(embedder, &texts)
+            .unwrap_or_else(|e| {
+                tracing::error!("Semantic search unavailable: {:#}", e);
+                Vec::new()
+            })
+    }
+}
+
+/// Check a new pattern's name is 1-100 alphanumeric, dash or underscore characters
+pub fn validate_pattern_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 100 {
+        return Err("Pattern must be 1-100 characters".to_string());
+    }
+    if name
+        .chars()
+        .any(|c| !c.is_alphanumeric() && c != '-' && c != '_')
+    {
+        return Err(
+            "Pattern name can only contain alphanumeric, dash and underscore characters"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
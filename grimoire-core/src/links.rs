@@ -0,0 +1,164 @@
+//! `[[pattern-name#section]]` references between patterns
+//!
+//! A plain reference links to another pattern (or one of its sections), while
+//! `![[pattern-name#section]]` transcludes the referenced content in place.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(!?)\[\[([^\]#|]+)(?:#([^\]|]+))?(?:\|[^\]]*)?\]\]").expect("valid link regex")
+});
+
+/// A reference to another pattern found in a pattern body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub target: String,
+    pub section: Option<String>,
+    /// `![[...]]` references are replaced by the referenced content
+    pub embed: bool,
+    /// Byte range of the whole reference in the body
+    pub span: std::ops::Range<usize>,
+}
+
+impl Link {
+    /// The reference as written, without the embed marker
+    pub fn reference(&self) -> String {
+        match &self.section {
+            Some(section) => format!("{}#{}", self.target, section),
+            None => self.target.clone(),
+        }
+    }
+}
+
+/// Find every pattern reference in the body
+pub fn parse_links(body: &str) -> Vec<Link> {
+    LINK_RE
+        .captures_iter(body)
+        .map(|c| Link {
+            target: c[2].trim().to_string(),
+            section: c.get(3).map(|s| s.as_str().trim().to_string()),
+            embed: !c[1].is_empty(),
+            span: c.get(0).expect("whole match").range(),
+        })
+        .collect()
+}
+
+/// Split `name#section` into the pattern name and optional section anchor
+pub fn split_anchor(reference: &str) -> (&str, Option<&str>) {
+    match reference.split_once('#') {
+        Some((name, section)) => (name.trim(), Some(section.trim()).filter(|s| !s.is_empty())),
+        None => (reference.trim(), None),
+    }
+}
+
+/// GitHub-style heading anchor: lowercase, spaces to dashes, punctuation removed
+pub fn slugify(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Markdown headings in the body as (level, text, byte offset), skipping fenced code
+fn headings(body: &str) -> Vec<(usize, &str, usize)> {
+    let mut found = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        } else if !in_fence && trimmed.starts_with('#') {
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            let text = trimmed[level..].trim();
+            if !text.is_empty() && trimmed[level..].starts_with(' ') {
+                found.push((level, text, offset));
+            }
+        }
+        offset += line.len();
+    }
+    found
+}
+
+/// Anchors of every section in the body
+pub fn section_anchors(body: &str) -> Vec<String> {
+    headings(body)
+        .iter()
+        .map(|(_, text, _)| slugify(text))
+        .collect()
+}
+
+/// The section under the heading matching `anchor`, up to the next heading of the same or higher level
+pub fn extract_section<'a>(body: &'a str, anchor: &str) -> Option<&'a str> {
+    let anchor = slugify(anchor);
+    let all = headings(body);
+    let start = all
+        .iter()
+        .position(|(_, text, _)| slugify(text) == anchor)?;
+    let (level, _, begin) = all[start];
+    let end = all[start + 1..]
+        .iter()
+        .find(|(l, _, _)| *l <= level)
+        .map_or(body.len(), |(_, _, offset)| *offset);
+    Some(body[begin..end].trim_end())
+}
+
+/// Heading level of the sections `merge_sections` combines
+const SECTION_LEVEL: usize = 2;
+
+/// The text before the first `##` section, then each section with its anchor
+fn split_sections(body: &str) -> (&str, Vec<(String, &str)>) {
+    let starts: Vec<(String, usize)> = headings(body)
+        .into_iter()
+        .filter(|(level, _, _)| *level == SECTION_LEVEL)
+        .map(|(_, text, offset)| (slugify(text), offset))
+        .collect();
+    let preamble = &body[..starts.first().map_or(body.len(), |(_, offset)| *offset)];
+    let sections = starts
+        .iter()
+        .enumerate()
+        .map(|(i, (anchor, begin))| {
+            let end = starts.get(i + 1).map_or(body.len(), |(_, offset)| *offset);
+            (anchor.clone(), &body[*begin..end])
+        })
+        .collect();
+    (preamble, sections)
+}
+
+/// Merge a child body over the base body it extends, `##` section by section
+///
+/// The child's preamble replaces the base's unless it is blank, child sections
+/// replace base sections with the same anchor in place, and any other child
+/// sections are appended in order.
+pub fn merge_sections(base: &str, child: &str) -> String {
+    let (base_preamble, base_sections) = split_sections(base);
+    let (child_preamble, mut child_sections) = split_sections(child);
+
+    let mut parts = vec![if child_preamble.trim().is_empty() {
+        base_preamble
+    } else {
+        child_preamble
+    }];
+    for (anchor, text) in base_sections {
+        match child_sections.iter().position(|(a, _)| *a == anchor) {
+            Some(i) => parts.push(child_sections.remove(i).1),
+            None => parts.push(text),
+        }
+    }
+    parts.extend(child_sections.into_iter().map(|(_, text)| text));
+
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,158 @@
+//! Export of the patterns marked `publish: true`, for a public site
+//!
+//! Internal and public patterns live in one library. The export writes the
+//! published ones to a separate directory, such as a checkout of a blog's
+//! content branch, without what is internal: the projects that used them,
+//! custom frontmatter keys and links or embeds of unpublished patterns.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    library::{Library, Pattern, PatternMetadata, render_pattern, without_usage_entries},
+    links,
+};
+
+/// Lists the files the last export wrote, so the next one can remove unpublished patterns
+const MANIFEST_FILE: &str = ".grimoire-published";
+
+/// What an export wrote and what it left out
+#[derive(Debug, Default)]
+pub struct Export {
+    pub written: Vec<PathBuf>,
+    /// Files of patterns that are no longer published, removed from the output
+    pub removed: Vec<PathBuf>,
+    /// Patterns marked for publishing but left out, with the reason
+    pub skipped: Vec<(String, &'static str)>,
+}
+
+fn is_published(pattern: &Pattern) -> bool {
+    pattern.metadata.publish && !pattern.metadata.draft
+}
+
+/// Where a published pattern can be linked: its canonical URL, or its exported file
+fn public_link(pattern: &Pattern, section: Option<&str>) -> String {
+    let target = pattern
+        .metadata
+        .published_url
+        .clone()
+        .unwrap_or_else(|| format!("{}.md", pattern.metadata.pattern));
+    match section {
+        Some(section) => format!("{}#{}", target, section),
+        None => target,
+    }
+}
+
+/// The text with embeds of published patterns expanded, references to them turned into
+/// markdown links, and references to anything else reduced to plain text (or dropped, for
+/// embeds), so nothing unpublished is revealed
+fn public_text(library: &Library, text: &str, visited: &mut Vec<String>) -> String {
+    let text = without_usage_entries(text);
+    let mut public = String::with_capacity(text.len());
+    let mut last = 0;
+    for link in links::parse_links(&text) {
+        public.push_str(&text[last..link.span.start]);
+        last = link.span.end;
+        let reference = link.reference();
+        let target = library.find_pattern(&link.target).filter(|p| is_published(p));
+        match (target, link.embed) {
+            (Some(_), true) if !visited.contains(&reference) => {
+                if let Ok(content) = library.resolve_link(&link) {
+                    visited.push(reference);
+                    public.push_str(&public_text(library, content, visited));
+                    visited.pop();
+                }
+            }
+            (Some(target), _) => public.push_str(&format!(
+                "[{}]({})",
+                reference,
+                public_link(target, link.section.as_deref())
+            )),
+            (None, true) => {}
+            (None, false) => public.push_str(&reference),
+        }
+    }
+    public.push_str(&text[last..]);
+    public
+}
+
+/// The pattern's public body, with the blank lines left by dropped embeds collapsed
+fn public_body(library: &Library, pattern: &Pattern) -> String {
+    let mut visited = vec![pattern.metadata.pattern.clone()];
+    let mut body = public_text(library, &library.composed_body(pattern), &mut visited);
+    while body.contains("\n\n\n") {
+        body = body.replace("\n\n\n", "\n\n");
+    }
+    body
+}
+
+/// Frontmatter safe to publish: only what describes the pattern itself
+fn public_metadata(library: &Library, metadata: &PatternMetadata) -> PatternMetadata {
+    PatternMetadata {
+        projects: Vec::new(),
+        prerequisites: metadata
+            .prerequisites
+            .iter()
+            .filter(|name| library.find_pattern(name).is_some_and(is_published))
+            .cloned()
+            .collect(),
+        see_also: metadata
+            .see_also
+            .iter()
+            .filter(|name| library.find_pattern(name).is_some_and(is_published))
+            .cloned()
+            .collect(),
+        extends: None,
+        priority: 0,
+        extra: Default::default(),
+        ..metadata.clone()
+    }
+}
+
+/// Write every published pattern to `output`, removing the files of patterns
+/// a previous export wrote that are no longer published
+pub fn export(library: &Library, output: &Path) -> Result<Export> {
+    fs::create_dir_all(output)
+        .with_context(|| format!("Failed to create output directory {:?}", output))?;
+    let manifest = output.join(MANIFEST_FILE);
+    let previous: BTreeSet<String> = fs::read_to_string(&manifest)
+        .map(|m| m.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+
+    let mut export = Export::default();
+    let mut names = BTreeSet::new();
+    for pattern in library.patterns.iter().filter(|p| p.metadata.publish) {
+        let name = &pattern.metadata.pattern;
+        if pattern.metadata.draft {
+            export.skipped.push((name.clone(), "still a draft"));
+            continue;
+        }
+        if !names.insert(format!("{}.md", name)) {
+            export.skipped.push((name.clone(), "another published pattern has the same name"));
+            continue;
+        }
+        let path = output.join(format!("{}.md", name));
+        let content = render_pattern(
+            &public_metadata(library, &pattern.metadata),
+            &public_body(library, pattern),
+        );
+        fs::write(&path, content).with_context(|| format!("Failed to write {:?}", path))?;
+        export.written.push(path);
+    }
+
+    for stale in previous.difference(&names) {
+        let path = output.join(stale);
+        if fs::remove_file(&path).is_ok() {
+            export.removed.push(path);
+        }
+    }
+    let listing: Vec<&str> = names.iter().map(String::as_str).collect();
+    fs::write(&manifest, listing.join("\n"))
+        .with_context(|| format!("Failed to write {:?}", manifest))?;
+    Ok(export)
+}
@@ -0,0 +1,142 @@
+//! Heuristic quality score of a pattern, used to nudge search ranking
+//!
+//! Well-maintained patterns pass a few lint checks, have some substance and
+//! working code, fill in their metadata and have been touched recently. Each of
+//! these is scored from 0.0 to 1.0 and the weighted mean is the quality score.
+
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    library::{Library, Pattern},
+    links, search,
+};
+
+/// Words of body text at which a pattern no longer counts as a stub
+const FULL_LENGTH_WORDS: f64 = 150.0;
+/// Age at which the recency part of the score has halved
+const RECENCY_HALF_LIFE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+/// Score lost per lint issue
+const ISSUE_PENALTY: f64 = 0.25;
+/// Markers of unfinished text
+const TODO_MARKERS: &[&str] = &["TODO", "FIXME", "TBD", "XXX"];
+
+/// Weights of the parts of the score, summing to 1.0
+const LINT_WEIGHT: f64 = 0.3;
+const LENGTH_WEIGHT: f64 = 0.2;
+const CODE_WEIGHT: f64 = 0.15;
+const METADATA_WEIGHT: f64 = 0.2;
+const RECENCY_WEIGHT: f64 = 0.15;
+
+/// A pattern's quality score and how it came about
+#[derive(Debug, Clone)]
+pub struct Quality {
+    /// Weighted mean of the parts below, from 0.0 to 1.0
+    pub score: f64,
+    pub lint: f64,
+    pub length: f64,
+    pub code: f64,
+    pub metadata: f64,
+    pub recency: f64,
+    /// What the lint checks found
+    pub issues: Vec<String>,
+}
+
+impl Quality {
+    /// One line listing the parts of the score and any issues
+    pub fn breakdown(&self) -> String {
+        let mut line = format!(
+            "lint {:.2}, length {:.2}, code {:.2}, metadata {:.2}, recency {:.2}",
+            self.lint, self.length, self.code, self.metadata, self.recency
+        );
+        if !self.issues.is_empty() {
+            line.push_str(&format!("; {}", self.issues.join(", ")));
+        }
+        line
+    }
+}
+
+/// Problems a maintainer should fix: broken links, empty sections, leftover TODOs,
+/// unclosed code fences and the draft flag
+pub fn lint(library: &Library, pattern: &Pattern) -> Vec<String> {
+    let mut issues = Vec::new();
+    if pattern.metadata.draft {
+        issues.push("still a draft".to_string());
+    }
+    for link in links::parse_links(&pattern.content) {
+        if library.resolve_link(&link).is_err() {
+            issues.push(format!("broken link [[{}]]", link.reference()));
+        }
+    }
+
+    let mut in_fence = false;
+    let mut heading: Option<&str> = None;
+    let mut empty_sections = Vec::new();
+    let mut todos = 0;
+    for line in pattern.content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if !in_fence && let Some(title) = trimmed.strip_prefix("## ") {
+            empty_sections.extend(heading.replace(title.trim()));
+            continue;
+        }
+        if !trimmed.is_empty() {
+            heading = None;
+        }
+        todos += search::raw_words(line).filter(|w| TODO_MARKERS.contains(w)).count();
+    }
+    empty_sections.extend(heading);
+    issues.extend(empty_sections.into_iter().map(|s| format!("empty section '{}'", s)));
+    if todos > 0 {
+        issues.push(format!("{} TODO markers", todos));
+    }
+    if in_fence {
+        issues.push("unclosed code block".to_string());
+    }
+    issues
+}
+
+/// Score a pattern as of `now`
+pub fn score(library: &Library, pattern: &Pattern, now: SystemTime) -> Quality {
+    let issues = lint(library, pattern);
+    let lint = (1.0 - ISSUE_PENALTY * issues.len() as f64).max(0.0);
+    let length = (search::words(&pattern.content).count() as f64 / FULL_LENGTH_WORDS).min(1.0);
+    let code = if search::code_blocks(&pattern.content).trim().is_empty() { 0.0 } else { 1.0 };
+
+    let metadata = &pattern.metadata;
+    let filled = [
+        metadata.id.is_some(),
+        !metadata.frameworks.is_empty(),
+        metadata.description.is_some(),
+        !metadata.tags.is_empty(),
+    ];
+    let metadata = filled.iter().filter(|f| **f).count() as f64 / filled.len() as f64;
+
+    // Patterns without a known modification time count as half-way stale
+    let recency = pattern.updated.map_or(0.5, |updated| {
+        let age = now.duration_since(updated).unwrap_or_default();
+        0.5_f64.powf(age.as_secs_f64() / RECENCY_HALF_LIFE.as_secs_f64())
+    });
+
+    Quality {
+        score: LINT_WEIGHT * lint
+            + LENGTH_WEIGHT * length
+            + CODE_WEIGHT * code
+            + METADATA_WEIGHT * metadata
+            + RECENCY_WEIGHT * recency,
+        lint,
+        length,
+        code,
+        metadata,
+        recency,
+        issues,
+    }
+}
+
+/// Multiplier applied to a pattern's search score for its quality, blending in
+/// `weight` of the score: 1.0 at full quality, `1.0 - weight` at none
+pub fn boost(quality: f64, weight: f64) -> f64 {
+    let weight = weight.clamp(0.0, 1.0);
+    1.0 - weight + weight * quality
+}
@@ -0,0 +1,341 @@
+//! Boolean query syntax for text search
+//!
+//! `tokio AND (channel OR mpsc) NOT deprecated` style queries: `AND`, `OR` and
+//! `NOT` (upper case), `"quoted phrases"` and parentheses. Terms next to each
+//! other without an operator are ANDed, and `AND` binds tighter than `OR`.
+//!
+//! Queries can also carry field qualifiers such as `tag:async`, `category:rust`, `ns:team`,
+//! `author:jane` or `maturity:tested`,
+//! which are pulled out as filters before the rest of the query is matched.
+
+use crate::search::raw_words;
+
+/// A parsed boolean query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A single word, as written
+    Word(String),
+    /// Words that must appear next to each other, in order
+    Phrase(Vec<String>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+}
+
+/// Filters given as `field:value` qualifiers in a query
+#[derive(Debug, Default, PartialEq)]
+pub struct Qualifiers {
+    pub tags: Vec<String>,
+    pub categories: Vec<String>,
+    pub frameworks: Vec<String>,
+    pub projects: Vec<String>,
+    pub namespaces: Vec<String>,
+    pub authors: Vec<String>,
+    pub maturities: Vec<String>,
+    pub difficulties: Vec<String>,
+}
+
+/// Pull `tag:`, `category:`, `framework:`, `project:`, `namespace:`, `author:`, `maturity:`
+/// and `difficulty:` qualifiers out of a query
+///
+/// Returns the qualifiers and the remaining query text. Unknown fields such as
+/// `http://` stay in the query.
+pub fn extract_qualifiers(query: &str) -> (Qualifiers, String) {
+    let mut qualifiers = Qualifiers::default();
+    let mut rest: Vec<&str> = Vec::new();
+    for token in query.split_whitespace() {
+        let Some((field, value)) = token.split_once(':').filter(|(_, v)| !v.is_empty()) else {
+            rest.push(token);
+            continue;
+        };
+        let value = value.to_string();
+        match field.to_lowercase().as_str() {
+            "tag" | "tags" => qualifiers.tags.push(value),
+            "category" | "cat" => qualifiers.categories.push(value),
+            "framework" => qualifiers.frameworks.push(value),
+            "project" => qualifiers.projects.push(value),
+            "namespace" | "ns" => qualifiers.namespaces.push(value),
+            "author" => qualifiers.authors.push(value),
+            "maturity" => qualifiers.maturities.push(value),
+            "difficulty" => qualifiers.difficulties.push(value),
+            _ => rest.push(token),
+        }
+    }
+    (qualifiers, rest.join(" "))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Phrase(String),
+    Text(String),
+}
+
+/// Whether the query uses any boolean syntax, rather than being a plain list of terms
+pub fn is_boolean(query: &str) -> bool {
+    query.contains(['"', '(', ')'])
+        || query
+            .split_whitespace()
+            .any(|w| matches!(w, "AND" | "OR" | "NOT"))
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let phrase: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                tokens.push(Token::Phrase(phrase));
+            }
+            _ => {
+                let mut text = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                tokens.push(match text.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Text(text),
+                });
+            }
+        }
+    }
+    if !query.matches('"').count().is_multiple_of(2) {
+        return Err("unterminated quoted phrase".to_string());
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut branches = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            branches.push(self.and()?);
+        }
+        Ok(flatten(branches, Expr::Or))
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut operands = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                    operands.push(self.unary()?);
+                }
+                Some(Token::Or | Token::Close) | None => break,
+                Some(_) => operands.push(self.unary()?),
+            }
+        }
+        Ok(flatten(operands, Expr::And))
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Phrase(phrase)) => {
+                let words: Vec<String> = raw_words(&phrase).map(str::to_string).collect();
+                if words.is_empty() {
+                    return Err("empty quoted phrase".to_string());
+                }
+                Ok(Expr::Phrase(words))
+            }
+            Some(Token::Text(text)) => {
+                let words: Vec<Expr> = raw_words(&text)
+                    .map(|w| Expr::Word(w.to_string()))
+                    .collect();
+                if words.is_empty() {
+                    return Err(format!("'{}' contains no searchable words", text));
+                }
+                Ok(flatten(words, Expr::And))
+            }
+            Some(Token::Close) => Err("unexpected closing parenthesis".to_string()),
+            Some(Token::And | Token::Or) => Err("operator without a left-hand term".to_string()),
+            None => Err("query ends with an operator".to_string()),
+        }
+    }
+}
+
+/// A single operand on its own, otherwise the combined expression
+fn flatten(mut operands: Vec<Expr>, combine: fn(Vec<Expr>) -> Expr) -> Expr {
+    if operands.len() == 1 {
+        operands.remove(0)
+    } else {
+        combine(operands)
+    }
+}
+
+/// Parse a boolean query
+pub fn parse(query: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        pos: 0,
+    };
+    if parser.peek().is_none() {
+        return Err("empty query".to_string());
+    }
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err("unexpected closing parenthesis".to_string()),
+    }
+}
+
+impl Expr {
+    /// Words the expression looks for, leaving out anything under a `NOT`
+    pub fn positive_words(&self) -> Vec<&str> {
+        match self {
+            Expr::Word(word) => vec![word.as_str()],
+            Expr::Phrase(words) => words.iter().map(String::as_str).collect(),
+            Expr::And(operands) | Expr::Or(operands) => {
+                operands.iter().flat_map(Expr::positive_words).collect()
+            }
+            Expr::Not(_) => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(w: &str) -> Expr {
+        Expr::Word(w.to_string())
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parse("a OR b AND c").unwrap(),
+            Expr::Or(vec![word("a"), Expr::And(vec![word("b"), word("c")])])
+        );
+        assert_eq!(
+            parse("a b OR c").unwrap(),
+            Expr::Or(vec![Expr::And(vec![word("a"), word("b")]), word("c")])
+        );
+    }
+
+    #[test]
+    fn parentheses_and_not_group() {
+        assert_eq!(
+            parse("tokio AND (channel OR mpsc) NOT deprecated").unwrap(),
+            Expr::And(vec![
+                word("tokio"),
+                Expr::Or(vec![word("channel"), word("mpsc")]),
+                Expr::Not(Box::new(word("deprecated"))),
+            ])
+        );
+        assert_eq!(
+            parse("NOT (a OR b)").unwrap(),
+            Expr::Not(Box::new(Expr::Or(vec![word("a"), word("b")])))
+        );
+    }
+
+    #[test]
+    fn lower_case_operators_are_words() {
+        assert_eq!(parse("a or b").unwrap(), Expr::And(vec![word("a"), word("or"), word("b")]));
+        assert!(!is_boolean("error or panic"));
+        assert!(is_boolean("error OR panic"));
+    }
+
+    #[test]
+    fn quoted_phrases() {
+        assert_eq!(
+            parse("\"error handling\" axum").unwrap(),
+            Expr::And(vec![
+                Expr::Phrase(vec!["error".to_string(), "handling".to_string()]),
+                word("axum"),
+            ])
+        );
+        // Operators and parentheses inside quotes are part of the phrase
+        assert_eq!(
+            parse("\"a (b) OR c\"").unwrap(),
+            Expr::Phrase(vec!["a".into(), "b".into(), "OR".into(), "c".into()])
+        );
+        assert_eq!(parse("\"unterminated").unwrap_err(), "unterminated quoted phrase");
+        assert_eq!(parse("\"  \"").unwrap_err(), "empty quoted phrase");
+    }
+
+    #[test]
+    fn unbalanced_parentheses() {
+        assert_eq!(parse("(a OR b").unwrap_err(), "missing closing parenthesis");
+        assert_eq!(parse("a OR b)").unwrap_err(), "unexpected closing parenthesis");
+        assert_eq!(parse(")a").unwrap_err(), "unexpected closing parenthesis");
+        assert_eq!(parse("()").unwrap_err(), "unexpected closing parenthesis");
+    }
+
+    #[test]
+    fn dangling_operators() {
+        assert_eq!(parse("OR a").unwrap_err(), "operator without a left-hand term");
+        assert_eq!(parse("a AND").unwrap_err(), "query ends with an operator");
+        assert_eq!(parse("a NOT").unwrap_err(), "query ends with an operator");
+        assert_eq!(parse("   ").unwrap_err(), "empty query");
+    }
+
+    #[test]
+    fn terms_split_into_words() {
+        assert_eq!(parse("tokio::sync").unwrap(), Expr::And(vec![word("tokio"), word("sync")]));
+        assert_eq!(parse("--").unwrap_err(), "'--' contains no searchable words");
+    }
+
+    #[test]
+    fn positive_words_skip_negations() {
+        let expr = parse("a AND (b OR NOT c) NOT \"d e\"").unwrap();
+        assert_eq!(expr.positive_words(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn qualifiers_are_pulled_out() {
+        let (qualifiers, rest) =
+            extract_qualifiers("retry tag:async CAT:rust http://example.com ns:team author:");
+        assert_eq!(qualifiers.tags, vec!["async"]);
+        assert_eq!(qualifiers.categories, vec!["rust"]);
+        assert_eq!(qualifiers.namespaces, vec!["team"]);
+        assert_eq!(rest, "retry http://example.com author:");
+    }
+}
@@ -0,0 +1,234 @@
+//! Recovery of pattern files whose frontmatter is missing or invalid
+//!
+//! The loader skips any file it cannot parse. Repair salvages whatever frontmatter
+//! entries are still valid YAML and infers the rest from the body and file name.
+
+use serde_yaml::{Mapping, Value};
+
+use crate::links;
+
+/// Metadata recovered from a damaged pattern file, with notes on how each part was obtained
+#[derive(Debug, Default)]
+pub struct Recovered {
+    pub pattern: Option<String>,
+    pub id: Option<String>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub version: Option<String>,
+    pub category: Option<String>,
+    pub frameworks: Vec<String>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub maturity: Option<String>,
+    pub difficulty: Option<String>,
+    pub projects: Vec<String>,
+    pub tags: Vec<String>,
+    pub aliases: Vec<String>,
+    pub prerequisites: Vec<String>,
+    pub see_also: Vec<String>,
+    pub priority: i64,
+    pub draft: bool,
+    pub publish: bool,
+    pub published_url: Option<String>,
+    pub body: String,
+    pub notes: Vec<String>,
+}
+
+/// Split a file into its frontmatter lines (if any) and body
+///
+/// Without a closing `---` the leading `key: value` lines are taken as frontmatter.
+fn split_frontmatter(content: &str) -> (Option<String>, String) {
+    let content = content.replace("\r\n", "\n");
+    let Some(rest) = content.strip_prefix("---\n") else {
+        return (None, content.trim().to_string());
+    };
+
+    let lines: Vec<&str> = rest.lines().collect();
+    let end = match lines.iter().position(|l| l.trim_end() == "---") {
+        Some(end) => end,
+        None => lines
+            .iter()
+            .position(|l| !is_entry_line(l) && !is_continuation(l))
+            .unwrap_or(lines.len()),
+    };
+    let body_start = if lines.get(end).is_some_and(|l| l.trim_end() == "---") {
+        end + 1
+    } else {
+        end
+    };
+    (
+        Some(lines[..end].join("\n")),
+        lines[body_start..].join("\n").trim().to_string(),
+    )
+}
+
+fn is_entry_line(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(key, _)| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+fn is_continuation(line: &str) -> bool {
+    line.starts_with(' ') || line.starts_with('\t') || line.starts_with("- ")
+}
+
+/// Parse each top-level frontmatter entry on its own, keeping the ones that are valid YAML
+fn salvage_entries(yaml: &str, notes: &mut Vec<String>) -> Mapping {
+    let mut chunks: Vec<String> = Vec::new();
+    for line in yaml.lines().filter(|l| !l.trim().is_empty()) {
+        match chunks.last_mut() {
+            Some(chunk) if is_continuation(line) => {
+                chunk.push('\n');
+                chunk.push_str(line);
+            }
+            _ => chunks.push(line.to_string()),
+        }
+    }
+
+    let mut salvaged = Mapping::new();
+    for chunk in chunks {
+        match serde_yaml::from_str::<Mapping>(&chunk) {
+            Ok(entry) => salvaged.extend(entry),
+            Err(_) => notes.push(format!("dropped invalid frontmatter: {}", chunk.trim())),
+        }
+    }
+    salvaged
+}
+
+/// A frontmatter value as a single string
+fn string_field(entries: &Mapping, key: &str) -> Option<String> {
+    match entries.get(key)? {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+    .filter(|s| !s.is_empty())
+}
+
+/// A frontmatter value as a list, accepting a single value or a comma separated string
+fn list_field(entries: &Mapping, key: &str) -> Vec<String> {
+    let values: Vec<String> = match entries.get(key) {
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|v| match v {
+                Value::String(s) => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect(),
+        Some(Value::String(s)) => s.split(',').map(str::to_string).collect(),
+        _ => Vec::new(),
+    };
+    values
+        .into_iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Text of the first markdown heading in the body
+fn first_heading(body: &str) -> Option<&str> {
+    body.lines()
+        .map(str::trim)
+        .find(|l| l.starts_with('#'))
+        .map(|l| l.trim_start_matches('#').trim())
+        .filter(|h| !h.is_empty())
+}
+
+/// Guess tags from code block languages and tags already used elsewhere in the library
+fn guess_tags(body: &str, known_tags: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut push = |tag: String| {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    };
+
+    for line in body.lines() {
+        if let Some(lang) = line.trim_start().strip_prefix("```") {
+            push(lang.trim().to_lowercase());
+        }
+    }
+    let words: Vec<String> = crate::search::words(body).collect();
+    for tag in known_tags {
+        if words.contains(tag) {
+            push(tag.clone());
+        }
+    }
+    tags
+}
+
+/// Recover what can be recovered from a pattern file
+///
+/// `file_stem` is used as the name when neither the frontmatter nor a heading provides one.
+pub fn recover(content: &str, file_stem: &str, known_tags: &[String]) -> Recovered {
+    let (yaml, body) = split_frontmatter(content);
+    let mut recovered = Recovered {
+        body,
+        ..Default::default()
+    };
+
+    let entries = match yaml {
+        Some(yaml) => salvage_entries(&yaml, &mut recovered.notes),
+        None => {
+            recovered.notes.push("no frontmatter found".to_string());
+            Mapping::new()
+        }
+    };
+
+    recovered.pattern = string_field(&entries, "pattern").or_else(|| {
+        let inferred = first_heading(&recovered.body)
+            .map(links::slugify)
+            .filter(|s| !s.is_empty())
+            .map(|name| (name, "first heading"))
+            .unwrap_or_else(|| (file_stem.to_string(), "file name"));
+        recovered
+            .notes
+            .push(format!("inferred pattern name '{}' from the {}", inferred.0, inferred.1));
+        Some(inferred.0)
+    });
+    recovered.id = string_field(&entries, "id");
+    recovered.created = string_field(&entries, "created");
+    recovered.updated = string_field(&entries, "updated");
+    recovered.version = string_field(&entries, "version");
+    recovered.category = string_field(&entries, "category");
+    recovered.frameworks = list_field(&entries, "framework");
+    recovered.description = string_field(&entries, "description");
+    recovered.author = string_field(&entries, "author");
+    recovered.maturity = string_field(&entries, "maturity");
+    recovered.difficulty = string_field(&entries, "difficulty");
+    recovered.projects = list_field(&entries, "projects");
+    recovered.aliases = list_field(&entries, "aliases");
+    recovered.prerequisites = list_field(&entries, "prerequisites");
+    if recovered.prerequisites.is_empty() {
+        recovered.prerequisites = list_field(&entries, "requires");
+    }
+    recovered.see_also = list_field(&entries, "see_also");
+    recovered.priority = entries
+        .get("priority")
+        .and_then(Value::as_i64)
+        .unwrap_or_default();
+    recovered.draft = entries
+        .get("draft")
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
+    recovered.publish = entries
+        .get("publish")
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
+    recovered.published_url = string_field(&entries, "published_url");
+    recovered.tags = list_field(&entries, "tags");
+    if recovered.tags.is_empty() {
+        recovered.tags = guess_tags(&recovered.body, known_tags);
+        if !recovered.tags.is_empty() {
+            recovered
+                .notes
+                .push(format!("guessed tags: {}", recovered.tags.join(", ")));
+        }
+    }
+    recovered
+}
@@ -0,0 +1,70 @@
+//! Pattern directories found under the filesystem roots an MCP client shares
+//!
+//! Clients advertise the folders the user is working in as `file://` roots.
+//! Projects can keep their own patterns in a `patterns/` or `.grimoire/`
+//! folder, which the server then loads alongside the patterns directory.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// How deep below a root pattern directories are looked for
+const MAX_DEPTH: usize = 3;
+
+/// Directories never searched, as they hold dependencies and build output
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target"];
+
+/// The local path of a `file://` root, with percent-encoded characters decoded
+pub fn path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    // Skip the host, usually empty or `localhost`
+    let encoded = &encoded[encoded.find('/')?..];
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = (byte == b'%')
+            .then(|| std::str::from_utf8(tail.get(..2)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Directories named one of `names` under `root`. Found directories are not searched
+/// further, nor are hidden ones unless they are named
+pub fn discover(root: &Path, names: &[String]) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if names.contains(&name) {
+                found.push(entry.path());
+            } else if depth + 1 < MAX_DEPTH
+                && !name.starts_with('.')
+                && !SKIPPED_DIRS.contains(&name.as_str())
+            {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    found.sort();
+    found
+}
@@ -0,0 +1,684 @@
+//! Text matching and scoring used by the pattern search tools
+
+use std::{collections::HashMap, ops::Range, sync::LazyLock};
+
+use regex::{Regex, RegexBuilder};
+use rust_stemmers::{Algorithm, Stemmer};
+
+use crate::query::{self, Expr};
+
+/// Minimum similarity for a fuzzy term or name match to count as a hit
+pub const FUZZY_THRESHOLD: f64 = 0.75;
+/// Weight of an indexed word sharing the query term's stem, so the exact form ranks first
+const STEM_WEIGHT: f64 = 0.9;
+
+static STEMMER: LazyLock<Stemmer> = LazyLock::new(|| Stemmer::create(Algorithm::English));
+
+/// English stem of a lowercase word, e.g. "cach" for "caching", "cached" and "cache"
+pub fn stem(word: &str) -> String {
+    STEMMER.stem(word).into_owned()
+}
+
+/// BM25 term frequency saturation
+const BM25_K1: f64 = 1.2;
+/// BM25 document length normalization
+const BM25_B: f64 = 0.75;
+
+/// Field weights, so hits in the name or tags outrank hits in the body
+const NAME_WEIGHT: f64 = 3.0;
+const TAG_WEIGHT: f64 = 2.0;
+const METADATA_WEIGHT: f64 = 1.5;
+const BODY_WEIGHT: f64 = 1.0;
+
+/// Relevance multiplier per point of curator priority
+const PRIORITY_STEP: f64 = 0.1;
+/// Bounds on the priority multiplier, so priority never completely overrides relevance
+const MIN_PRIORITY_BOOST: f64 = 0.5;
+const MAX_PRIORITY_BOOST: f64 = 2.0;
+
+/// Multiplier applied to a pattern's search score for its `priority:` value, 1.0 at priority 0
+pub fn priority_boost(priority: i64) -> f64 {
+    (1.0 + PRIORITY_STEP * priority as f64).clamp(MIN_PRIORITY_BOOST, MAX_PRIORITY_BOOST)
+}
+
+/// Reciprocal rank fusion constant, damping the influence of top ranks
+const RRF_K: f64 = 60.0;
+
+/// Merge rankings of document indexes with reciprocal rank fusion, best first
+///
+/// Each document scores `1 / (RRF_K + rank)` in every ranking it appears in, so
+/// documents ranked well by several rankings rise above those only one ranking likes.
+pub fn reciprocal_rank_fusion(rankings: &[Vec<usize>]) -> Vec<(usize, f64)> {
+    let mut scores: HashMap<usize, f64> = HashMap::new();
+    for ranking in rankings {
+        for (rank, doc) in ranking.iter().enumerate() {
+            *scores.entry(*doc).or_default() += 1.0 / (RRF_K + rank as f64 + 1.0);
+        }
+    }
+    let mut fused: Vec<(usize, f64)> = scores.into_iter().collect();
+    fused.sort_by(|(a_doc, a), (b_doc, b)| b.total_cmp(a).then(a_doc.cmp(b_doc)));
+    fused
+}
+
+/// Similarity between two strings in the range 0.0..=1.0, where 1.0 is an exact match
+pub fn similarity(a: &str, b: &str) -> f64 {
+    strsim::normalized_damerau_levenshtein(&a.to_lowercase(), &b.to_lowercase())
+}
+
+/// Candidates completing a partially typed value, best first: those starting with
+/// it, then those containing it, then close misspellings of it
+pub fn complete<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let value = value.to_lowercase();
+    let mut ranked: Vec<(u8, f64, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let lower = candidate.to_lowercase();
+            if lower.starts_with(&value) {
+                return Some((0, 1.0, candidate));
+            }
+            if lower.contains(&value) {
+                return Some((1, 1.0, candidate));
+            }
+            // Compare against the candidate's start too, as the value may be only partly typed
+            let start: String = lower.chars().take(value.chars().count()).collect();
+            let score = similarity(&value, &lower).max(similarity(&value, &start));
+            (score >= FUZZY_THRESHOLD).then_some((2, score, candidate))
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal))
+            .then_with(|| a.2.to_lowercase().cmp(&b.2.to_lowercase()))
+    });
+    ranked.into_iter().map(|(_, _, candidate)| candidate).collect()
+}
+
+/// Split text into alphanumeric words, keeping their case
+pub fn raw_words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+}
+
+/// Split text into lowercase alphanumeric words
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    raw_words(text).map(str::to_lowercase)
+}
+
+/// The searchable fields of a single document
+pub struct Fields<'a> {
+    pub name: &'a str,
+    pub aliases: &'a [String],
+    pub tags: &'a [String],
+    /// Other frontmatter values, such as the framework, projects and custom keys
+    pub metadata: &'a [String],
+    pub body: &'a str,
+}
+
+#[derive(Debug, Default)]
+struct DocStats {
+    /// Field-weighted term frequencies
+    terms: HashMap<String, f64>,
+    /// Field-weighted document length
+    len: f64,
+}
+
+/// A query term together with the indexed terms it matches and how closely
+#[derive(Debug)]
+pub struct QueryTerm {
+    candidates: Vec<(String, f64)>,
+}
+
+/// BM25 index over pattern names, tags and bodies
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    docs: Vec<DocStats>,
+    doc_freq: HashMap<String, usize>,
+    /// Indexed words grouped by their stem
+    stems: HashMap<String, Vec<String>>,
+    avg_len: f64,
+}
+
+impl SearchIndex {
+    /// Index the documents, in the same order they will later be scored by
+    pub fn build<'a>(docs: impl IntoIterator<Item = Fields<'a>>) -> Self {
+        let mut index = Self::default();
+
+        for doc in docs {
+            let mut stats = DocStats::default();
+            let mut add = |text: &str, weight: f64| {
+                for word in words(text) {
+                    *stats.terms.entry(word).or_default() += weight;
+                    stats.len += weight;
+                }
+            };
+            add(doc.name, NAME_WEIGHT);
+            doc.aliases.iter().for_each(|a| add(a, NAME_WEIGHT));
+            doc.tags.iter().for_each(|t| add(t, TAG_WEIGHT));
+            doc.metadata.iter().for_each(|m| add(m, METADATA_WEIGHT));
+            add(doc.body, BODY_WEIGHT);
+
+            for term in stats.terms.keys() {
+                *index.doc_freq.entry(term.clone()).or_default() += 1;
+            }
+            index.docs.push(stats);
+        }
+
+        for term in index.doc_freq.keys() {
+            index.stems.entry(stem(term)).or_default().push(term.clone());
+        }
+        let total: f64 = index.docs.iter().map(|d| d.len).sum();
+        index.avg_len = total / index.docs.len().max(1) as f64;
+        index
+    }
+
+    /// Split the query into terms, expanding each to the indexed words it should match
+    ///
+    /// Terms match indexed words exactly and, with stemming, other forms of the same
+    /// word ("caching" also matches "cache" and "cached"). A term that matches nothing
+    /// (usually a typo) matches every indexed term above [`FUZZY_THRESHOLD`], weighted
+    /// by similarity. `whole_word` turns off both expansions.
+    pub fn parse_query(&self, query: &str, options: MatchOptions) -> Vec<QueryTerm> {
+        let exact = options.whole_word;
+        words(query)
+            .map(|term| {
+                let mut candidates = Vec::new();
+                if self.doc_freq.contains_key(&term) || exact {
+                    candidates.push((term.clone(), 1.0));
+                }
+                if options.stemming && !exact {
+                    let forms = self.stems.get(&stem(&term)).into_iter().flatten();
+                    candidates.extend(
+                        forms
+                            .filter(|form| **form != term)
+                            .map(|form| (form.clone(), STEM_WEIGHT)),
+                    );
+                }
+                if candidates.is_empty() {
+                    candidates = self
+                        .doc_freq
+                        .keys()
+                        .map(|known| (known.clone(), similarity(&term, known)))
+                        .filter(|(_, sim)| *sim >= FUZZY_THRESHOLD)
+                        .collect();
+                }
+                QueryTerm { candidates }
+            })
+            .collect()
+    }
+
+    /// Terms for retrying a query that found nothing: each word also matches other forms
+    /// and every indexed word within typo distance, even when it is indexed itself
+    pub fn relaxed_query<'a>(&self, words: impl IntoIterator<Item = &'a str>) -> Vec<QueryTerm> {
+        let options = MatchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            stemming: true,
+        };
+        words
+            .into_iter()
+            .filter_map(|word| {
+                let mut term = self.parse_query(word, options).pop()?;
+                let word = word.to_lowercase();
+                term.candidates.extend(
+                    self.doc_freq
+                        .keys()
+                        .filter(|known| **known != word)
+                        .map(|known| (known.clone(), similarity(&word, known)))
+                        .filter(|(_, sim)| *sim >= FUZZY_THRESHOLD),
+                );
+                Some(term)
+            })
+            .collect()
+    }
+
+    /// Number of indexed documents
+    pub fn doc_count(&self) -> usize {
+        self.docs.len()
+    }
+
+    fn idf(&self, term: &str) -> f64 {
+        let n = self.docs.len() as f64;
+        let df = self.doc_freq.get(term).copied().unwrap_or(0) as f64;
+        ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+    }
+
+    /// BM25 score of a single query term in a document, `None` if the term doesn't match it
+    fn term_score(&self, stats: &DocStats, term: &QueryTerm) -> Option<f64> {
+        let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * stats.len / self.avg_len.max(f64::EPSILON));
+        term.candidates
+            .iter()
+            .filter_map(|(t, weight)| {
+                let tf = *stats.terms.get(t)?;
+                Some(weight * self.idf(t) * tf * (BM25_K1 + 1.0) / (tf + norm))
+            })
+            .fold(None, |best: Option<f64>, s| {
+                Some(best.map_or(s, |b| b.max(s)))
+            })
+    }
+
+    /// BM25 score of a document, `None` unless every query term matches it
+    pub fn score(&self, doc: usize, query: &[QueryTerm]) -> Option<f64> {
+        let stats = self.docs.get(doc)?;
+        if query.is_empty() {
+            return None;
+        }
+        query
+            .iter()
+            .try_fold(0.0, |total, term| Some(total + self.term_score(stats, term)?))
+    }
+
+    /// BM25 score of a document over the query terms it matches, `None` if it matches none
+    pub fn score_any(&self, doc: usize, query: &[QueryTerm]) -> Option<f64> {
+        let stats = self.docs.get(doc)?;
+        query
+            .iter()
+            .filter_map(|term| self.term_score(stats, term))
+            .reduce(|total, score| total + score)
+    }
+}
+
+/// Options narrowing how query words match
+#[derive(Debug, Clone, Copy)]
+pub struct MatchOptions {
+    /// Words must match with the same case, so `Arc` doesn't match `arc`
+    pub case_sensitive: bool,
+    /// Words must match exactly, without stemming or typo tolerance (word boundaries for regexes)
+    pub whole_word: bool,
+    /// Words also match other forms with the same stem
+    pub stemming: bool,
+}
+
+/// How a search query is matched against pattern text
+#[derive(Debug)]
+pub enum QueryMatcher {
+    /// Typo-tolerant terms ranked with BM25, see [`SearchIndex::parse_query`]
+    Terms(Vec<QueryTerm>),
+    /// Relaxed terms, any of which may match, see [`SearchIndex::relaxed_query`]
+    AnyTerm(Vec<QueryTerm>),
+    /// Regular expression, case-insensitive unless requested otherwise
+    Regex(Regex),
+    /// `AND`/`OR`/`NOT` query, with each word expanded like in [`QueryMatcher::Terms`]
+    Boolean {
+        expr: Expr,
+        /// Query terms keyed by their lowercase word
+        terms: HashMap<String, QueryTerm>,
+        case_sensitive: bool,
+    },
+}
+
+impl QueryMatcher {
+    /// Build a matcher for the query, compiling it as a regular expression if requested
+    ///
+    /// Queries using `AND`, `OR`, `NOT`, quotes or parentheses are parsed as boolean
+    /// queries, as are case-sensitive queries so each word can be checked as written.
+    pub fn new(
+        index: &SearchIndex,
+        query: &str,
+        regex: bool,
+        options: MatchOptions,
+    ) -> Result<Self, String> {
+        if regex {
+            let pattern = if options.whole_word {
+                format!(r"\b(?:{})\b", query)
+            } else {
+                query.to_string()
+            };
+            let re = RegexBuilder::new(&pattern)
+                .case_insensitive(!options.case_sensitive)
+                .build()
+                .map_err(|e| format!("invalid regular expression: {}", e))?;
+            Ok(Self::Regex(re))
+        } else if options.case_sensitive || query::is_boolean(query) {
+            let expr = query::parse(query).map_err(|e| format!("invalid query: {}", e))?;
+            let terms = expr
+                .positive_words()
+                .into_iter()
+                .chain(negated_words(&expr))
+                .filter_map(|word| {
+                    let term = index.parse_query(word, options).pop()?;
+                    Some((word.to_lowercase(), term))
+                })
+                .collect();
+            Ok(Self::Boolean {
+                expr,
+                terms,
+                case_sensitive: options.case_sensitive,
+            })
+        } else {
+            Ok(Self::Terms(index.parse_query(query, options)))
+        }
+    }
+
+    /// A looser matcher for retrying a query that found nothing, `None` for regular
+    /// expressions, which can't be loosened
+    pub fn relaxed(&self, index: &SearchIndex, query: &str) -> Option<Self> {
+        let words: Vec<String> = match self {
+            Self::Regex(_) => return None,
+            Self::Boolean { expr, .. } => {
+                expr.positive_words().into_iter().map(str::to_string).collect()
+            }
+            Self::Terms(_) | Self::AnyTerm(_) => words(query).collect(),
+        };
+        Some(Self::AnyTerm(index.relaxed_query(words.iter().map(String::as_str))))
+    }
+
+    /// Score a document against the query, `None` if it does not match
+    pub fn score(&self, index: &SearchIndex, doc: usize, text: &str) -> Option<f64> {
+        match self {
+            Self::Terms(terms) => index.score(doc, terms),
+            Self::AnyTerm(terms) => index.score_any(doc, terms),
+            Self::Regex(re) => re.is_match(text).then_some(1.0),
+            Self::Boolean {
+                expr,
+                terms,
+                case_sensitive,
+            } => {
+                let text_words: Vec<&str> = raw_words(text).collect();
+                let eval = Eval {
+                    terms,
+                    index,
+                    doc,
+                    text_words: &text_words,
+                    case_sensitive: *case_sensitive,
+                };
+                eval.score(expr)
+            }
+        }
+    }
+}
+
+/// Words that only appear under a `NOT`
+fn negated_words(expr: &Expr) -> Vec<&str> {
+    match expr {
+        Expr::Not(inner) => inner.positive_words(),
+        Expr::And(operands) | Expr::Or(operands) => {
+            operands.iter().flat_map(negated_words).collect()
+        }
+        Expr::Word(_) | Expr::Phrase(_) => Vec::new(),
+    }
+}
+
+/// Evaluation of a boolean expression against one document
+struct Eval<'a> {
+    terms: &'a HashMap<String, QueryTerm>,
+    index: &'a SearchIndex,
+    doc: usize,
+    /// Words of the document text, as written
+    text_words: &'a [&'a str],
+    case_sensitive: bool,
+}
+
+impl Eval<'_> {
+    fn same_word(&self, text_word: &str, query_word: &str) -> bool {
+        if self.case_sensitive {
+            text_word == query_word
+        } else {
+            text_word.to_lowercase() == query_word.to_lowercase()
+        }
+    }
+
+    fn word_score(&self, word: &str) -> Option<f64> {
+        if self.case_sensitive && !self.text_words.contains(&word) {
+            return None;
+        }
+        self.terms
+            .get(&word.to_lowercase())
+            .and_then(|term| self.index.score(self.doc, std::slice::from_ref(term)))
+    }
+
+    /// Score of the document for the expression, `None` if it does not match
+    ///
+    /// Matching operands add up their BM25 scores, while a `NOT` matches with a score of zero.
+    fn score(&self, expr: &Expr) -> Option<f64> {
+        match expr {
+            Expr::Word(word) => self.word_score(word),
+            Expr::Phrase(phrase) => {
+                let found = self.text_words.windows(phrase.len()).any(|window| {
+                    window
+                        .iter()
+                        .zip(phrase)
+                        .all(|(text_word, query_word)| self.same_word(text_word, query_word))
+                });
+                if !found {
+                    return None;
+                }
+                Some(phrase.iter().filter_map(|w| self.word_score(w)).sum())
+            }
+            Expr::And(operands) => operands
+                .iter()
+                .try_fold(0.0, |total, e| Some(total + self.score(e)?)),
+            Expr::Or(operands) => operands
+                .iter()
+                .filter_map(|e| self.score(e))
+                .fold(None, |total: Option<f64>, s| Some(total.unwrap_or(0.0) + s)),
+            Expr::Not(inner) => self.score(inner).is_none().then_some(0.0),
+        }
+    }
+}
+
+/// Byte ranges of every whole word in the text
+fn word_spans(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = None;
+    text.char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+        .filter_map(move |(i, c)| match (c.is_alphanumeric(), start) {
+            (true, None) => {
+                start = Some(i);
+                None
+            }
+            (false, Some(s)) => {
+                start = None;
+                Some(s..i)
+            }
+            _ => None,
+        })
+}
+
+impl QueryMatcher {
+    /// Byte ranges in the text that the query matched, in order
+    pub fn match_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            Self::Terms(terms) | Self::AnyTerm(terms) => {
+                let wanted: Vec<&str> = terms
+                    .iter()
+                    .flat_map(|t| t.candidates.iter().map(|(c, _)| c.as_str()))
+                    .collect();
+                word_spans(text)
+                    .filter(|span| wanted.contains(&text[span.clone()].to_lowercase().as_str()))
+                    .collect()
+            }
+            Self::Boolean {
+                expr,
+                case_sensitive: true,
+                ..
+            } => {
+                let wanted = expr.positive_words();
+                word_spans(text)
+                    .filter(|span| wanted.contains(&&text[span.clone()]))
+                    .collect()
+            }
+            Self::Boolean { expr, terms, .. } => {
+                let wanted: Vec<String> = expr
+                    .positive_words()
+                    .iter()
+                    .flat_map(|word| match terms.get(&word.to_lowercase()) {
+                        Some(term) => term.candidates.iter().map(|(c, _)| c.clone()).collect(),
+                        None => vec![word.to_lowercase()],
+                    })
+                    .collect();
+                word_spans(text)
+                    .filter(|span| wanted.contains(&text[span.clone()].to_lowercase()))
+                    .collect()
+            }
+            Self::Regex(re) => re
+                .find_iter(text)
+                .map(|m| m.range())
+                .filter(|r| !r.is_empty())
+                .collect(),
+        }
+    }
+}
+
+/// Largest char boundary at or before `index`
+fn floor_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Longest excerpt, in characters, a search result shows
+pub const MAX_SNIPPET_LENGTH: usize = 2000;
+
+/// An excerpt of about `length` characters centered on the first match, with matches in `**bold**`
+///
+/// Falls back to the start of the text when nothing in it matched.
+pub fn snippet(text: &str, matches: &[Range<usize>], length: usize) -> String {
+    // Start half a window before the first match, or at the top without one
+    let center = matches.first().map_or(0, |m| m.start);
+    let before = if matches.is_empty() { 0 } else { length / 2 };
+    let start = text[..center]
+        .char_indices()
+        .rev()
+        .take(before)
+        .last()
+        .map_or(center, |(i, _)| i);
+    let end = text[start..]
+        .char_indices()
+        .nth(length)
+        .map_or(text.len(), |(i, _)| start + i);
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut last = start;
+    for m in matches.iter().filter(|m| m.start >= start && m.end <= end) {
+        if m.start < last {
+            continue;
+        }
+        out.push_str(&text[last..m.start]);
+        out.push_str("**");
+        out.push_str(&text[m.clone()]);
+        out.push_str("**");
+        last = m.end;
+    }
+    out.push_str(&text[last..floor_boundary(text, end)]);
+    if end < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// The contents of every fenced code block in a markdown body, joined by newlines
+pub fn code_blocks(body: &str) -> String {
+    let mut code = Vec::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        } else if in_fence {
+            code.push(line);
+        }
+    }
+    code.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use super::*;
+
+    const OPTIONS: MatchOptions = MatchOptions {
+        case_sensitive: false,
+        whole_word: false,
+        stemming: false,
+    };
+
+    fn index(bodies: &[&str]) -> SearchIndex {
+        SearchIndex::build(bodies.iter().map(|body| Fields {
+            name: "",
+            aliases: &[],
+            tags: &[],
+            metadata: &[],
+            body,
+        }))
+    }
+
+    /// Which of the bodies the query matches
+    fn matching(bodies: &[&str], query: &str, options: MatchOptions) -> Vec<usize> {
+        let index = index(bodies);
+        let matcher = QueryMatcher::new(&index, query, false, options).unwrap();
+        (0..bodies.len()).filter(|&doc| matcher.score(&index, doc, bodies[doc]).is_some()).collect()
+    }
+
+    #[test]
+    fn boolean_queries_match_documents() {
+        let bodies = [
+            "tokio channel for messages",
+            "tokio mpsc deprecated in favour of channels",
+            "std mpsc channel",
+        ];
+        assert_eq!(matching(&bodies, "tokio AND (channel OR mpsc)", OPTIONS), vec![0, 1]);
+        assert_eq!(matching(&bodies, "tokio NOT deprecated", OPTIONS), vec![0]);
+        assert_eq!(matching(&bodies, "\"mpsc channel\"", OPTIONS), vec![2]);
+        assert_eq!(matching(&bodies, "\"channel mpsc\"", OPTIONS), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn case_sensitive_words_match_as_written() {
+        let bodies = ["wrap it in an Arc", "the arc of the story"];
+        let options = MatchOptions {
+            case_sensitive: true,
+            ..OPTIONS
+        };
+        assert_eq!(matching(&bodies, "Arc", options), vec![0]);
+        assert_eq!(matching(&bodies, "Arc", OPTIONS), vec![0, 1]);
+    }
+
+    #[test]
+    fn invalid_queries_are_errors() {
+        let index = index(&["body"]);
+        let error = QueryMatcher::new(&index, "(a OR b", false, OPTIONS).unwrap_err();
+        assert_eq!(error, "invalid query: missing closing parenthesis");
+        assert!(QueryMatcher::new(&index, "a(", true, OPTIONS).is_err());
+    }
+
+    #[test]
+    fn match_ranges_are_byte_ranges() {
+        let index = index(&["über tokio"]);
+        let matcher = QueryMatcher::new(&index, "tokio", false, OPTIONS).unwrap();
+        let text = "über tokio";
+        assert_eq!(matcher.match_ranges(text), vec![6..11]);
+    }
+
+    #[test]
+    fn snippet_bolds_matches() {
+        let matched = 4..9;
+        assert_eq!(snippet("use tokio here", slice::from_ref(&matched), 100), "use **tokio** here");
+        assert_eq!(snippet("no match at all", &[], 8), "no match…");
+    }
+
+    #[test]
+    fn snippet_cuts_multi_byte_text_on_char_boundaries() {
+        let text = "日本語のテキストで tokio を使う例です";
+        let start = text.find("tokio").unwrap();
+        let matched = start..start + "tokio".len();
+        let matches = slice::from_ref(&matched);
+        assert_eq!(snippet(text, matches, 12), "…テキストで **tokio** …");
+        // A match the window cuts through is shown as far as it goes, without bold
+        assert_eq!(snippet(text, matches, 6), "…トで tok…");
+        // Windows longer than the text take all of it
+        assert_eq!(snippet(text, matches, 100), "日本語のテキストで **tokio** を使う例です");
+        // Emoji are single characters of four bytes
+        assert_eq!(snippet("🦀🦀🦀🦀", &[], 2), "🦀🦀…");
+    }
+
+    #[test]
+    fn snippet_skips_matches_outside_the_window() {
+        let text = "alpha beta gamma";
+        assert_eq!(snippet(text, &[0..5, 11..16], 7), "**alpha** b…");
+    }
+}
@@ -0,0 +1,49 @@
+//! Cancellation and timeouts for tool calls
+//!
+//! Tools run synchronously, so nothing can interrupt them from the outside.
+//! Long-running tools poll a [`Deadline`] instead, and stop with a report of
+//! how far they got once the client cancels the request or the timeout passes.
+
+use std::time::{Duration, Instant};
+
+use rmcp::ErrorData as McpError;
+use tokio_util::sync::CancellationToken;
+
+/// How often, in items, long loops check whether they should stop
+pub const CHECK_INTERVAL: usize = 64;
+
+/// When a tool call has to stop
+#[derive(Debug, Clone)]
+pub struct Deadline {
+    /// Cancelled when the client sends `notifications/cancelled` for the request
+    ct: CancellationToken,
+    timeout: Option<Duration>,
+    started: Instant,
+}
+
+impl Deadline {
+    pub fn new(ct: CancellationToken, timeout: Option<Duration>) -> Self {
+        Self {
+            ct,
+            timeout,
+            started: Instant::now(),
+        }
+    }
+
+    /// Error out if the call was cancelled or timed out, reporting `done` of `total` items of work
+    pub fn check(&self, done: usize, total: usize, work: &str) -> Result<(), McpError> {
+        let reason = if self.ct.is_cancelled() {
+            "cancelled by the client".to_string()
+        } else if let Some(timeout) = self.timeout.filter(|t| self.started.elapsed() > *t) {
+            format!("timed out after {}s", timeout.as_secs())
+        } else {
+            return Ok(());
+        };
+
+        tracing::info!("Tool call {} after {} of {} {}", reason, done, total, work);
+        Err(McpError::internal_error(
+            format!("Request {} after {} of {} {}", reason, done, total, work),
+            Some(serde_json::json!({ "done": done, "total": total })),
+        ))
+    }
+}
@@ -0,0 +1,142 @@
+//! `search` and `get` subcommands: the search_patterns and get_pattern tools run
+//! straight against the library, for looking things up from a terminal
+
+use std::io::{ErrorKind, Write};
+
+use anyhow::Result;
+use rmcp::{handler::server::wrapper::Parameters, model::CallToolResult};
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+
+use grimoire_core::{config::Config, links, search};
+
+use crate::{cancel::Deadline, patterns::Patterns};
+
+/// Most close names suggested when a pattern isn't found
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Filters for the `search` subcommand, named as in the search_patterns tool
+#[derive(Debug, clap::Args)]
+pub struct SearchFilters {
+    /// Only patterns in this category (can be repeated)
+    #[arg(long, value_name = "CATEGORY")]
+    category: Vec<String>,
+    /// Only patterns with this tag (can be repeated)
+    #[arg(long, value_name = "TAG")]
+    tag: Vec<String>,
+    /// Only patterns for this framework (can be repeated)
+    #[arg(long, value_name = "FRAMEWORK")]
+    framework: Vec<String>,
+    /// Only patterns used in this project (can be repeated)
+    #[arg(long, value_name = "PROJECT")]
+    project: Vec<String>,
+    /// Only patterns from the library mounted under this namespace (can be repeated)
+    #[arg(long, value_name = "NAMESPACE")]
+    namespace: Vec<String>,
+    /// Only patterns by this author, or whose author's name contains it (can be repeated)
+    #[arg(long, value_name = "AUTHOR")]
+    author: Vec<String>,
+    /// Only patterns of this maturity, e.g. battle-tested (can be repeated)
+    #[arg(long, value_name = "MATURITY")]
+    maturity: Vec<String>,
+    /// Only patterns of this difficulty, e.g. beginner (can be repeated)
+    #[arg(long, value_name = "DIFFICULTY")]
+    difficulty: Vec<String>,
+    /// Maximum number of results
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+}
+
+fn patterns(config: Config) -> Result<Patterns> {
+    crate::require_patterns_dir(&config);
+    Ok(Patterns::new(config))
+}
+
+/// Write to stdout, stopping quietly when a pager or `head` closes the pipe early
+fn print(output: &str) -> Result<()> {
+    match writeln!(std::io::stdout().lock(), "{}", output) {
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The text of a tool result, or its error message
+fn text(result: CallToolResult) -> Result<String> {
+    let text = result
+        .content
+        .iter()
+        .filter_map(|c| c.as_text())
+        .map(|t| t.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if result.is_error == Some(true) {
+        anyhow::bail!(text);
+    }
+    Ok(text)
+}
+
+/// Search the library and print the results, as the search_patterns tool returns them
+pub fn search(config: Config, query: String, filters: SearchFilters, json: bool) -> Result<()> {
+    let patterns = patterns(config)?;
+    let request = serde_json::from_value(json!({
+        "query": query,
+        "category": filters.category,
+        "tag": filters.tag,
+        "framework": filters.framework,
+        "project": filters.project,
+        "namespace": filters.namespace,
+        "author": filters.author,
+        "maturity": filters.maturity,
+        "difficulty": filters.difficulty,
+        "limit": filters.limit,
+        "format": if json { "json" } else { "markdown" },
+    }))?;
+    let result = patterns
+        .search_patterns(
+            Parameters(request),
+            Deadline::new(CancellationToken::new(), None),
+        )
+        .map_err(|e| anyhow::anyhow!(e.message))?;
+
+    match result.structured_content.clone() {
+        Some(results) if json => print(&serde_json::to_string_pretty(&results)?),
+        _ => print(&text(result)?),
+    }
+}
+
+/// Print a pattern as the get_pattern tool returns it, or its metadata and content as JSON
+pub fn get(config: Config, name: String, json: bool) -> Result<()> {
+    let patterns = patterns(config)?;
+    let library = patterns.library();
+    let (bare_name, _) = links::split_anchor(&name);
+    let Some(pattern) = library.find_pattern(bare_name) else {
+        let names = library.patterns.iter().map(|p| p.metadata.pattern.as_str());
+        let suggestions = search::complete(bare_name, names);
+        if suggestions.is_empty() {
+            anyhow::bail!("Pattern '{}' not found", bare_name);
+        }
+        anyhow::bail!(
+            "Pattern '{}' not found, did you mean: {}",
+            bare_name,
+            suggestions[..suggestions.len().min(MAX_SUGGESTIONS)].join(", ")
+        );
+    };
+
+    let request = serde_json::from_value(json!({ "pattern_name": name }))?;
+    let result = patterns
+        .get_pattern(Parameters(request))
+        .map_err(|e| anyhow::anyhow!(e.message))?;
+    let content = text(result)?;
+
+    if json {
+        let output = json!({
+            "name": pattern.metadata.pattern,
+            "path": pattern.filepath,
+            "metadata": pattern.metadata,
+            "content": content,
+        });
+        print(&serde_json::to_string_pretty(&output)?)
+    } else {
+        print(&content)
+    }
+}
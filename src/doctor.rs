@@ -0,0 +1,158 @@
+//! `doctor` subcommand: a report on the setup, the first thing to attach to a bug report
+//!
+//! Runs the startup diagnostics for every library served, parses every pattern
+//! file and says why the loader would skip the ones it can't read, lists
+//! patterns sharing a name or ID, and prints the effective configuration with
+//! its secrets left out.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use grimoire_core::{
+    config::Config,
+    diagnostics::{self, Diagnostic, Severity},
+    embeddings,
+    library::{Library, Pattern},
+};
+
+/// Shown in place of tokens and tenant keys
+const REDACTED: &str = "<redacted>";
+
+/// Print the report, failing when a problem keeps a library from being served.
+/// `config_path` is the file the config was read from, if any
+pub fn run(config: &Config, config_path: Option<&Path>) -> Result<()> {
+    println!("grimoire-mcp {}", env!("CARGO_PKG_VERSION"));
+    let mut worst = Severity::Ok;
+    let libraries = std::iter::once(("library".to_string(), config.clone()))
+        .filter(|(_, config)| config.patterns_dir.is_some() || config.tenants.is_empty())
+        .chain(
+            config
+                .tenants
+                .iter()
+                .map(|tenant| (format!("tenant '{}'", tenant.name), config.for_tenant(tenant))),
+        );
+    for (name, config) in libraries {
+        println!("\n== {}", name);
+        worst = worst.max(check_library(&config));
+    }
+
+    println!("\n== configuration");
+    match config_path {
+        Some(path) => println!("Read from {}", path.display()),
+        None => println!("No config file found, using the defaults"),
+    }
+    println!("\n{}", toml::to_string_pretty(&redacted(config))?);
+
+    match worst {
+        Severity::Error => anyhow::bail!("Found problems keeping the server from working"),
+        Severity::Warning => println!("Found problems, some features won't work"),
+        Severity::Ok => println!("No problems found"),
+    }
+    Ok(())
+}
+
+/// Check one library and print what was found, returning the worst severity
+fn check_library(config: &Config) -> Severity {
+    let Some(dir) = config.patterns_dir.as_deref() else {
+        print_diagnostic(&Diagnostic {
+            check: "patterns directory",
+            severity: Severity::Error,
+            message: "no patterns directory is configured".into(),
+            remedy: Some("Pass --patterns-dir, set PATTERNS_DIR or run `grimoire-mcp init`".into()),
+        });
+        return Severity::Error;
+    };
+
+    if let Some(problem) = diagnostics::unreadable(dir) {
+        print_diagnostic(&problem);
+        return Severity::Error;
+    }
+
+    let embedder = config.embeddings.as_ref().map(embeddings::embedder);
+    let library = Library::load(config, dir, embedder.as_deref());
+    let checks = diagnostics::run(config, dir, &library);
+    for diagnostic in &checks {
+        print_diagnostic(diagnostic);
+    }
+    let mut worst = checks.iter().map(|d| d.severity).max().unwrap_or(Severity::Ok);
+
+    let dirs = std::iter::once(dir.to_path_buf())
+        .chain(config.mounts.iter().map(|mount| mount.path.clone()))
+        .filter(|dir| dir.is_dir());
+    let mut files = 0;
+    for dir in dirs {
+        let (count, skipped) = unparsed_files(config, &dir);
+        files += count;
+        for (path, reason) in &skipped {
+            println!("- warning pattern file {}: {}", path.display(), reason);
+            println!("  Fix: Correct the frontmatter by hand, or run the repair_pattern tool");
+        }
+        if !skipped.is_empty() {
+            worst = worst.max(Severity::Warning);
+        }
+    }
+    let archived = library.patterns.iter().filter(|p| p.read_only).count();
+    println!("- {} pattern files, {} patterns loaded", files + archived, library.patterns.len());
+
+    for conflict in library.conflicts() {
+        worst = worst.max(Severity::Warning);
+        println!(
+            "- warning duplicates: {} patterns share the {} '{}'",
+            conflict.patterns.len(),
+            conflict.kind,
+            conflict.key
+        );
+        for pattern in &conflict.patterns {
+            println!("  {}", pattern.filepath.display());
+        }
+        println!("  Fix: Rename all but one, or run the resolve_id_conflicts tool");
+    }
+    worst
+}
+
+/// The number of pattern files directly in directory `dir`, and those that can't be loaded with why
+fn unparsed_files(config: &Config, dir: &Path) -> (usize, Vec<(PathBuf, String)>) {
+    let config = config.with_ignore_file(dir);
+    let files: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flat_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| config.is_pattern_file(path))
+        .collect();
+    let skipped = files
+        .iter()
+        .filter_map(|path| {
+            let reason = match fs::read_to_string(path) {
+                Ok(content) => Pattern::parse_error(&content)?,
+                Err(e) => format!("can't be read: {}", e),
+            };
+            Some((path.clone(), reason))
+        })
+        .collect();
+    (files.len(), skipped)
+}
+
+fn print_diagnostic(diagnostic: &Diagnostic) {
+    println!("- {} {}: {}", diagnostic.severity, diagnostic.check, diagnostic.message);
+    if let Some(remedy) = &diagnostic.remedy {
+        println!("  Fix: {}", remedy);
+    }
+}
+
+/// The config without the tokens and keys clients authenticate with
+fn redacted(config: &Config) -> Config {
+    let mut config = config.clone();
+    for token in &mut config.auth.tokens {
+        if token.token.is_some() {
+            token.token = Some(REDACTED.into());
+        }
+    }
+    for tenant in &mut config.tenants {
+        tenant.keys = vec![REDACTED.into(); tenant.keys.len()];
+    }
+    config
+}
@@ -0,0 +1,306 @@
+//! HTTP transports, serving many clients from one process
+//!
+//! Streamable HTTP serves each client an MCP session at `/mcp`. The older
+//! HTTP+SSE transport, which some web clients still use, streams to `/sse` and
+//! takes requests at `/message`, and can be served on the same address. Either
+//! way every session is handled by a session of the shared [`Patterns`]
+//! service, so all clients search and edit the same library and are told about
+//! changes the others make.
+//!
+//! In multi-tenant mode each tenant's library is mounted under `/<tenant>`,
+//! only open to clients presenting one of the tenant's keys. Otherwise the
+//! configured auth tokens, if any, guard the library, each granting read-only
+//! or full access. With a certificate configured, everything is served over
+//! HTTPS instead.
+//!
+//! `/healthz` and `/readyz` are open to anyone, for supervisors to probe.
+//! Request bodies over the configured size are refused before they reach a
+//! session.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use axum::{
+    Router,
+    body::{self, Body},
+    extract::{Request, State, connect_info::Connected},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    serve::IncomingStream,
+};
+use grimoire_core::config::{Access, Tls};
+use rmcp::{
+    ServiceExt,
+    transport::{
+        sse_server::{SseServer, SseServerConfig},
+        streamable_http_server::{
+            StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+        },
+    },
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    patterns::Patterns,
+    tls::{self, TlsListener},
+};
+
+/// Path the streamable HTTP endpoint is served at
+const MCP_PATH: &str = "/mcp";
+/// Path SSE clients open their event stream at
+const SSE_PATH: &str = "/sse";
+/// Path SSE clients post their requests to, with the session ID in the query
+const MESSAGE_PATH: &str = "/message";
+/// Path answering whether the server is up, for liveness probes
+const HEALTH_PATH: &str = "/healthz";
+/// Path answering whether every library can serve clients, for readiness probes
+const READY_PATH: &str = "/readyz";
+
+/// A library served under a path prefix
+#[derive(Clone)]
+pub struct Mount {
+    /// Prefix of the library's endpoints, empty to serve them at the root
+    pub prefix: String,
+    pub patterns: Patterns,
+    /// Bearer tokens clients must present with what they grant, any client is let in
+    /// with full access when empty
+    pub keys: Vec<(String, Access)>,
+}
+
+/// Address of the client a request came from, over HTTP or HTTPS
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddress(pub SocketAddr);
+
+impl Connected<IncomingStream<'_, tokio::net::TcpListener>> for ClientAddress {
+    fn connect_info(stream: IncomingStream<'_, tokio::net::TcpListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for ClientAddress {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
+}
+
+async fn bind(listen: &str) -> Result<tokio::net::TcpListener> {
+    tokio::net::TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", listen))
+}
+
+/// Compare keys in a time that doesn't depend on where they differ
+fn same_key(key: &str, given: &str) -> bool {
+    key.len() == given.len() && key.bytes().zip(given.bytes()).fold(0, |d, (a, b)| d | (a ^ b)) == 0
+}
+
+/// The access granted by an `Authorization: Bearer` header value, if it holds one of the keys
+pub fn access(keys: &[(String, Access)], authorization: Option<&str>) -> Option<Access> {
+    let token = authorization?.strip_prefix("Bearer ")?.trim();
+    keys.iter().find(|(key, _)| same_key(key, token)).map(|(_, access)| *access)
+}
+
+/// Reject requests without a valid key, and tell the session what the key grants
+async fn authorize(
+    State(keys): State<Arc<Vec<(String, Access)>>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let authorization = request.headers().get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    match access(&keys, authorization) {
+        Some(access) => {
+            request.extensions_mut().insert(access);
+            next.run(request).await
+        }
+        None => {
+            (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")]).into_response()
+        }
+    }
+}
+
+/// Only let requests with one of the keys through to the router, if there are any keys
+fn authorized(router: Router, keys: Vec<(String, Access)>) -> Router {
+    if keys.is_empty() {
+        return router;
+    }
+    router.layer(middleware::from_fn_with_state(Arc::new(keys), authorize))
+}
+
+/// 200 whenever the server can answer at all
+async fn healthz() -> &'static str {
+    "ok\n"
+}
+
+/// 200 when every mounted library can serve clients, otherwise 503. Either way one line
+/// per library says how it is doing
+async fn readyz(State(mounts): State<Arc<Vec<Mount>>>) -> Response {
+    let mut ready = true;
+    let mut body = String::new();
+    for mount in mounts.iter() {
+        let name = if mount.prefix.is_empty() { "library" } else { &mount.prefix[1..] };
+        match mount.patterns.readiness() {
+            Ok(count) => body.push_str(&format!("{}: ready, {} patterns\n", name, count)),
+            Err(problem) => {
+                ready = false;
+                body.push_str(&format!("{}: not ready, {}\n", name, problem));
+            }
+        }
+    }
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, body).into_response()
+}
+
+/// Liveness and readiness probes for the mounts, outside any authentication
+fn probes(mounts: &[Mount]) -> Router {
+    Router::new()
+        .route(HEALTH_PATH, get(healthz))
+        .route(READY_PATH, get(readyz))
+        .with_state(Arc::new(mounts.to_vec()))
+}
+
+/// Refuse request bodies over `max` bytes with `413 Payload Too Large`
+async fn limit_body(State(max): State<usize>, request: Request, next: Next) -> Response {
+    let (parts, request_body) = request.into_parts();
+    match body::to_bytes(request_body, max).await {
+        Ok(bytes) => next.run(Request::from_parts(parts, Body::from(bytes))).await,
+        Err(_) => (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Request bodies are limited to {} bytes\n", max),
+        )
+            .into_response(),
+    }
+}
+
+/// Which HTTP transports to serve
+#[derive(Debug, Clone, Copy)]
+pub struct Endpoints {
+    /// Streamable HTTP at `/mcp`
+    pub streamable: bool,
+    /// HTTP+SSE at `/sse` and `/message`
+    pub sse: bool,
+}
+
+/// How the HTTP transports are served
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Interval of keep-alive pings on idle event streams
+    pub keep_alive: Duration,
+    pub endpoints: Endpoints,
+    /// Certificate to serve HTTPS with, plain HTTP when unset
+    pub tls: Option<Tls>,
+    /// Largest request body accepted
+    pub max_body: usize,
+}
+
+/// Streamable HTTP endpoints for the mounts, reached over `scheme`
+fn streamable_router(
+    mounts: &[Mount],
+    scheme: &str,
+    address: SocketAddr,
+    keep_alive: Duration,
+) -> Router {
+    let mut router = Router::new();
+    for Mount { prefix, patterns, keys } in mounts.iter().cloned() {
+        let service = StreamableHttpService::new(
+            move || Ok(patterns.remote_session()),
+            Arc::new(LocalSessionManager::default()),
+            StreamableHttpServerConfig {
+                sse_keep_alive: Some(keep_alive),
+                stateful_mode: true,
+            },
+        );
+        let path = format!("{}{}", prefix, MCP_PATH);
+        tracing::info!("Serving MCP over HTTP at {}://{}{}", scheme, address, path);
+        router = router.merge(authorized(Router::new().nest_service(&path, service), keys));
+    }
+    router
+}
+
+/// HTTP+SSE endpoints for the mounts, reached over `scheme`. Each event stream is one
+/// session, closed along with its background tasks when the client disconnects
+fn sse_router(mounts: &[Mount], scheme: &str, bind: SocketAddr, keep_alive: Duration) -> Router {
+    let ct = CancellationToken::new();
+    let mut router = Router::new();
+    for Mount { prefix, patterns, keys } in mounts.iter().cloned() {
+        let (sse_path, post_path) =
+            (format!("{}{}", prefix, SSE_PATH), format!("{}{}", prefix, MESSAGE_PATH));
+        let (mut server, sse_router) = SseServer::new(SseServerConfig {
+            bind,
+            sse_path: sse_path.clone(),
+            post_path: post_path.clone(),
+            ct: ct.clone(),
+            sse_keep_alive: Some(keep_alive),
+        });
+
+        let ct = ct.clone();
+        tokio::spawn(async move {
+            while let Some(transport) = server.next_transport().await {
+                let session = patterns.remote_session();
+                let ct = ct.child_token();
+                tokio::spawn(async move {
+                    tracing::info!("SSE client connected");
+                    match session.clone().serve_with_ct(transport, ct).await {
+                        Ok(running) => {
+                            let _ = running.waiting().await;
+                        }
+                        Err(e) => tracing::warn!("SSE session failed to initialize: {}", e),
+                    }
+                    session.close();
+                    tracing::info!("SSE client disconnected, session closed");
+                });
+            }
+        });
+
+        tracing::info!(
+            "Serving MCP over SSE at {}://{}{} (requests to {})",
+            scheme,
+            bind,
+            sse_path,
+            post_path
+        );
+        router = router.merge(authorized(sse_router, keys));
+    }
+    router
+}
+
+/// Serve the endpoints for the mounts on `listen` until the server fails, over HTTPS
+/// when given a certificate. New connections are refused once `shutdown` is cancelled
+pub async fn serve(
+    mounts: Vec<Mount>,
+    listen: &str,
+    options: Options,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let Options {
+        keep_alive,
+        endpoints,
+        tls,
+        max_body,
+    } = options;
+    let acceptor = tls.as_ref().map(tls::acceptor).transpose()?;
+    let listener = bind(listen).await?;
+    let address = listener.local_addr()?;
+    let scheme = if acceptor.is_some() { "https" } else { "http" };
+    let mut router = probes(&mounts);
+    if endpoints.streamable {
+        router = router.merge(streamable_router(&mounts, scheme, address, keep_alive));
+    }
+    if endpoints.sse {
+        router = router.merge(sse_router(&mounts, scheme, address, keep_alive));
+    }
+    let router = router.layer(middleware::from_fn_with_state(max_body, limit_body));
+    // Client addresses are needed to rate limit each client's tool calls
+    let service = router.into_make_service_with_connect_info::<ClientAddress>();
+    let shutdown = shutdown.cancelled_owned();
+    match acceptor {
+        Some(acceptor) => {
+            axum::serve(TlsListener::new(listener, acceptor)?, service)
+                .with_graceful_shutdown(shutdown)
+                .await
+        }
+        None => axum::serve(listener, service).with_graceful_shutdown(shutdown).await,
+    }
+    .context("HTTP server failed")
+}
@@ -0,0 +1,227 @@
+//! `init` subcommand: scaffold a patterns directory and write a `grimoire.toml` for it,
+//! or migrate an environment variable setup to one
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use grimoire_core::{
+    config::{Config, ENV_CONFIG, ENV_PATTERNS_DIR, IGNORE_FILE},
+    library::{self, Pattern},
+};
+
+/// Name of the directory created next to the config when no patterns directory is given
+const DEFAULT_PATTERNS_DIR: &str = "patterns";
+/// Starting point for new patterns, never loaded as one
+const TEMPLATE_FILE: &str = "TEMPLATE.md";
+/// File name of the example pattern written to an empty patterns directory
+const EXAMPLE_FILE: &str = "example-retry-with-backoff.md";
+
+const TEMPLATE: &str = r#"---
+pattern: my-pattern-name
+category: rust
+framework: tokio
+tags: [first-tag, second-tag]
+description: One line on what the pattern is for
+---
+
+Copy this file to `<pattern>.md` to start a new pattern. It is listed in
+.grimoireignore, so it is never loaded as one itself.
+
+## When to use
+
+The problem the pattern solves, and when to reach for something else.
+
+## Pattern
+
+```rust
+// The code, trimmed to what matters
+```
+
+## Pitfalls
+
+What tends to go wrong, and how to avoid it.
+"#;
+
+const IGNORE: &str = "\
+# Files in this directory that are never loaded as patterns, one glob per line,
+# on top of `ignore` in grimoire.toml
+TEMPLATE.md
+README.md
+";
+
+/// The example pattern, with a fresh ID
+fn example() -> String {
+    format!(
+        r#"---
+pattern: example-retry-with-backoff
+id: {}
+category: rust
+framework: tokio
+tags: [resilience, async, example]
+description: Retry a fallible async call with exponential backoff
+---
+
+An example pattern written by `grimoire-mcp init`. Edit or delete it once the
+library has patterns of its own.
+
+## When to use
+
+Calls to services that fail now and then, where trying again a moment later
+usually works.
+
+## Pattern
+
+```rust
+async fn with_backoff<T, E, F, Fut>(mut call: F, attempts: u32) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+{{
+    let mut delay = std::time::Duration::from_millis(100);
+    for _ in 1..attempts {{
+        match call().await {{
+            Ok(value) => return Ok(value),
+            Err(_) => tokio::time::sleep(delay).await,
+        }}
+        delay *= 2;
+    }}
+    call().await
+}}
+```
+
+## Pitfalls
+
+Only retry errors that can go away, and cap the attempts so a dead service
+fails fast.
+"#,
+        library::new_id()
+    )
+}
+
+/// Scaffold the patterns directory, write a config for it and print client configuration.
+/// `patterns_dir` defaults to `PATTERNS_DIR`, or else a `patterns` directory next to the
+/// config
+pub fn run(output: Option<PathBuf>, patterns_dir: Option<PathBuf>, force: bool) -> Result<()> {
+    let config_path = output
+        .or_else(Config::path)
+        .context("Cannot determine a config location, pass --output")?;
+    let config_path = std::path::absolute(config_path)?;
+    if config_path.exists() && !force {
+        anyhow::bail!(
+            "{:?} already exists, pass --force to overwrite it",
+            config_path
+        );
+    }
+
+    let patterns_dir = patterns_dir
+        .or_else(|| std::env::var_os(ENV_PATTERNS_DIR).map(PathBuf::from))
+        .unwrap_or_else(|| {
+            config_path.parent().unwrap_or(Path::new(".")).join(DEFAULT_PATTERNS_DIR)
+        });
+    // The server may be started from anywhere
+    let patterns_dir = std::path::absolute(&patterns_dir)?;
+    let config = Config::recommended(Some(patterns_dir.clone()));
+    scaffold(&patterns_dir, &config)?;
+    inspect_patterns_dir(&patterns_dir, &config);
+
+    let toml = format!(
+        "# Generated by `grimoire-mcp init`\n# {} still overrides patterns_dir when set\n\n{}",
+        ENV_PATTERNS_DIR,
+        toml::to_string_pretty(&config)?
+    );
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&config_path, toml)
+        .with_context(|| format!("Failed to write config file {:?}", config_path))?;
+    println!("\nWrote {}", config_path.display());
+
+    print_client_snippets(&config_path)
+}
+
+/// Create the patterns directory with the files a new library starts with, leaving any
+/// that exist alone. The example pattern is only written to a directory without patterns
+fn scaffold(dir: &Path, config: &Config) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create the patterns directory {:?}", dir))?;
+    let config = config.with_ignore_file(dir);
+    let has_patterns = fs::read_dir(dir)?
+        .flat_map(|e| e.ok())
+        .any(|e| config.is_pattern_file(&e.path()));
+    let mut files = vec![(TEMPLATE_FILE, TEMPLATE.to_string()), (IGNORE_FILE, IGNORE.to_string())];
+    if !has_patterns {
+        files.push((EXAMPLE_FILE, example()));
+    }
+    for (name, content) in files {
+        let path = dir.join(name);
+        if path.exists() {
+            continue;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write {:?}", path))?;
+        println!("Created {}", path.display());
+    }
+    Ok(())
+}
+
+/// Report what the loader would make of the patterns directory
+fn inspect_patterns_dir(dir: &Path, config: &Config) {
+    println!("\npatterns_dir = {}", dir.display());
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("  warning: cannot read the directory: {}", e);
+            return;
+        }
+    };
+
+    let config = config.with_ignore_file(dir);
+    let files: Vec<PathBuf> = entries
+        .flat_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| config.is_pattern_file(p))
+        .collect();
+    let invalid: Vec<&PathBuf> = files
+        .iter()
+        .filter(|p| Pattern::load(p).is_none())
+        .collect();
+
+    println!(
+        "  {} pattern files, {} valid patterns",
+        files.len(),
+        files.len() - invalid.len()
+    );
+    for path in invalid {
+        println!(
+            "  skipped (missing or invalid frontmatter): {}",
+            path.display()
+        );
+    }
+}
+
+fn print_client_snippets(config_path: &Path) -> Result<()> {
+    let command = std::env::current_exe()?.display().to_string();
+    let server = json!({
+        "command": command,
+        "env": { ENV_CONFIG: config_path.display().to_string() }
+    });
+
+    println!("\nAmazon Q CLI / Claude Desktop:");
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({ "mcpServers": { "pattern-library": server } }))?
+    );
+    println!("\nCursor:");
+    println!(
+        "{}",
+        serde_json::to_string_pretty(
+            &json!({ "mcp": { "servers": { "pattern-library": server } } })
+        )?
+    );
+    Ok(())
+}
@@ -0,0 +1,241 @@
+//! Log messages sent to the client over MCP, and written to a log file
+//!
+//! Besides stderr, every `tracing` event at or above the level the client asked
+//! for with `logging/setLevel` is forwarded as a `notifications/message`, so
+//! clients can follow the server's logs without access to its stderr. When a
+//! log file is configured, events are also appended to it as JSON lines, which
+//! outlive the session for debugging tool failures after the fact.
+
+use std::{
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock},
+};
+
+use chrono::{SecondsFormat, Utc};
+use grimoire_core::config::Logging;
+
+use rmcp::{
+    Peer, RoleServer,
+    model::{LoggingLevel, LoggingMessageNotificationParam},
+};
+use serde_json::{Map, Value};
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{Layer, layer::Context};
+
+/// Level used until the client sets one
+const DEFAULT_LEVEL: LoggingLevel = LoggingLevel::Warning;
+
+/// The connected client and the lowest level it wants to receive
+static CLIENT: RwLock<Option<(Peer<RoleServer>, LoggingLevel)>> = RwLock::new(None);
+
+/// Start sending log messages to a newly connected client
+pub fn connect(peer: Peer<RoleServer>) {
+    let mut client = CLIENT.write().unwrap_or_else(|e| e.into_inner());
+    let level = client.as_ref().map_or(DEFAULT_LEVEL, |(_, level)| *level);
+    *client = Some((peer, level));
+}
+
+/// Change the lowest level sent to the client
+pub fn set_level(peer: Peer<RoleServer>, level: LoggingLevel) {
+    *CLIENT.write().unwrap_or_else(|e| e.into_inner()) = Some((peer, level));
+}
+
+/// Order of severity, as `LoggingLevel` has none
+fn severity(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+fn logging_level(level: &Level) -> LoggingLevel {
+    match *level {
+        Level::TRACE | Level::DEBUG => LoggingLevel::Debug,
+        Level::INFO => LoggingLevel::Info,
+        Level::WARN => LoggingLevel::Warning,
+        Level::ERROR => LoggingLevel::Error,
+    }
+}
+
+/// Collects an event's message and fields into a JSON object
+#[derive(Default)]
+struct JsonVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl Visit for JsonVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            self.fields
+                .insert(field.name().to_string(), Value::String(format!("{:?}", value)));
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.fields
+                .insert(field.name().to_string(), Value::String(value.to_string()));
+        }
+    }
+}
+
+/// Forwards events to the connected client
+pub struct ClientLayer;
+
+impl<S: Subscriber> Layer<S> for ClientLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let target = event.metadata().target();
+        // Only the server's own events, as rmcp logs while sending the notification
+        if !target.starts_with(env!("CARGO_CRATE_NAME")) && !target.starts_with("grimoire_core") {
+            return;
+        }
+        let level = logging_level(event.metadata().level());
+        let Some(peer) = CLIENT
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .filter(|(_, min)| severity(level) >= severity(*min))
+            .map(|(peer, _)| peer.clone())
+        else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        let data = if visitor.fields.is_empty() {
+            Value::String(visitor.message)
+        } else {
+            visitor
+                .fields
+                .insert("message".to_string(), Value::String(visitor.message));
+            Value::Object(visitor.fields)
+        };
+        let param = LoggingMessageNotificationParam {
+            level,
+            logger: Some(target.to_string()),
+            data,
+        };
+        runtime.spawn(async move {
+            let _ = peer.notify_logging_message(param).await;
+        });
+    }
+}
+
+/// A file appended to until it reaches its size limit, then moved to `<file>.1` with the
+/// older ones shifted up to `<file>.<keep>`
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+            keep,
+        })
+    }
+
+    /// Path of the `n`th most recently rotated file
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    /// Start a new file, keeping the current one as `<file>.1` unless none are kept
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let _ = fs::rename(self.rotated(n), self.rotated(n + 1));
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64;
+        if self.max_bytes > 0 && self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+/// Appends events to the configured log file as JSON lines
+pub struct FileLayer {
+    file: Mutex<RotatingFile>,
+}
+
+impl FileLayer {
+    /// The layer for the configured log file, `None` when there is none
+    pub fn new(logging: &Logging) -> io::Result<Option<Self>> {
+        let Some(path) = &logging.file else {
+            return Ok(None);
+        };
+        let file = RotatingFile::open(path, logging.max_bytes, logging.keep)?;
+        Ok(Some(Self {
+            file: Mutex::new(file),
+        }))
+    }
+}
+
+impl<S: Subscriber> Layer<S> for FileLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        let mut line = Map::new();
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+        line.insert("timestamp".to_string(), Value::String(timestamp));
+        let metadata = event.metadata();
+        line.insert("level".to_string(), Value::String(metadata.level().to_string()));
+        line.insert("target".to_string(), Value::String(metadata.target().to_string()));
+        line.insert("message".to_string(), Value::String(visitor.message));
+        if !visitor.fields.is_empty() {
+            line.insert("fields".to_string(), Value::Object(visitor.fields));
+        }
+
+        let mut line = Value::Object(line).to_string();
+        line.push('\n');
+        // A failing write can't be logged anywhere else, and must not fail the server
+        let _ = self.file.lock().unwrap_or_else(|e| e.into_inner()).write_line(&line);
+    }
+}
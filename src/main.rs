@@ -1,25 +1,579 @@
-use anyhow::Result;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    pin::pin,
+};
+
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, error::ErrorKind};
+use grimoire_core::{
+    archive, check,
+    config::{Access, Config, Tenant},
+    fabric, git,
+    library::{Library, Pattern},
+    publish,
+};
+use notify::RecommendedWatcher;
 use rmcp::{ServiceExt, transport::stdio};
-use tracing_subscriber::{self, EnvFilter};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing_subscriber::{self, EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
+mod cancel;
+mod cli;
+mod doctor;
+mod http;
+mod init;
+mod logging;
 mod patterns;
+mod rate_limit;
+mod recording;
+mod tcp;
+mod tls;
+mod watch;
 use patterns::Patterns;
+use recording::{Recorder, Redactor};
+
+/// A Model Context Protocol server for managing software development patterns
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Config file to use instead of GRIMOIRE_CONFIG or the default location
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Patterns directory, overriding PATTERNS_DIR and patterns_dir in the config
+    #[arg(long, global = true, value_name = "DIR")]
+    patterns_dir: Option<PathBuf>,
+
+    /// Most verbose level logged to stderr: error, warn, info, debug or trace (debug when
+    /// serving, error for search and get). RUST_LOG can still refine it per module
+    #[arg(long, global = true, value_name = "LEVEL")]
+    log_level: Option<tracing::Level>,
+
+    /// Hide every tool that changes anything, whatever GRIMOIRE_READ_ONLY and the config say
+    #[arg(long, global = true)]
+    read_only: bool,
+
+    /// Record every tool request and response as numbered JSON files in this directory,
+    /// in a subdirectory per tenant when serving tenants
+    #[arg(long, value_name = "DIR")]
+    record: Option<PathBuf>,
+
+    /// Additional JSON key to redact from recordings (can be repeated)
+    #[arg(long = "redact", value_name = "KEY")]
+    redact: Vec<String>,
+
+    /// How clients connect: stdio for a single client that started the server, http
+    /// (streamable HTTP), sse (HTTP+SSE, for older web clients) or tcp (newline-delimited
+    /// JSON) for any number of clients over the network. Can be repeated to serve several
+    /// at once, and overrides the transports enabled in the config (stdio by default)
+    #[arg(long, value_enum, value_name = "TRANSPORT")]
+    transport: Vec<Transport>,
+
+    /// Address to listen on (defaults to [http] listen in the config, 127.0.0.1:7422, or
+    /// [tcp] listen, 127.0.0.1:7423)
+    #[arg(long, value_name = "ADDR")]
+    listen: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Transport {
+    Stdio,
+    Http,
+    Sse,
+    Tcp,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Re-execute a recorded session against the pattern library and report differences
+    Replay {
+        /// Directory containing a session captured with --record
+        dir: PathBuf,
+    },
+    /// Pack a patterns directory into a read-only .grimoire archive
+    Archive {
+        /// Patterns directory to pack
+        dir: PathBuf,
+        /// Archive file to write
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Write the patterns marked `publish: true` to a directory, without internal details
+    ExportPublished {
+        /// Directory to write the published patterns to, e.g. a checkout of the blog's content
+        #[arg(short, long, value_name = "DIR")]
+        output: PathBuf,
+    },
+    /// Compare the patterns with an earlier git revision, failing on changes that break
+    /// references: removed or renamed patterns, lost metadata and broken links
+    Check {
+        /// Git revision to compare with, e.g. origin/main
+        #[arg(long, value_name = "REV")]
+        against: String,
+    },
+    /// Import Fabric patterns, each a directory holding a system.md, as prompt patterns
+    ImportFabric {
+        /// Fabric's patterns directory, e.g. ~/.config/fabric/patterns
+        dir: PathBuf,
+        /// Category of the imported patterns (defaults to the prompt category)
+        #[arg(long, value_name = "CATEGORY")]
+        category: Option<String>,
+        /// Overwrite pattern files with the same names
+        #[arg(long)]
+        force: bool,
+    },
+    /// Export patterns as Fabric pattern directories
+    ExportFabric {
+        /// Directory to write the Fabric patterns to
+        #[arg(short, long, value_name = "DIR")]
+        output: PathBuf,
+        /// Only patterns in this category (defaults to the prompt category, can be repeated)
+        #[arg(long, value_name = "CATEGORY")]
+        category: Vec<String>,
+        /// Only patterns with this tag (can be repeated)
+        #[arg(long, value_name = "TAG")]
+        tag: Vec<String>,
+    },
+    /// Create a patterns directory and a grimoire.toml for it, and print client configuration
+    Init {
+        /// Where to write the config (defaults to GRIMOIRE_CONFIG or ~/.config/grimoire-mcp/grimoire.toml)
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check the setup and print a report to attach to bug reports: the directories, every
+    /// pattern file's frontmatter, duplicate names and the effective configuration
+    Doctor,
+    /// Search the pattern library from the terminal
+    Search {
+        /// Search query, with the same syntax as the search_patterns tool
+        query: String,
+        #[command(flatten)]
+        filters: cli::SearchFilters,
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a pattern, optionally a single section with name#section
+    Get {
+        /// Pattern name or alias
+        name: String,
+        /// Print the pattern's metadata and content as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize the tracing subscriber with file and stdout logging
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env().add_directive(tracing::Level::DEBUG.into()))
-        .with_writer(std::io::stderr)
-        .with_ansi(false)
+    let cli = Cli::parse();
+    if let Some(Command::Init { output, force }) = cli.command {
+        return init::run(output.or(cli.config), cli.patterns_dir, force);
+    }
+    if let Some(Command::Search { .. } | Command::Get { .. } | Command::Doctor) = &cli.command {
+        // Only errors by default, so the output stays readable and pipeable
+        let mut filter = EnvFilter::from_default_env();
+        if let Some(level) = cli.log_level {
+            filter = filter.add_directive(level.into());
+        }
+        tracing_subscriber::fmt().with_writer(std::io::stderr).with_env_filter(filter).init();
+        let config = load_config(&cli)?;
+        return match cli.command {
+            Some(Command::Search {
+                query,
+                filters,
+                json,
+            }) => cli::search(config, query, filters, json),
+            Some(Command::Get { name, json }) => cli::get(config, name, json),
+            Some(Command::Doctor) => {
+                let path = cli.config.clone().or_else(|| Config::path().filter(|p| p.exists()));
+                doctor::run(&config, path.as_deref())
+            }
+            _ => Ok(()),
+        };
+    }
+
+    // Log to stderr, and the log file if there is one, as RUST_LOG allows, and to the client
+    // at the level it sets
+    let config = load_config(&cli)?;
+    let filter = || {
+        EnvFilter::from_default_env()
+            .add_directive(cli.log_level.unwrap_or(tracing::Level::DEBUG).into())
+    };
+    let log_file = logging::FileLayer::new(&config.logging).with_context(|| {
+        format!("Failed to open the log file {:?}", config.logging.file.clone().unwrap_or_default())
+    })?;
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_ansi(false)
+                .with_filter(filter()),
+        )
+        .with(log_file.map(|layer| layer.with_filter(filter())))
+        .with(logging::ClientLayer)
         .init();
 
+    if !matches!(cli.command, Some(Command::Archive { .. })) {
+        require_patterns_dir(&config);
+    }
+    if let Some(Command::Archive { dir, output }) = &cli.command {
+        let index = archive::build(dir, output, &config)?;
+        println!("Packed {} files into {}", index.files.len(), output.display());
+        return Ok(());
+    }
+    if let Some(Command::ExportPublished { output }) = &cli.command {
+        let dir = require_patterns_dir(&config);
+        let library = Library::load(&config, dir, None);
+        let export = publish::export(&library, output)?;
+        for (name, reason) in &export.skipped {
+            println!("Skipped '{}': {}", name, reason);
+        }
+        for path in &export.removed {
+            println!("Removed {}, no longer published", path.display());
+        }
+        println!("Exported {} patterns to {}", export.written.len(), output.display());
+        return Ok(());
+    }
+    if let Some(Command::Check { against }) = &cli.command {
+        let dir = require_patterns_dir(&config);
+        // Archives and mounts aren't versioned with the patterns directory
+        let local = Config {
+            namespace: None,
+            archives: Vec::new(),
+            mounts: Vec::new(),
+            ..config.clone()
+        };
+        let current = Library::from_patterns(Library::load_patterns(&local, &[dir.to_path_buf()]));
+        let reference = Library::from_patterns(
+            git::read_revision(dir, against, &local)?
+                .into_iter()
+                .filter_map(|(file, content)| Pattern::parse(&content, &dir.join(file)))
+                .collect(),
+        );
+        let problems = check::check(&current, &reference, dir);
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        println!(
+            "Checked {} patterns against {} patterns at {}: {} problems",
+            current.patterns.len(),
+            reference.patterns.len(),
+            against,
+            problems.len()
+        );
+        if !problems.is_empty() {
+            anyhow::bail!("{} changes break references to patterns at {}", problems.len(), against);
+        }
+        return Ok(());
+    }
+    if let Some(Command::ImportFabric { dir, category, force }) = &cli.command {
+        let patterns_dir = require_patterns_dir(&config);
+        let category = category.as_deref().unwrap_or(config.prompt_category());
+        let import = fabric::import(dir, patterns_dir, category, *force)?;
+        for (name, reason) in &import.skipped {
+            println!("Skipped '{}': {}", name, reason);
+        }
+        println!(
+            "Imported {} Fabric patterns into {}",
+            import.written.len(),
+            patterns_dir.display()
+        );
+        return Ok(());
+    }
+    if let Some(Command::ExportFabric { output, category, tag }) = &cli.command {
+        let dir = require_patterns_dir(&config);
+        let library = Library::load(&config, dir, None);
+        let categories = if category.is_empty() {
+            vec![config.prompt_category().to_string()]
+        } else {
+            category.clone()
+        };
+        let selected: Vec<_> = library
+            .patterns
+            .iter()
+            .filter(|p| categories.contains(&p.metadata.category))
+            .filter(|p| tag.is_empty() || p.metadata.tags.iter().any(|t| tag.contains(t)))
+            .collect();
+        let export = fabric::export(&library, &selected, output)?;
+        for (name, reason) in &export.skipped {
+            println!("Skipped '{}': {}", name, reason);
+        }
+        println!("Exported {} patterns to {}", export.written.len(), output.display());
+        return Ok(());
+    }
+
+    let redactor = Redactor::new(&cli.redact, config.patterns_dir.as_deref());
+
+    if let Some(Command::Replay { dir }) = &cli.command {
+        return recording::replay(dir, config, redactor).await;
+    }
+
     tracing::info!("Starting Grimoire-MCP server");
 
+    let transports = if cli.transport.is_empty() {
+        [
+            (config.stdio.enabled, Transport::Stdio),
+            (config.http.enabled, Transport::Http),
+            (config.http.sse, Transport::Sse),
+            (config.tcp.enabled, Transport::Tcp),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, transport)| enabled.then_some(transport))
+        .collect()
+    } else {
+        cli.transport.clone()
+    };
+    if transports.is_empty() {
+        anyhow::bail!("No transport enabled, pass --transport or enable one in the config");
+    }
+    let endpoints = http::Endpoints {
+        streamable: transports.contains(&Transport::Http),
+        sse: transports.contains(&Transport::Sse),
+    };
+    let serves_http = endpoints.streamable || endpoints.sse;
+    let serves_tcp = transports.contains(&Transport::Tcp);
+    let serves_stdio = transports.contains(&Transport::Stdio);
+    let (http_listen, tcp_listen) = match cli.listen.clone() {
+        Some(_) if serves_http && serves_tcp => anyhow::bail!(
+            "--listen can't be used to serve HTTP and TCP together, set [http] listen and \
+             [tcp] listen in the config instead"
+        ),
+        Some(listen) => (listen.clone(), listen),
+        None => (config.http.listen.clone(), config.tcp.listen.clone()),
+    };
+    let keep_alive = config.http.keep_alive();
+    let tokens = config.auth.resolve()?;
+    if tokens.is_empty() && (serves_tcp || serves_http && config.tenants.is_empty()) {
+        tracing::warn!(
+            "No [auth] tokens configured, any client that can connect can change the library"
+        );
+    }
+
+    // Every transport shares one service, and so one library, unless tenants have their own
+    let tenants = serves_http && !config.tenants.is_empty();
+    let mut watchers = Vec::new();
+    let patterns = if serves_stdio || serves_tcp || !tenants {
+        if !config.tenants.is_empty() {
+            tracing::warn!("Tenants are only served over HTTP, other clients get patterns_dir");
+        }
+        require_patterns_dir(&config);
+        let (patterns, started) = start(config.clone(), cli.record.clone().map(|d| (d, redactor)))?;
+        watchers.extend(started);
+        Some(patterns)
+    } else {
+        None
+    };
+
+    let shutdown = CancellationToken::new();
+    // Every library served, to drain on shutdown
+    let mut libraries: Vec<Patterns> = patterns.iter().cloned().collect();
+    // The tenant each library is served for, to apply changes to the config file to
+    let mut configured: Vec<(Patterns, Option<Tenant>)> =
+        patterns.iter().map(|p| (p.clone(), None)).collect();
+    let mut servers = JoinSet::new();
+    if serves_http {
+        let mounts = match &patterns {
+            Some(patterns) if !tenants => vec![http::Mount {
+                prefix: String::new(),
+                patterns: patterns.clone(),
+                keys: tokens.clone(),
+            }],
+            _ => {
+                // Each tenant gets its own service, and with it its own index, caches and
+                // recording
+                let mut names = BTreeSet::new();
+                let mut mounts = Vec::new();
+                for tenant in &config.tenants {
+                    tenant.validate()?;
+                    if !names.insert(&tenant.name) {
+                        anyhow::bail!("Tenant '{}' is configured twice", tenant.name);
+                    }
+                    let redactor = Redactor::new(&cli.redact, Some(&tenant.patterns_dir));
+                    let record = cli.record.as_ref().map(|dir| (dir.join(&tenant.name), redactor));
+                    let (patterns, started) = start(config.for_tenant(tenant), record)?;
+                    watchers.extend(started);
+                    libraries.push(patterns.clone());
+                    configured.push((patterns.clone(), Some(tenant.clone())));
+                    mounts.push(http::Mount {
+                        prefix: format!("/{}", tenant.name),
+                        patterns,
+                        keys: tenant.keys.iter().map(|k| (k.clone(), Access::Write)).collect(),
+                    });
+                }
+                mounts
+            }
+        };
+        let options = http::Options {
+            keep_alive,
+            endpoints,
+            tls: config.http.tls.clone(),
+            max_body: config.limits.max_request_bytes,
+        };
+        let shutdown = shutdown.clone();
+        servers.spawn(async move { http::serve(mounts, &http_listen, options, shutdown).await });
+    }
+    if let Some(patterns) = patterns.clone().filter(|_| serves_tcp) {
+        let shutdown = shutdown.clone();
+        let max_message = config.limits.max_request_bytes;
+        servers.spawn(async move {
+            tcp::serve(patterns, &tcp_listen, tokens, max_message, shutdown).await
+        });
+    }
+
+    // Settings such as read-only mode, validation and mounts are picked up when the config
+    // file is saved, without dropping any client
+    if let Some(path) = cli.config.clone().or_else(|| Config::path().filter(|p| p.exists())) {
+        match watch_config(&path, cli.read_only, configured) {
+            Ok(watcher) => watchers.push(watcher),
+            Err(e) => tracing::warn!("Not watching the config file {:?}: {}", path, e),
+        }
+    }
+
     // Create an instance of our router
-    let service = Patterns::new().serve(stdio()).await.inspect_err(|e| {
-        tracing::error!("serving error: {:?}", e);
-    })?;
+    let service = match patterns.filter(|_| serves_stdio) {
+        Some(patterns) => Some(patterns.serve(stdio()).await.inspect_err(|e| {
+            tracing::error!("serving error: {:?}", e);
+        })?),
+        None => None,
+    };
+    // Pinned outside the select, so the session stays open while its calls finish
+    let mut stdio_closed = pin!(async {
+        match service {
+            Some(service) => service.waiting().await.map(|_| ()).context("stdio session failed"),
+            None => std::future::pending().await,
+        }
+    });
+
+    // The process ends with the stdio session, when a network transport fails, or when
+    // it is asked to stop, taking the other transports down with it
+    let result = tokio::select! {
+        result = &mut stdio_closed => result,
+        Some(result) = servers.join_next() => result.context("Server task panicked").flatten(),
+        () = shutdown_signal() => {
+            tracing::info!("Shutting down");
+            Ok(())
+        }
+    };
+
+    // Stop taking new connections and tool calls, and let the calls in progress finish
+    // writing before the process exits
+    shutdown.cancel();
+    drop(watchers);
+    let grace = config.limits.shutdown_grace();
+    for library in &libraries {
+        library.shutdown(grace).await;
+    }
+    if serves_stdio {
+        // Reading stdin holds a runtime thread until the client sends another line, which
+        // would keep the process alive after a signal
+        if let Err(e) = &result {
+            eprintln!("Error: {:?}", e);
+        }
+        std::process::exit(i32::from(result.is_err()));
+    }
+    result
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM as sent by container runtimes and systemd
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Not handling SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        Ok(()) = tokio::signal::ctrl_c() => {}
+        () = terminate => {}
+    }
+}
+
+/// The config file chosen on the command line or by the environment, with the command
+/// line's overrides applied
+fn load_config(cli: &Cli) -> Result<Config> {
+    let mut config = Config::load_from(cli.config.as_deref())?;
+    if let Some(dir) = &cli.patterns_dir {
+        config.patterns_dir = Some(dir.clone());
+    }
+    if cli.read_only {
+        config.read_only = true;
+    }
+    Ok(config)
+}
+
+/// The configured patterns directory, exiting with a usage error when there is none
+pub fn require_patterns_dir(config: &Config) -> &Path {
+    match config.patterns_dir.as_deref() {
+        Some(dir) => dir,
+        None => Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "No patterns directory configured, pass --patterns-dir, set PATTERNS_DIR or run \
+                 `grimoire-mcp init`",
+            )
+            .exit(),
+    }
+}
+
+/// The service for a library, recording tool calls if given a directory to record to,
+/// with the watcher keeping it in sync with edits made outside the server
+fn start(
+    config: Config,
+    record: Option<(PathBuf, Redactor)>,
+) -> Result<(Patterns, Option<RecommendedWatcher>)> {
+    let mut patterns = Patterns::new(config);
+    if let Some((dir, redactor)) = record {
+        tracing::info!("Recording tool calls to {:?}", dir);
+        patterns = patterns.with_recorder(Recorder::new(dir, redactor)?);
+    }
+
+    let watcher = patterns
+        .watch()
+        .inspect_err(|e| tracing::warn!("Not watching the patterns directory: {}", e))
+        .ok()
+        .flatten();
+    Ok((patterns, watcher))
+}
 
-    service.waiting().await?;
-    Ok(())
+/// Apply the config file at `path` to the libraries whenever it is saved, each tenant's
+/// library getting its own view of it. `--read-only` keeps applying to the new settings
+fn watch_config(
+    path: &Path,
+    read_only: bool,
+    libraries: Vec<(Patterns, Option<Tenant>)>,
+) -> notify::Result<RecommendedWatcher> {
+    let runtime = tokio::runtime::Handle::current();
+    let file = path.to_path_buf();
+    watch::watch_file(path, move || {
+        let _guard = runtime.enter();
+        let mut config = match Config::load_from(Some(&file)) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Keeping the current settings: {:#}", e);
+                return;
+            }
+        };
+        config.read_only |= read_only;
+        for (patterns, tenant) in &libraries {
+            match tenant {
+                Some(tenant) => patterns.apply_config(config.for_tenant(tenant)),
+                None => patterns.apply_config(config.clone()),
+            }
+        }
+    })
 }
@@ -1,49 +1,238 @@
 use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
     fs,
+    net::IpAddr,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
 };
 
+use arc_swap::ArcSwap;
+use axum::{extract::ConnectInfo, http::request::Parts};
+use notify::RecommendedWatcher;
 use rmcp::{
-    RoleServer, ServerHandler,
-    handler::server::{tool::ToolRouter, wrapper::Parameters},
+    Peer, RoleServer, ServerHandler,
+    handler::server::{
+        common::FromContextPart,
+        tool::{ToolCallContext, ToolRouter},
+        wrapper::Parameters,
+    },
     model::{
-        CallToolResult, Content, Implementation, InitializeRequestParam, InitializeResult,
-        ProtocolVersion, ServerCapabilities, ServerInfo,
+        Annotated, Annotations, CallToolRequestParam, CallToolResult, CompleteRequestParam,
+        CompleteResult, CompletionInfo, Content, CreateMessageRequestParam, GetPromptRequestParam,
+        GetPromptResult, Implementation, InitializeRequestParam, InitializeResult,
+        ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, Meta,
+        ModelPreferences, PaginatedRequestParam, Prompt, PromptArgument, PromptMessage,
+        PromptMessageRole,
+        ProtocolVersion, RawResource, RawResourceTemplate, ReadResourceRequestParam,
+        ReadResourceResult, Resource, ResourceContents, ResourceTemplate,
+        ResourceUpdatedNotificationParam, Role, SamplingMessage, ServerCapabilities, ServerInfo,
+        SetLevelRequestParam, SubscribeRequestParam, UnsubscribeRequestParam,
     },
     schemars,
-    service::RequestContext,
-    tool, tool_handler, tool_router,
+    service::{ElicitationError, NotificationContext, RequestContext},
+    tool, tool_router,
 };
+use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, Receiver, error::RecvError};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 use rmcp::ErrorData as McpError;
 
-const ENV_PATTERNS_DIR: &str = "PATTERNS_DIR";
+use grimoire_core::{
+    archive,
+    config::{Access, Config, DEFAULT_EXTENSIONS, Limits, normalize_optional},
+    dates,
+    diagnostics::{self, Diagnostic, Severity},
+    embeddings::{self, Embedder},
+    feedback::FeedbackStore,
+    flashcards,
+    frontmatter,
+    interpolate,
+    library::{
+        ConflictKind, INITIAL_VERSION, Library, PROVENANCE_END, Pattern, PatternMetadata,
+        add_usage_entry, derived_id, new_id, normalize, normalize_all, one_or_many,
+        provenance_marker, render_pattern, split_namespace, timestamp_now, validate_pattern_name,
+        without_usage_entries,
+    },
+    links,
+    quality,
+    query,
+    repair,
+    roots,
+    search,
+};
+
+use crate::{
+    cancel::{self, Deadline},
+    http::{self, ClientAddress},
+    logging,
+    rate_limit::RateLimiter,
+    recording::Recorder,
+    watch,
+};
 
 #[derive(Debug, Clone)]
 pub struct Patterns {
-    patterns: Arc<Vec<Pattern>>,
+    /// Settings, replaced as a whole when the config file changes
+    config: Arc<ArcSwap<Config>>,
+    patterns_dir: PathBuf,
+    /// The loaded library, replaced as a whole when patterns are reloaded. Reads load the
+    /// current snapshot without taking a lock, so concurrent searches never wait on each other
+    library: Arc<ArcSwap<Snapshot>>,
+    feedback: Arc<ArcSwap<FeedbackStore>>,
+    /// Held while building and publishing a new snapshot, so writers don't overwrite each
+    /// other's changes and generations are published in order. Readers never take it
+    publish: Arc<Mutex<()>>,
+    /// Embedding service for semantic search, when one is configured
+    embedder: Option<Arc<dyn Embedder>>,
     tool_router: ToolRouter<Self>,
+    recorder: Option<Arc<Recorder>>,
+    /// The connected client, for change notifications
+    peer: Arc<RwLock<Option<Peer<RoleServer>>>>,
+    /// Resource URIs the client subscribed to
+    subscriptions: Arc<RwLock<BTreeSet<String>>>,
+    /// Tools hidden from the client because they can't work in the current setup, with the reason
+    disabled_tools: Arc<RwLock<BTreeMap<Cow<'static, str>, &'static str>>>,
+    /// Pattern directories found under the client's roots, loaded with the patterns directory
+    root_dirs: Arc<RwLock<Vec<PathBuf>>>,
+    /// Watchers reloading the library when files in the root directories change
+    root_watchers: Arc<Mutex<Vec<RecommendedWatcher>>>,
+    /// Watchers reloading the library when files in the mounted directories change
+    mount_watchers: Arc<Mutex<Vec<RecommendedWatcher>>>,
+    /// Every reload, so each connected client is told what changed
+    reloads: broadcast::Sender<Arc<Reload>>,
+    /// Serving a client over the network, whose roots aren't paths on this machine
+    /// and who isn't sent the server's logs
+    remote: bool,
+    /// Cancelled once this session's client disconnects, ending its background tasks
+    closed: CancellationToken,
+    /// Patterns being written with draft_in_session, kept in memory until committed
+    drafts: Arc<Mutex<BTreeMap<String, SessionDraft>>>,
+    /// What this session's client may do, as granted by its token
+    access: Arc<RwLock<Access>>,
+    /// Tokens a client must send in the `authorization` key of its initialize request's
+    /// `_meta`, for transports without headers to carry them
+    tokens: Option<Arc<Vec<(String, Access)>>>,
+    /// Tool calls in progress in every session, waited for on shutdown
+    calls: TaskTracker,
+    /// Tool calls made by each network client, shared by all sessions
+    rate_limiter: Arc<RateLimiter>,
+    /// Address of the session's client when it is known for the whole session, as over TCP
+    client: Option<IpAddr>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Pattern {
-    metadata: PatternMetadata,
-    content: String,
-    filepath: PathBuf,
+/// MCP revisions the server speaks, newest first
+const PROTOCOL_VERSIONS: [ProtocolVersion; 3] = [
+    ProtocolVersion::V_2025_06_18,
+    ProtocolVersion::V_2025_03_26,
+    ProtocolVersion::V_2024_11_05,
+];
+
+/// The revision to answer an `initialize` request with: the one the client asked for
+/// when supported, otherwise the newest, which the client can accept or disconnect over
+fn negotiate(requested: &ProtocolVersion) -> ProtocolVersion {
+    PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| *version == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0])
+        .clone()
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PatternMetadata {
-    pattern: String,
-    category: String,
-    #[serde(default)]
-    framework: Option<String>,
-    #[serde(default)]
-    projects: Vec<String>,
-    #[serde(default)]
-    tags: Vec<String>,
+/// Whether the client asked for `revision` or a later one, so it understands what
+/// that revision added. Revisions are dates, which compare in order
+fn speaks(peer: &Peer<RoleServer>, revision: &ProtocolVersion) -> bool {
+    peer.peer_info().is_some_and(|info| info.protocol_version >= *revision)
+}
+
+/// Reloads kept for a client that is slow to take them, after which it skips to the latest
+const RELOAD_BACKLOG: usize = 16;
+
+/// `_meta` key of tool results carrying the generation of the library that answered
+const GENERATION_META: &str = "grimoire/generation";
+
+/// Tools that change pattern files, unavailable when the library can't be written
+const WRITE_TOOLS: &[&str] = &[
+    "create_pattern",
+    "record_pattern_usage",
+    "capture_note",
+    "rename_category",
+    "repair_pattern",
+    "resolve_id_conflicts",
+    "summarize_pattern",
+    "commit_draft",
+];
+
+/// Why write tools are hidden from a client with read-only access
+const READ_ONLY_ACCESS: &str = "your token only grants read access";
+
+/// Tools that only write when asked to, such as to an output file. They stay available in
+/// read-only mode and refuse the writing themselves
+const OPTIONAL_WRITE_TOOLS: &[&str] = &["export_flashcards"];
+
+/// Most similar dependency names suggested when no pattern imports the one asked for
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Most used dependencies listed by library_stats in markdown
+const STATS_DEPENDENCIES: usize = 20;
+
+/// Most characters of a pattern body sent to the client's model to summarize
+const SUMMARY_INPUT_CHARS: usize = 8000;
+/// Token budget for the generated description
+const SUMMARY_MAX_TOKENS: u32 = 120;
+
+/// Weights of the signals related_patterns combines
+const RELATED_TAG_WEIGHT: f64 = 0.4;
+const RELATED_FRAMEWORK_WEIGHT: f64 = 0.2;
+const RELATED_PROJECT_WEIGHT: f64 = 0.2;
+const RELATED_EMBEDDING_WEIGHT: f64 = 0.4;
+
+/// Share of the combined values two lists have in common, 0.0 when both are empty
+fn overlap(a: &[String], b: &[String]) -> f64 {
+    let shared = a.iter().filter(|v| b.contains(v)).count();
+    let total = a.len() + b.len() - shared;
+    if total == 0 {
+        0.0
+    } else {
+        shared as f64 / total as f64
+    }
+}
+
+/// Log a startup check, with the fix for any problem it found
+fn log_diagnostic(diagnostic: &Diagnostic) {
+    let Diagnostic { check, severity, message, remedy } = diagnostic;
+    let remedy = remedy.as_deref().unwrap_or_default();
+    match severity {
+        Severity::Ok => tracing::debug!("{}: {}", check, message),
+        Severity::Warning => tracing::warn!("{}: {}. {}", check, message, remedy),
+        Severity::Error => tracing::error!("{}: {}. {}", check, message, remedy),
+    }
+}
+
+/// A search hit, with any duplicate copies of the same logical pattern folded into it
+struct SearchHit<'a> {
+    pattern: &'a Pattern,
+    score: Option<f64>,
+    alternates: Vec<&'a Pattern>,
+}
+
+/// Collapse results describing the same logical pattern, keeping the first (best ranked) one
+fn dedup_results<'a>(results: Vec<(&'a Pattern, Option<f64>)>) -> Vec<SearchHit<'a>> {
+    let mut hits: Vec<SearchHit<'a>> = Vec::new();
+    for (pattern, score) in results {
+        match hits.iter_mut().find(|h| h.pattern.is_same_as(pattern)) {
+            Some(hit) => hit.alternates.push(pattern),
+            None => hits.push(SearchHit {
+                pattern,
+                score,
+                alternates: Vec::new(),
+            }),
+        }
+    }
+    hits
 }
 
 // === Request structs ===
@@ -51,21 +240,731 @@ pub struct PatternMetadata {
 /// Search parameters
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct PatternSearchRequest {
-    #[schemars(description = "Text Search")]
+    #[schemars(description = "Text Search. Supports AND, OR, NOT, \"quoted phrases\" and parentheses, e.g. 'tokio AND (channel OR mpsc) NOT deprecated', and tag:, category:, framework:, project:, namespace:, author:, maturity: and difficulty: qualifiers, e.g. 'retry tag:async category:rust'")]
     query: Option<String>,
-    #[schemars(description = "Filter by category")]
-    category: Option<String>,
-    #[schemars(description = "Filter by framework")]
-    framework: Option<String>,
-    #[schemars(description = "Filter by tag")]
-    tag: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Filter by category, matching any of the given categories")]
+    category: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Filter by framework, matching patterns tied to any of the given frameworks")]
+    framework: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Filter by tags, matching any of them unless match_all_tags is set")]
+    tag: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Filter by the projects a pattern was used in, matching any of them")]
+    project: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Filter by the namespace of the mounted library a pattern comes from, e.g. 'team', matching any of them")]
+    namespace: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Filter by author, matching any of them. Case is ignored and part of a name is enough, e.g. 'jane'")]
+    author: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Filter by maturity, e.g. 'battle-tested' to prefer production-proven patterns, matching any of them")]
+    maturity: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Filter by difficulty, e.g. 'beginner' or 'advanced', matching any of them")]
+    difficulty: Vec<String>,
+    #[serde(default)]
+    #[schemars(description = "Require patterns to have every tag in the tag filter")]
+    match_all_tags: bool,
+    #[serde(default)]
+    #[schemars(description = "Treat the query as a regular expression matched against names and bodies")]
+    regex: bool,
+    #[serde(default)]
+    #[schemars(description = "Match query words with the same case, e.g. 'Arc' but not 'arc'")]
+    case_sensitive: bool,
+    #[serde(default)]
+    #[schemars(description = "Match whole words only, without stemming or typo tolerance, so 'Arc' doesn't match 'architecture' or 'march'")]
+    whole_word: bool,
+    #[serde(default = "default_stemming")]
+    #[schemars(description = "Also match other forms of each word, so 'caching' finds 'cache' and 'cached' (default true). Turn off for exact technical terms")]
+    stemming: bool,
+    #[serde(default = "default_relax")]
+    #[schemars(description = "When nothing matches, retry without the tag filter, then without the framework filter, then matching any query word loosely, and label the results with what was dropped (default true)")]
+    relax: bool,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Exclude patterns with any of these tags")]
+    exclude_tags: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Exclude patterns in any of these categories")]
+    exclude_category: Vec<String>,
+    #[schemars(description = "Exclude patterns whose name or content contains this text")]
+    exclude_query: Option<String>,
+    #[schemars(description = "Only patterns created since this date: an ISO date, an amount of time like '30d' or '2 weeks ago', or a period like 'last quarter' (meaning since it began)")]
+    created_after: Option<String>,
+    #[schemars(description = "Only patterns created before this date, in the same formats as created_after")]
+    created_before: Option<String>,
+    #[schemars(description = "Only patterns created within this long, e.g. '30d' or '6 months'")]
+    created_within: Option<String>,
+    #[schemars(description = "Only patterns updated since this date, in the same formats as created_after")]
+    updated_after: Option<String>,
+    #[schemars(description = "Only patterns updated before this date, in the same formats as created_after")]
+    updated_before: Option<String>,
+    #[schemars(description = "Only patterns updated within this long, e.g. '30d' or '6 months'")]
+    updated_within: Option<String>,
+    #[schemars(description = "Maximum number of results to return")]
+    limit: Option<usize>,
+    #[schemars(description = "Number of results to skip, for fetching further pages")]
+    offset: Option<usize>,
+    #[serde(default)]
+    #[schemars(description = "Only search inside fenced code blocks, ignoring prose")]
+    code_only: bool,
+    #[serde(default = "default_snippet_length")]
+    #[schemars(description = "Length in characters of the excerpt shown around the first match (default 200, at most 2000)")]
+    snippet_length: usize,
+    #[schemars(description = "Order results by name, category, created, updated or relevance (default)")]
+    sort_by: Option<SortBy>,
+    #[schemars(description = "Sort order, asc or desc. Defaults to asc for names and desc for dates and relevance")]
+    order: Option<SortOrder>,
+    #[serde(default)]
+    #[schemars(description = "Show how each score breaks down into relevance, feedback and priority")]
+    explain: bool,
+    #[serde(default)]
+    #[schemars(description = "Response format: markdown (default) or json for a machine-readable result list")]
+    format: OutputFormat,
+    #[serde(default)]
+    #[schemars(description = "keyword (default), semantic for meaning-based matches, or hybrid to fuse both rankings")]
+    mode: SearchMode,
+}
+
+/// Format of list and search responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Markdown,
+    /// A JSON result list, also returned as structured content
+    Json,
+}
+
+/// A pattern in a JSON list or search response
+#[derive(Debug, Serialize)]
+struct PatternSummary<'a> {
+    name: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<&'a str>,
+    category: &'a str,
+    frameworks: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    maturity: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    difficulty: Option<&'a str>,
+    tags: &'a [String],
+    snippet: String,
+    filepath: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    /// Quality score from 0.0 to 1.0, included when the search is explained
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alternates: Vec<&'a str>,
+    /// Fields the query matched, such as "tags" or "body"
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    matched_fields: Vec<String>,
+}
+
+/// Most lines of code shown on a pattern card
+const CARD_CODE_LINES: usize = 20;
+/// Most characters of the summary shown on a pattern card
+const CARD_SUMMARY_LENGTH: usize = 160;
+
+/// A compact view of a pattern, for rendering in GUI clients
+#[derive(Debug, Serialize)]
+struct PatternCard<'a> {
+    title: &'a str,
+    summary: Option<String>,
+    category: &'a str,
+    frameworks: &'a [String],
+    author: Option<&'a str>,
+    version: Option<&'a str>,
+    tags: &'a [String],
+    /// Badge such as "draft", from the pattern's `maturity` or its draft flag
+    maturity: Option<String>,
+    difficulty: Option<&'a str>,
+    /// Date of the last change, as YYYY-MM-DD
+    updated: Option<String>,
+    /// The pattern's main code block, cut to `CARD_CODE_LINES` lines
+    code: Option<CardCode>,
+}
+
+#[derive(Debug, Serialize)]
+struct CardCode {
+    language: Option<String>,
+    code: String,
+    truncated: bool,
+}
+
+impl<'a> PatternCard<'a> {
+    fn new(pattern: &'a Pattern, body: &str) -> Self {
+        let metadata = &pattern.metadata;
+        let maturity = match &metadata.maturity {
+            Some(maturity) => Some(maturity.clone()),
+            None => metadata.draft.then(|| "draft".to_string()),
+        };
+        let code = flashcards::key_code_block(body).map(|block| {
+            let mut lines = block.lines();
+            let language = lines
+                .next()
+                .map(|fence| fence.trim().trim_start_matches('`').trim().to_string())
+                .filter(|l| !l.is_empty());
+            let lines: Vec<&str> = lines.collect();
+            let lines = &lines[..lines.len().saturating_sub(1)];
+            CardCode {
+                language,
+                code: lines[..lines.len().min(CARD_CODE_LINES)].join("\n"),
+                truncated: lines.len() > CARD_CODE_LINES,
+            }
+        });
+        Self {
+            title: &metadata.pattern,
+            summary: metadata
+                .description
+                .clone()
+                .or_else(|| flashcards::summary(body))
+                .map(|s| search::snippet(&s, &[], CARD_SUMMARY_LENGTH)),
+            category: &metadata.category,
+            frameworks: &metadata.frameworks,
+            author: metadata.author.as_deref(),
+            version: metadata.version.as_deref(),
+            tags: &metadata.tags,
+            maturity,
+            difficulty: metadata.difficulty.as_deref(),
+            updated: pattern
+                .updated
+                .map(|t| DateTime::<Local>::from(t).format("%Y-%m-%d").to_string()),
+            code,
+        }
+    }
+
+    /// The card as short markdown, for clients that don't render structured content
+    fn markdown(&self) -> String {
+        let mut out = format!("### {}", self.title);
+        if let Some(maturity) = &self.maturity {
+            out.push_str(&format!(" `{}`", maturity));
+        }
+        if let Some(summary) = &self.summary {
+            out.push_str(&format!("\n{}", summary));
+        }
+        let mut details = vec![self.category.to_string()];
+        if !self.frameworks.is_empty() {
+            details.push(self.frameworks.join(", "));
+        }
+        details.extend(self.author.map(|author| format!("by {}", author)));
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self.tags.iter().map(|t| format!("#{}", t)).collect();
+            details.push(tags.join(" "));
+        }
+        details.extend(self.difficulty.map(str::to_string));
+        if let Some(version) = self.version {
+            details.push(format!("v{}", version));
+        }
+        if let Some(updated) = &self.updated {
+            details.push(format!("updated {}", updated));
+        }
+        out.push_str(&format!("\n_{}_", details.join(" · ")));
+        if let Some(code) = &self.code {
+            out.push_str(&format!(
+                "\n\n```{}\n{}\n```",
+                code.language.as_deref().unwrap_or_default(),
+                code.code
+            ));
+            if code.truncated {
+                out.push_str("\n_(code shortened)_");
+            }
+        }
+        out
+    }
+}
+
+impl<'a> PatternSummary<'a> {
+    fn new(pattern: &'a Pattern, snippet: String) -> Self {
+        Self {
+            name: pattern.qualified_name(),
+            namespace: pattern.namespace.as_deref(),
+            category: &pattern.metadata.category,
+            frameworks: &pattern.metadata.frameworks,
+            description: pattern.metadata.description.as_deref(),
+            author: pattern.metadata.author.as_deref(),
+            version: pattern.metadata.version.as_deref(),
+            maturity: pattern.metadata.maturity.as_deref(),
+            difficulty: pattern.metadata.difficulty.as_deref(),
+            tags: &pattern.metadata.tags,
+            snippet,
+            filepath: &pattern.filepath,
+            score: None,
+            quality: None,
+            alternates: Vec::new(),
+            matched_fields: Vec::new(),
+        }
+    }
+}
+
+/// URI prefix of patterns exposed as MCP resources, followed by the pattern name
+const PATTERN_URI_PREFIX: &str = "grimoire://pattern/";
+/// URI prefix of the pattern listings for each category, followed by the category
+const CATEGORY_URI_PREFIX: &str = "grimoire://category/";
+/// URI prefix of the pattern listings for each tag, followed by the tag
+const TAG_URI_PREFIX: &str = "grimoire://tag/";
+const MARKDOWN_MIME_TYPE: &str = "text/markdown";
+
+/// A generated resource listing every pattern in a category or with a tag
+enum Listing {
+    Category(String),
+    Tag(String),
+}
+
+impl Listing {
+    fn parse(uri: &str) -> Option<Self> {
+        if let Some(category) = uri.strip_prefix(CATEGORY_URI_PREFIX) {
+            Some(Listing::Category(normalize(category)))
+        } else {
+            uri.strip_prefix(TAG_URI_PREFIX).map(|tag| Listing::Tag(normalize(tag)))
+        }
+    }
+
+    fn contains(&self, metadata: &PatternMetadata) -> bool {
+        match self {
+            Listing::Category(category) => &metadata.category == category,
+            Listing::Tag(tag) => metadata.tags.contains(tag),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Listing::Category(category) => format!("category '{}'", category),
+            Listing::Tag(tag) => format!("tag '{}'", tag),
+        }
+    }
+
+    /// The listing as markdown, one line per pattern linking to its resource
+    fn render(&self, library: &Library) -> Option<String> {
+        let lines: Vec<String> = library
+            .patterns
+            .iter()
+            .filter(|p| self.contains(&p.metadata))
+            .map(|p| {
+                let draft = if p.metadata.draft { " [draft]" } else { "" };
+                let name = p.qualified_name();
+                format!("- [{}]({}{}){}", name, PATTERN_URI_PREFIX, name, draft)
+            })
+            .collect();
+        let title = match self {
+            Listing::Category(category) => category.clone(),
+            Listing::Tag(tag) => format!("#{}", tag),
+        };
+        (!lines.is_empty()).then(|| format!("# {}\n\n{}\n", title, lines.join("\n")))
+    }
+}
+
+/// A pattern file rewritten with new metadata and body, keeping the comments and layout of
+/// its frontmatter, with `updated` bumped to now. Files that don't exist yet are rendered
+/// from scratch
+fn rewrite_file(path: &Path, metadata: &PatternMetadata, body: &str) -> String {
+    let original = fs::read_to_string(path).unwrap_or_default();
+    let metadata = PatternMetadata {
+        updated: Some(timestamp_now()),
+        ..metadata.clone()
+    };
+    frontmatter::update(&original, &metadata, body)
+}
+
+/// Where a pattern file goes when the pattern is renamed, keeping its extension. Names may
+/// hold dots, so the extension is appended rather than swapped in
+fn renamed_path(path: &Path, name: &str) -> PathBuf {
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{}.{}", name, ext.to_string_lossy())),
+        None => path.with_file_name(name),
+    }
+}
+
+/// A model's answer reduced to a one-line description: the first line, without quotes
+/// or a "Description:" label
+fn clean_description(text: &str) -> String {
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    let line = line
+        .strip_prefix("Description:")
+        .unwrap_or(line)
+        .trim()
+        .trim_matches(['"', '`', '\'']);
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Category, framework, tags and summary of a pattern, for resource and prompt listings
+fn pattern_description(pattern: &Pattern) -> String {
+    let metadata = &pattern.metadata;
+    let mut description = format!("{} pattern", metadata.category);
+    if !metadata.frameworks.is_empty() {
+        description.push_str(&format!(" for {}", metadata.frameworks.join(", ")));
+    }
+    if !metadata.tags.is_empty() {
+        description.push_str(&format!(" ({})", metadata.tags.join(", ")));
+    }
+    let summary = metadata
+        .description
+        .clone()
+        .or_else(|| flashcards::summary(&pattern.content));
+    if let Some(summary) = summary {
+        let summary = search::snippet(&summary, &[], default_snippet_length());
+        description.push_str(&format!(": {}", summary));
+    }
+    description
+}
+
+/// A pattern listed as an MCP resource
+fn pattern_resource(pattern: &Pattern) -> Resource {
+    let resource = RawResource {
+        description: Some(pattern_description(pattern)),
+        mime_type: Some(MARKDOWN_MIME_TYPE.to_string()),
+        size: u32::try_from(pattern.content.len()).ok(),
+        ..RawResource::new(
+            format!("{}{}", PATTERN_URI_PREFIX, pattern.qualified_name()),
+            pattern.qualified_name(),
+        )
+    };
+    let annotations = pattern.updated.map(|updated| Annotations {
+        audience: None,
+        priority: None,
+        last_modified: Some(DateTime::<Utc>::from(updated)),
+    });
+    Annotated::new(resource, annotations)
+}
+
+/// A prompt pattern listed as an MCP prompt
+fn pattern_prompt(pattern: &Pattern) -> Prompt {
+    let arguments = pattern
+        .metadata
+        .arguments
+        .iter()
+        .map(|a| PromptArgument {
+            name: a.name.clone(),
+            title: None,
+            description: a.description.clone(),
+            required: Some(a.required),
+        })
+        .collect::<Vec<_>>();
+    let summary = flashcards::summary(&pattern.content)
+        .map(|s| search::snippet(&s, &[], default_snippet_length()));
+    Prompt::new(
+        pattern.qualified_name(),
+        summary,
+        (!arguments.is_empty()).then_some(arguments),
+    )
+}
+
+/// A URI template clients can fill in to read resources without listing them first
+fn resource_template(uri_template: String, name: &str, description: &str) -> ResourceTemplate {
+    Annotated::new(
+        RawResourceTemplate {
+            uri_template,
+            name: name.to_string(),
+            title: None,
+            description: Some(description.to_string()),
+            mime_type: Some(MARKDOWN_MIME_TYPE.to_string()),
+        },
+        None,
+    )
+}
+
+/// Values from the loaded patterns that an argument of this name takes, for completion
+fn completion_candidates<'a>(library: &'a Library, argument: &str) -> BTreeSet<&'a str> {
+    let patterns = library.patterns.iter().map(|p| &p.metadata);
+    match argument {
+        "pattern_name" | "name" | "pattern" => patterns
+            .flat_map(|m| std::iter::once(&m.pattern).chain(&m.aliases))
+            .map(String::as_str)
+            .collect(),
+        "namespace" => library.patterns.iter().filter_map(|p| p.namespace.as_deref()).collect(),
+        "category" => patterns.map(|m| m.category.as_str()).collect(),
+        "framework" => patterns.flat_map(|m| &m.frameworks).map(String::as_str).collect(),
+        "tag" | "tags" => patterns.flat_map(|m| &m.tags).map(String::as_str).collect(),
+        "project" | "projects" => patterns.flat_map(|m| &m.projects).map(String::as_str).collect(),
+        _ => BTreeSet::new(),
+    }
+}
+
+/// How search_patterns matches the query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Typo-tolerant keywords (or a regular expression) ranked with BM25
+    #[default]
+    Keyword,
+    /// Embedding similarity, catching paraphrases that share no keywords
+    Semantic,
+    /// Keyword and semantic rankings merged with reciprocal rank fusion
+    Hybrid,
+}
+
+/// Semantic search parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SemanticSearchRequest {
+    #[schemars(description = "Describe what you are looking for in natural language")]
+    query: String,
+    #[schemars(description = "Maximum number of results to return")]
+    limit: Option<usize>,
+    #[schemars(description = "Number of results to skip, for fetching further pages")]
+    offset: Option<usize>,
+}
+
+/// Feedback parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ResultFeedbackRequest {
+    #[schemars(description = "The query or task the results were retrieved for")]
+    query: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Names of returned patterns that were useful for the task")]
+    useful: Vec<String>,
+    #[serde(default)]
+    #[schemars(description = "Names of returned patterns that were not useful for the task")]
+    not_useful: Vec<String>,
+}
+
+fn default_snippet_length() -> usize {
+    200
+}
+
+fn default_stemming() -> bool {
+    true
+}
+
+fn default_relax() -> bool {
+    true
+}
+
+/// A constraint dropped from a search that found nothing, in the order they are dropped
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Relaxed {
+    Tags,
+    Framework,
+    Query,
+}
+
+impl Relaxed {
+    fn name(self) -> &'static str {
+        match self {
+            Relaxed::Tags => "tag",
+            Relaxed::Framework => "framework",
+            Relaxed::Query => "query",
+        }
+    }
+
+    fn describe(self, tags: &[String], frameworks: &[String]) -> String {
+        match self {
+            Relaxed::Tags => format!("dropping the tag filter ({})", tags.join(", ")),
+            Relaxed::Framework => {
+                format!("dropping the framework filter ({})", frameworks.join(", "))
+            }
+            Relaxed::Query => "matching any query word, allowing typos".to_string(),
+        }
+    }
+}
+
+/// Project listing parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ProjectPatternsRequest {
+    #[schemars(description = "Project name")]
+    project: String,
+    #[schemars(description = "Maximum number of patterns to return")]
+    limit: Option<usize>,
+    #[schemars(description = "Number of patterns to skip, for fetching further pages")]
+    offset: Option<usize>,
+    #[schemars(description = "Cursor from the previous page, to continue after it. Unlike offset, stays aligned when patterns are added or removed")]
+    cursor: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Response format: markdown (default) or json for a machine-readable list")]
+    format: OutputFormat,
+}
+
+/// Dependency listing parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct DependencyPatternsRequest {
+    #[schemars(description = "Dependency imported by pattern code, e.g. a crate, Python package, npm package or Go module path. Matched case-insensitively, with - and _ alike")]
+    dependency: String,
+    #[schemars(description = "Maximum number of patterns to return")]
+    limit: Option<usize>,
+    #[schemars(description = "Number of patterns to skip, for fetching further pages")]
+    offset: Option<usize>,
+    #[schemars(description = "Cursor from the previous page, to continue after it. Unlike offset, stays aligned when patterns are added or removed")]
+    cursor: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Response format: markdown (default) or json for a machine-readable list")]
+    format: OutputFormat,
+}
+
+/// Library statistics parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct LibraryStatsRequest {
+    #[serde(default)]
+    #[schemars(description = "Response format: markdown (default) or json, also returned as structured content")]
+    format: OutputFormat,
+}
+
+/// Server status parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ServerStatusRequest {
+    #[serde(default)]
+    #[schemars(description = "Response format: markdown (default) or json, also returned as structured content")]
+    format: OutputFormat,
+}
+
+/// List parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListPatternsRequest {
+    #[schemars(description = "Maximum number of patterns to return")]
+    limit: Option<usize>,
+    #[schemars(description = "Number of patterns to skip, for fetching further pages")]
+    offset: Option<usize>,
+    #[schemars(description = "Cursor from the previous page, to continue after it. Unlike offset, stays aligned when patterns are added or removed")]
+    cursor: Option<String>,
+    #[schemars(description = "Order patterns by name, category, created or updated. Defaults to directory order")]
+    sort_by: Option<SortBy>,
+    #[schemars(description = "Sort order, asc or desc. Defaults to asc for names and desc for dates")]
+    order: Option<SortOrder>,
+    #[schemars(description = "Only patterns created since this date: an ISO date, an amount of time like '30d' or '2 weeks ago', or a period like 'last quarter' (meaning since it began)")]
+    created_after: Option<String>,
+    #[schemars(description = "Only patterns created before this date, in the same formats as created_after")]
+    created_before: Option<String>,
+    #[schemars(description = "Only patterns updated since this date, in the same formats as created_after")]
+    updated_after: Option<String>,
+    #[schemars(description = "Only patterns updated before this date, in the same formats as created_after")]
+    updated_before: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Response format: markdown (default) or json for a machine-readable list")]
+    format: OutputFormat,
+}
+
+/// Created and updated date ranges patterns must fall in, resolved to times
+struct DateFilters {
+    created_after: Option<SystemTime>,
+    created_before: Option<SystemTime>,
+    updated_after: Option<SystemTime>,
+    updated_before: Option<SystemTime>,
+}
+
+impl DateFilters {
+    /// Resolve the created after and before, then updated after and before, dates. Relative
+    /// dates are resolved once, so every pattern is compared against the same cutoff
+    fn resolve(dates: [Option<String>; 4]) -> Result<Self, McpError> {
+        let now = Local::now();
+        let [created_after, created_before, updated_after, updated_before] = dates.map(|expr| {
+            expr.map(|e| dates::parse(&e, now).map(SystemTime::from))
+                .transpose()
+                .map_err(|e| McpError::invalid_params(e, None))
+        });
+        Ok(Self {
+            created_after: created_after?,
+            created_before: created_before?,
+            updated_after: updated_after?,
+            updated_before: updated_before?,
+        })
+    }
+
+    fn matches(&self, pattern: &Pattern) -> bool {
+        type Time = Option<SystemTime>;
+        let in_range = |time: Time, after: Time, before: Time| {
+            (after.is_none() && before.is_none())
+                || time.is_some_and(|t| {
+                    after.is_none_or(|a| t >= a) && before.is_none_or(|b| t < b)
+                })
+        };
+        in_range(pattern.created, self.created_after, self.created_before)
+            && in_range(pattern.updated, self.updated_after, self.updated_before)
+    }
+}
+
+/// Field to order list and search results by
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    Name,
+    Category,
+    Created,
+    Updated,
+    Relevance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortBy {
+    /// Names sort A-Z by default, dates and relevance newest/best first
+    fn default_order(self) -> SortOrder {
+        match self {
+            SortBy::Name | SortBy::Category => SortOrder::Asc,
+            SortBy::Created | SortBy::Updated | SortBy::Relevance => SortOrder::Desc,
+        }
+    }
+
+    /// Compare two patterns (with their scores) ascending by this field
+    fn compare(
+        self,
+        (a, a_score): (&Pattern, Option<f64>),
+        (b, b_score): (&Pattern, Option<f64>),
+    ) -> Ordering {
+        match self {
+            SortBy::Name => a
+                .metadata
+                .pattern
+                .to_lowercase()
+                .cmp(&b.metadata.pattern.to_lowercase()),
+            SortBy::Category => a
+                .metadata
+                .category
+                .to_lowercase()
+                .cmp(&b.metadata.category.to_lowercase())
+                .then_with(|| a.metadata.pattern.cmp(&b.metadata.pattern)),
+            SortBy::Created => a.created.cmp(&b.created),
+            SortBy::Updated => a.updated.cmp(&b.updated),
+            SortBy::Relevance => a_score.partial_cmp(&b_score).unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+/// Sort scored patterns in place, keeping the existing order for ties
+fn sort_patterns<T>(
+    items: &mut [T],
+    key: impl Fn(&T) -> (&Pattern, Option<f64>),
+    sort_by: SortBy,
+    order: Option<SortOrder>,
+) {
+    let order = order.unwrap_or(sort_by.default_order());
+    items.sort_by(|a, b| {
+        let ordering = sort_by.compare(key(a), key(b));
+        match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
 }
 
 /// Get parameters
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct GetPatternRequest {
-    #[schemars(description = "Pattern Name")]
+    #[schemars(description = "Pattern Name, optionally with a section anchor like 'axum-middleware#error-handling'")]
     pattern_name: String,
+    #[schemars(description = "Only return the section under this heading anchor")]
+    section: Option<String>,
+    #[schemars(description = "Values for the {{name}} arguments the pattern declares, to return it filled in")]
+    arguments: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    #[schemars(description = "Wrap the content and each embedded chunk in <!-- grimoire:source --> markers naming the pattern, ID, section and a content hash, for citing sources and spotting changed chunks")]
+    provenance: bool,
+    #[serde(default)]
+    #[schemars(description = "Return a compact card instead of the full body: title, one-line summary, tags, maturity badge, last update and the main code snippet, also as structured content")]
+    card: bool,
 }
 
 /// Create parameters
@@ -73,232 +972,3373 @@ pub struct GetPatternRequest {
 pub struct CreatePatternRequest {
     #[schemars(description = "Pattern name")]
     pattern_name: String,
-    #[schemars(description = "Pattern category")]
-    category: String,
-    #[schemars(description = "Pattern framework")]
-    framework: String,
+    #[schemars(description = "Pattern category. Falls back to the configured default when omitted")]
+    category: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Frameworks the pattern is tied to, one name or a list such as ['axum', 'sqlx']. Leave out rather than using 'none'")]
+    framework: Vec<String>,
     #[schemars(description = "Projects in which these patterns were used")]
     projects: Option<Vec<String>>,
     #[schemars(description = "Pattern tags")]
     tag: Vec<String>,
+    #[schemars(description = "Names of existing patterns this one builds on, read first by get_pattern_with_dependencies and pattern_path. Overwriting keeps the existing list when omitted")]
+    requires: Option<Vec<String>>,
+    #[schemars(description = "Names of existing patterns worth reading alongside this one. Overwriting keeps the existing list when omitted")]
+    see_also: Option<Vec<String>>,
+    #[serde(default)]
+    #[schemars(description = "One-line summary of what the pattern is for, shown in listings and search results. Overwriting keeps the existing description when omitted")]
+    description: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Who wrote the pattern. Overwriting keeps the existing author, and new patterns fall back to the configured default, when omitted")]
+    author: Option<String>,
     #[schemars(description = "Pattern content")]
     content: String,
+    #[serde(default)]
+    #[schemars(description = "Overwrite an existing pattern file of the same name. Clients that support elicitation ask the user instead, whatever this is set to")]
+    confirm: bool,
+    #[serde(default)]
+    #[schemars(description = "Part of the version to bump when overwriting: major for breaking changes, minor for additions or patch (default) for fixes. New patterns start at 1.0.0")]
+    bump: VersionBump,
+}
+
+/// Part of a pattern's MAJOR.MINOR.PATCH version raised when it is overwritten
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionBump {
+    /// Changes that break code following the old version
+    Major,
+    /// Additions that keep the old advice valid
+    Minor,
+    /// Fixes and rewording
+    #[default]
+    Patch,
+}
+
+impl VersionBump {
+    /// The version after this bump, or why `version` can't be bumped
+    ///
+    /// Build metadata (`+build`) is dropped. A pre-release (`2.0.0-beta.1`) is bumped to
+    /// its release when that is as big a step, like semver tools do, so `major` takes
+    /// `2.0.0-beta.1` to `2.0.0` but `patch` takes `2.0.1-rc.1` to `2.0.1`.
+    fn apply(self, version: &str) -> Result<String, String> {
+        let invalid = || {
+            format!(
+                "version '{}' is not MAJOR.MINOR.PATCH, fix it in the frontmatter first",
+                version
+            )
+        };
+        let text = version.trim().trim_start_matches('v');
+        let text = text.split_once('+').map_or(text, |(text, _)| text);
+        let (core, pre_release) = match text.split_once('-') {
+            Some((_, "")) => return Err(invalid()),
+            Some((core, _)) => (core, true),
+            None => (text, false),
+        };
+        let parts: Option<Vec<u64>> = core
+            .split('.')
+            .map(|part| {
+                let digits = !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+                digits.then(|| part.parse().ok()).flatten()
+            })
+            .collect();
+        let parts = parts.as_deref().and_then(|p| <[u64; 3]>::try_from(p).ok());
+        let [major, minor, patch] = parts.ok_or_else(invalid)?;
+        let next = |n: u64| {
+            n.checked_add(1)
+                .ok_or_else(|| format!("version '{}' can't be raised any further", version))
+        };
+        Ok(match self {
+            VersionBump::Major if pre_release && minor == 0 && patch == 0 => {
+                format!("{}.0.0", major)
+            }
+            VersionBump::Major => format!("{}.0.0", next(major)?),
+            VersionBump::Minor if pre_release && patch == 0 => format!("{}.{}.0", major, minor),
+            VersionBump::Minor => format!("{}.{}.0", major, next(minor)?),
+            VersionBump::Patch if pre_release => format!("{}.{}.{}", major, minor, patch),
+            VersionBump::Patch => format!("{}.{}.{}", major, minor, next(patch)?),
+        })
+    }
+}
+
+/// The user's answer when asked to confirm a destructive write
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct Confirmation {
+    #[schemars(description = "Go ahead with the change")]
+    confirm: bool,
+}
+
+rmcp::elicit_safe!(Confirmation);
+
+/// Step of writing a pattern in session memory
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DraftAction {
+    /// Begin a draft with its metadata and an optional intro
+    Start,
+    /// Add a `## heading` section at the end
+    Append,
+    /// Replace a section's content, or the intro and any given metadata when no heading is given
+    Revise,
+    /// Drop a section
+    Remove,
+    /// Show the file the draft would be committed as
+    Preview,
+    /// Throw the draft away
+    Discard,
+}
+
+/// Session draft parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct DraftInSessionRequest {
+    #[schemars(description = "Name of the pattern being drafted")]
+    pattern_name: String,
+    #[schemars(description = "start a draft, append a section, revise a section (or the intro and metadata when no heading is given), remove a section, preview the rendered pattern or discard the draft")]
+    action: DraftAction,
+    #[schemars(description = "Section heading, without the leading ##, for append, revise and remove")]
+    heading: Option<String>,
+    #[schemars(description = "Section content for append and revise, or the intro before the first section for start and revise without a heading")]
+    content: Option<String>,
+    #[schemars(description = "Pattern category, for start and revise. Falls back to the configured default on commit")]
+    category: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Pattern frameworks, one name or a list, for start and revise")]
+    framework: Vec<String>,
+    #[schemars(description = "Projects in which the pattern was used, for start and revise")]
+    projects: Option<Vec<String>>,
+    #[schemars(description = "Pattern tags, for start and revise")]
+    tag: Option<Vec<String>>,
+}
+
+/// Commit draft parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct CommitDraftRequest {
+    #[schemars(description = "Name of the drafted pattern to write to disk")]
+    pattern_name: String,
+    #[serde(default)]
+    #[schemars(description = "Overwrite an existing pattern file of the same name. Clients that support elicitation ask the user instead, whatever this is set to")]
+    confirm: bool,
+}
+
+/// A pattern written across several draft_in_session calls, held in memory until committed
+#[derive(Debug, Clone, Default)]
+struct SessionDraft {
+    category: Option<String>,
+    frameworks: Vec<String>,
+    projects: Vec<String>,
+    tags: Vec<String>,
+    /// Text before the first section
+    intro: String,
+    /// `##` sections in order, as heading and content
+    sections: Vec<(String, String)>,
+}
+
+impl SessionDraft {
+    fn body(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if !self.intro.trim().is_empty() {
+            parts.push(self.intro.trim().to_string());
+        }
+        for (heading, content) in &self.sections {
+            parts.push(format!("## {}\n\n{}", heading, content.trim()));
+        }
+        parts.join("\n\n")
+    }
+
+    fn section(&mut self, heading: &str) -> Option<usize> {
+        self.sections.iter().position(|(h, _)| h.eq_ignore_ascii_case(heading))
+    }
+
+    /// Overwrite the metadata fields that were given
+    fn set_metadata(
+        &mut self,
+        category: Option<String>,
+        frameworks: Vec<String>,
+        projects: Option<Vec<String>>,
+        tags: Option<Vec<String>>,
+    ) {
+        self.category = category.or(self.category.take());
+        if !frameworks.is_empty() {
+            self.frameworks = frameworks;
+        }
+        self.projects = projects.unwrap_or(std::mem::take(&mut self.projects));
+        self.tags = tags.unwrap_or(std::mem::take(&mut self.tags));
+    }
+
+    /// One line naming the sections, for replies to draft edits
+    fn outline(&self) -> String {
+        let headings: Vec<&str> = self.sections.iter().map(|(h, _)| h.as_str()).collect();
+        if headings.is_empty() {
+            "no sections yet".to_string()
+        } else {
+            format!("sections: {}", headings.join(", "))
+        }
+    }
 }
 
-impl Patterns {
-    /// Parse pattern from file
-    fn load_patterns(path: &Path) -> Option<Pattern> {
-        let content = fs::read_to_string(path).ok()?;
-        let rest = content.strip_prefix("---\n")?;
-        let mut parts = rest.splitn(2, "\n---\n");
-        let yaml = parts.next()?;
-        let body = parts.next()?.trim();
-        let metadata: PatternMetadata = serde_yaml::from_str(yaml).ok()?;
-
-        Some(Pattern {
-            metadata,
-            content: body.to_string(),
-            filepath: path.to_path_buf(),
-        })
-    }
+/// Record usage parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct RecordPatternUsageRequest {
+    #[schemars(description = "Pattern name")]
+    pattern_name: String,
+    #[schemars(description = "Project the pattern was used in")]
+    project: String,
+    #[schemars(description = "Optional note on how the pattern was used")]
+    note: Option<String>,
+}
+
+/// Summarize parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SummarizePatternRequest {
+    #[schemars(description = "Pattern name")]
+    pattern_name: String,
+    #[serde(default)]
+    #[schemars(description = "Replace the description the pattern already has")]
+    overwrite: bool,
+}
+
+/// Repair parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct RepairPatternRequest {
+    #[schemars(description = "File name of the damaged pattern in the patterns directory, e.g. 'axum-middleware.md'")]
+    file: String,
+    #[serde(default)]
+    #[schemars(description = "Write the repaired file. Without it the proposed repair is only shown")]
+    confirm: bool,
+}
+
+/// Resolve ID conflicts parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ResolveIdConflictsRequest {
+    #[serde(default)]
+    #[schemars(description = "Apply the planned changes. Without it they are only shown")]
+    confirm: bool,
+}
+
+/// Rename category parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct RenameCategoryRequest {
+    #[schemars(description = "Category to rename")]
+    from: String,
+    #[schemars(description = "New category name")]
+    to: String,
+    #[serde(default)]
+    #[schemars(description = "Apply the change when it affects more patterns than the configured threshold")]
+    confirm: bool,
+    #[schemars(description = "Number of patterns the preview said would change, required with confirm")]
+    affected_count: Option<usize>,
+}
+
+/// Quick capture parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct CaptureNoteRequest {
+    #[schemars(description = "Topic the note belongs to, e.g. 'tokio' or 'aws-iam'. Notes on the same topic share one scratch pattern")]
+    topic: String,
+    #[schemars(description = "The note, as rough as it is")]
+    note: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Tags to add to the scratch pattern")]
+    tag: Vec<String>,
+}
+
+/// Category of scratch patterns when no default category is configured
+const SCRATCH_CATEGORY: &str = "scratch";
+
+/// Related patterns parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct RelatedPatternsRequest {
+    #[schemars(description = "Pattern to find related material for")]
+    pattern_name: String,
+    #[schemars(description = "Maximum number of related patterns to return")]
+    limit: Option<usize>,
+}
+
+/// Prerequisite chain parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PatternPathRequest {
+    #[schemars(description = "Name of the goal pattern")]
+    pattern_name: String,
+}
+
+/// Parameters for getting a pattern with what it requires
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PatternDependenciesRequest {
+    #[schemars(description = "Name of the pattern to get, with every pattern it requires")]
+    pattern_name: String,
+}
+
+/// Flashcard export parameters
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ExportFlashcardsRequest {
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Only export patterns in any of these categories")]
+    category: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(description = "Only export patterns with any of these tags")]
+    tag: Vec<String>,
+    #[schemars(description = "File name to write the TSV to in the server's configured export directory, instead of returning it. Only a plain file name is accepted")]
+    output: Option<String>,
+}
+
+/// Category given to repaired patterns when none can be recovered and no default is configured
+const FALLBACK_CATEGORY: &str = "uncategorized";
+
+/// A loaded library and its generation, which counts the reloads since startup
+#[derive(Debug)]
+struct Snapshot {
+    library: Arc<Library>,
+    generation: u64,
+}
+
+/// What reloading the library changed
+#[derive(Debug, Clone)]
+struct Reload {
+    /// The library that was replaced
+    previous: Arc<Library>,
+    generation: u64,
+    count: usize,
+    elapsed: Duration,
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+/// The window of results a list or search call returns
+struct Page {
+    offset: usize,
+    limit: usize,
+    /// Cursor of the page after this one, for lists paginated by cursor
+    next_cursor: Option<String>,
+}
+
+/// Position of the pattern a cursor points to. Cursors name the first pattern of the next
+/// page rather than counting, so pages stay aligned when patterns are added or removed
+fn cursor_position(
+    mut names: impl Iterator<Item = impl AsRef<str>>,
+    cursor: &str,
+) -> Result<usize, McpError> {
+    names.position(|name| name.as_ref() == cursor).ok_or_else(|| {
+        McpError::invalid_params(
+            format!("Cursor '{}' no longer names a pattern, list again from the start", cursor),
+            None,
+        )
+    })
+}
+
+/// One page of an MCP list response starting at the request's cursor, and the next cursor
+fn cursor_page<T>(
+    items: Vec<T>,
+    name: impl Fn(&T) -> Cow<str>,
+    request: Option<PaginatedRequestParam>,
+    page_size: usize,
+) -> Result<(Vec<T>, Option<String>), McpError> {
+    let start = match request.and_then(|r| r.cursor) {
+        Some(cursor) => cursor_position(items.iter().map(&name), &cursor)?,
+        None => 0,
+    };
+    let page_size = page_size.max(1);
+    let next_cursor = items.get(start + page_size).map(|item| name(item).to_string());
+    let page = items.into_iter().skip(start).take(page_size).collect();
+    Ok((page, next_cursor))
+}
+
+impl Page {
+    /// Requested window, with the limit defaulted and capped by the configured limits
+    fn new(limits: &Limits, limit: Option<usize>, offset: Option<usize>) -> Self {
+        Self {
+            offset: offset.unwrap_or(0),
+            limit: limit
+                .unwrap_or(limits.default_limit)
+                .clamp(1, limits.max_results.max(1)),
+            next_cursor: None,
+        }
+    }
+
+    /// Start at the pattern a cursor names instead of the offset, and name the first
+    /// pattern after this page as the next cursor
+    fn with_cursor(
+        mut self,
+        cursor: Option<&str>,
+        patterns: &[&Pattern],
+    ) -> Result<Self, McpError> {
+        let names = || patterns.iter().map(|p| p.qualified_name());
+        if let Some(cursor) = cursor {
+            self.offset = cursor_position(names(), cursor)?;
+        }
+        self.next_cursor = names().nth(self.offset + self.limit).map(Cow::into_owned);
+        Ok(self)
+    }
+
+    /// Footer telling the caller where this page sits in the full result set
+    fn footer(&self, total: usize) -> String {
+        let end = (self.offset + self.limit).min(total);
+        if self.offset >= total {
+            return format!("\n\n(offset {} is past the last of {} results)", self.offset, total);
+        }
+        let more = match &self.next_cursor {
+            Some(cursor) if end < total => {
+                format!(", use cursor '{}' or offset {} for more", cursor, end)
+            }
+            _ if end < total => format!(", use offset {} for more", end),
+            _ => String::new(),
+        };
+        format!("\n\n(showing {}-{} of {} results{})", self.offset + 1, end, total, more)
+    }
+
+    /// JSON response carrying the page's results and where it sits in the full result set
+    fn structured<T: Serialize>(&self, results: &[T], total: usize) -> CallToolResult {
+        CallToolResult::structured(self.json(results, total))
+    }
+
+    fn json<T: Serialize>(&self, results: &[T], total: usize) -> serde_json::Value {
+        let mut response = serde_json::json!({
+            "results": results,
+            "total": total,
+            "offset": self.offset,
+            "limit": self.limit,
+        });
+        if let Some(cursor) = &self.next_cursor {
+            response["next_cursor"] = cursor.clone().into();
+        }
+        response
+    }
+}
+
+impl Patterns {
+    /// Path of the file backing a pattern, whether it was loaded at startup or created since.
+    /// The name can be namespaced, like `team/axum-errors`
+    fn pattern_file(&self, name: &str) -> PathBuf {
+        let library = self.library();
+        library
+            .patterns
+            .iter()
+            .find(|p| p.qualified_name() == name)
+            .or_else(|| library.patterns.iter().find(|p| p.metadata.pattern == name))
+            .map(|p| p.filepath.clone())
+            .unwrap_or_else(|| self.new_pattern_file(name))
+    }
+
+    /// Path of the file a tool writes a pattern of the patterns directory to: its existing
+    /// file, whatever the extension, or a new `.md` file
+    fn own_pattern_file(&self, name: &str) -> PathBuf {
+        self.library()
+            .patterns
+            .iter()
+            .find(|p| !p.read_only && p.qualified_name() == name)
+            .map(|p| p.filepath.clone())
+            .unwrap_or_else(|| self.new_pattern_file(name))
+    }
+
+    /// Path of a pattern file not created yet, always with the default extension
+    fn new_pattern_file(&self, name: &str) -> PathBuf {
+        self.patterns_dir.join(format!("{}.{}", name, DEFAULT_EXTENSIONS[0]))
+    }
+
+    /// Snapshot of the settings, unaffected by config reloads while it is in use
+    fn config(&self) -> Arc<Config> {
+        self.config.load_full()
+    }
+
+    /// Snapshot of the loaded library, unaffected by reloads while it is in use
+    pub(crate) fn library(&self) -> Arc<Library> {
+        self.library.load().library.clone()
+    }
+
+    /// Generation of the loaded library, increased by every reload
+    fn generation(&self) -> u64 {
+        self.library.load().generation
+    }
+
+    /// One line per pattern in the page, under a title
+    fn list_summary(
+        title: &str,
+        patterns: &[&Pattern],
+        page: &Page,
+        format: OutputFormat,
+    ) -> CallToolResult {
+        if format == OutputFormat::Json {
+            let results: Vec<PatternSummary> = patterns
+                .iter()
+                .skip(page.offset)
+                .take(page.limit)
+                .map(|p| {
+                    let snippet = search::snippet(&p.content, &[], default_snippet_length());
+                    PatternSummary::new(p, snippet)
+                })
+                .collect();
+            return page.structured(&results, patterns.len());
+        }
+
+        let summary: Vec<String> = patterns
+            .iter()
+            .skip(page.offset)
+            .take(page.limit)
+            .map(|p| {
+                let draft = if p.metadata.draft { " [draft]" } else { "" };
+                let author = p
+                    .metadata
+                    .author
+                    .as_ref()
+                    .map(|author| format!(" by {}", author))
+                    .unwrap_or_default();
+                let description = p
+                    .metadata
+                    .description
+                    .as_ref()
+                    .map(|description| format!(" - {}", description))
+                    .unwrap_or_default();
+                format!(
+                    "- {} ({}){}{}{}",
+                    p.qualified_name(),
+                    p.metadata.category,
+                    author,
+                    draft,
+                    description
+                )
+            })
+            .collect();
+
+        CallToolResult::success(vec![Content::text(format!(
+            "{}:\n{}{}",
+            title,
+            summary.join("\n"),
+            page.footer(patterns.len())
+        ))])
+    }
+
+    /// Candidate patterns (by index) ordered by embedding similarity to the query, most similar first
+    fn semantic_ranking(
+        &self,
+        library: &Library,
+        query: &str,
+        candidates: &[(usize, &Pattern)],
+        deadline: &Deadline,
+    ) -> Result<Vec<(usize, f64)>, McpError> {
+        let Some(embedder) = &self.embedder else {
+            return Err(McpError::invalid_request(
+                "Semantic search is not configured, add an [embeddings] section to grimoire.toml",
+                None,
+            ));
+        };
+        if library.embeddings.len() != library.patterns.len() {
+            return Err(McpError::internal_error(
+                "Pattern embeddings are unavailable, check the embedding service and restart the server",
+                None,
+            ));
+        }
+
+        let query_vector = embedder
+            .embed(&[query.to_string()])
+            .ok()
+            .and_then(|mut v| v.pop())
+            .ok_or_else(|| McpError::internal_error("Failed to embed the query", None))?;
+        deadline.check(0, candidates.len(), "patterns ranked")?;
+        let mut ranking: Vec<(usize, f64)> = candidates
+            .iter()
+            .map(|&(i, _)| (i, embeddings::cosine_similarity(&query_vector, &library.embeddings[i])))
+            .collect();
+        ranking.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+        Ok(ranking)
+    }
+
+    /// Preview of a bulk change, unless it is small enough or confirmed with the exact affected count
+    ///
+    /// Returns `None` when the change may go ahead.
+    fn confirm_bulk(
+        &self,
+        operation: &str,
+        affected: &[&Pattern],
+        confirm: bool,
+        affected_count: Option<usize>,
+    ) -> Result<Option<CallToolResult>, McpError> {
+        let threshold = self.config().limits.bulk_confirm_threshold;
+        if affected.len() <= threshold {
+            return Ok(None);
+        }
+        if confirm {
+            return match affected_count {
+                Some(count) if count == affected.len() => Ok(None),
+                Some(count) => Err(McpError::invalid_params(
+                    format!(
+                        "{}: affected_count is {} but {} patterns would change, review the preview again",
+                        operation,
+                        count,
+                        affected.len()
+                    ),
+                    None,
+                )),
+                None => Err(McpError::invalid_params(
+                    format!(
+                        "{}: confirm needs affected_count set to the {} patterns that would change",
+                        operation,
+                        affected.len()
+                    ),
+                    None,
+                )),
+            };
+        }
+
+        let files: Vec<String> = affected
+            .iter()
+            .map(|p| format!("- {} ({:?})", p.metadata.pattern, p.filepath))
+            .collect();
+        Ok(Some(CallToolResult::success(vec![Content::text(format!(
+            "{} would change {} patterns, more than the {} allowed without confirmation:\n{}\n\nCall again with confirm: true and affected_count: {} to apply it.",
+            operation,
+            affected.len(),
+            threshold,
+            files.join("\n"),
+            affected.len()
+        ))])))
+    }
+
+    /// Build a new library from disk, validate it and swap it in
+    fn reload(&self) -> Result<Reload, McpError> {
+        if !archive::is_archive(&self.patterns_dir) && fs::read_dir(&self.patterns_dir).is_err() {
+            return Err(McpError::internal_error(
+                format!(
+                    "Cannot read patterns directory {:?}, keeping the loaded library",
+                    self.patterns_dir
+                ),
+                None,
+            ));
+        }
+
+        let _publishing = self.publish.lock().unwrap_or_else(|e| e.into_inner());
+        let started = Instant::now();
+        let library = Library::load_dirs(
+            &self.config(),
+            &self.patterns_dir,
+            &self.pattern_dirs(),
+            self.embedder.as_deref(),
+        );
+        library.validate().map_err(|e| {
+            McpError::internal_error(
+                format!("Reload failed, keeping the loaded library: {}", e),
+                None,
+            )
+        })?;
+        let snapshot = self.library.load_full();
+        let current = snapshot.library.clone();
+        if library.patterns.is_empty() && !current.patterns.is_empty() {
+            return Err(McpError::internal_error(
+                format!(
+                    "Reload found no patterns in {:?}, keeping the {} loaded ones",
+                    self.patterns_dir,
+                    current.patterns.len()
+                ),
+                None,
+            ));
+        }
+
+        let (added, removed, changed) = current.diff(&library);
+        let count = library.patterns.len();
+        let generation = snapshot.generation + 1;
+        self.library.store(Arc::new(Snapshot {
+            library: Arc::new(library),
+            generation,
+        }));
+        let elapsed = started.elapsed();
+        tracing::info!(
+            "Reloaded {} patterns in {:?}: {} added, {} removed, {} changed",
+            count,
+            elapsed,
+            added.len(),
+            removed.len(),
+            changed.len()
+        );
+
+        let reload = Reload {
+            previous: current,
+            generation,
+            count,
+            elapsed,
+            added,
+            removed,
+            changed,
+        };
+        // No receivers just means no client is connected yet
+        let _ = self.reloads.send(Arc::new(reload.clone()));
+        Ok(reload)
+    }
+
+    /// Ask the user to confirm a destructive change through MCP elicitation. `None` when the
+    /// client can't ask, so the caller falls back to its `confirm` parameter
+    async fn ask_confirmation(
+        peer: &Peer<RoleServer>,
+        question: String,
+    ) -> Result<Option<bool>, McpError> {
+        if !peer.supports_elicitation() {
+            return Ok(None);
+        }
+        match peer.elicit::<Confirmation>(question).await {
+            Ok(answer) => Ok(Some(answer.is_some_and(|a| a.confirm))),
+            Err(ElicitationError::UserDeclined | ElicitationError::UserCancelled) => Ok(Some(false)),
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to ask for confirmation: {}", e),
+                None,
+            )),
+        }
+    }
+
+    /// Tell this session's client about every reload, until it disconnects
+    async fn follow_reloads(self, peer: Peer<RoleServer>, mut reloads: Receiver<Arc<Reload>>) {
+        loop {
+            let received = tokio::select! {
+                _ = self.closed.cancelled() => break,
+                received = reloads.recv() => received,
+            };
+            match received {
+                Ok(_) | Err(RecvError::Lagged(_)) if peer.is_transport_closed() => break,
+                Ok(reload) => {
+                    self.notify_changes(&reload);
+                    self.refresh_tools();
+                }
+                // Reloads missed while busy are covered by the next one's tool refresh
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Tell the client which subscribed resources changed, and whether the resource list did
+    fn notify_changes(&self, reload: &Reload) {
+        let Some(peer) = self.peer.read().unwrap_or_else(|e| e.into_inner()).clone() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let library = self.library();
+        let touched = |pattern: &Pattern| {
+            let name = &pattern.metadata.pattern;
+            reload.added.contains(name) || reload.removed.contains(name) || reload.changed.contains(name)
+        };
+        let updated: Vec<String> = self
+            .subscriptions
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|uri| {
+                let libraries = [&reload.previous, &library];
+                if let Some(name) = uri.strip_prefix(PATTERN_URI_PREFIX) {
+                    libraries.iter().filter_map(|l| l.find_pattern(name)).any(touched)
+                } else if let Some(listing) = Listing::parse(uri) {
+                    libraries
+                        .iter()
+                        .flat_map(|l| &l.patterns)
+                        .any(|p| listing.contains(&p.metadata) && touched(p))
+                } else {
+                    false
+                }
+            })
+            .cloned()
+            .collect();
+        let list_changed = !reload.added.is_empty() || !reload.removed.is_empty();
+
+        runtime.spawn(async move {
+            for uri in updated {
+                let _ = peer
+                    .notify_resource_updated(ResourceUpdatedNotificationParam { uri })
+                    .await;
+            }
+            if list_changed {
+                let _ = peer.notify_resource_list_changed().await;
+            }
+        });
+    }
+
+    /// Whether a tool changes anything, on disk or in the session. Every tool not annotated
+    /// as read-only counts, so new tools are covered without being listed anywhere
+    fn mutates(&self, tool: &str) -> bool {
+        !OPTIONAL_WRITE_TOOLS.contains(&tool)
+            && self.tool_router.map.get(tool).is_some_and(|route| {
+                route.attr.annotations.as_ref().and_then(|a| a.read_only_hint) != Some(true)
+            })
+    }
+
+    /// Refuse a write the request asked for when the server is read-only or the client's
+    /// token only grants read access, for the tools in `OPTIONAL_WRITE_TOOLS`
+    fn check_writable(&self, context: &RequestContext<RoleServer>) -> Result<(), McpError> {
+        let reason = if self.config().read_only {
+            Some("the server is read-only")
+        } else if *self.access.read().unwrap_or_else(|e| e.into_inner()) == Access::Read
+            || (context.extensions.get::<Parts>().is_some()
+                && self.granted_access(context)? == Access::Read)
+        {
+            Some(READ_ONLY_ACCESS)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => Err(McpError::invalid_request(
+                format!("Writing files is not available: {}", reason),
+                None,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Tools that can't work in the current setup, and why
+    fn unavailable_tools(&self) -> BTreeMap<Cow<'static, str>, &'static str> {
+        let mut tools = BTreeMap::new();
+        if archive::is_archive(&self.patterns_dir) {
+            for tool in WRITE_TOOLS {
+                tools.insert(Cow::Borrowed(*tool), "the pattern library is a read-only archive");
+            }
+        }
+        let read_only = if self.config().read_only {
+            Some("the server is read-only")
+        } else if *self.access.read().unwrap_or_else(|e| e.into_inner()) == Access::Read {
+            Some(READ_ONLY_ACCESS)
+        } else {
+            None
+        };
+        if let Some(reason) = read_only {
+            for tool in self.tool_router.map.keys().filter(|tool| self.mutates(tool)) {
+                tools.entry(tool.clone()).or_insert(reason);
+            }
+        }
+        if self.embedder.is_none() {
+            tools.insert(
+                "semantic_search_patterns".into(),
+                "semantic search is not configured, add an [embeddings] section to grimoire.toml",
+            );
+        }
+        let sampling = self
+            .peer
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .and_then(|peer| peer.peer_info())
+            .is_some_and(|info| info.capabilities.sampling.is_some());
+        if !sampling {
+            tools
+                .entry("summarize_pattern".into())
+                .or_insert("the client doesn't support sampling");
+        }
+        tools
+    }
+
+    /// Recompute which tools are available, telling the client when the set changes
+    fn refresh_tools(&self) {
+        let unavailable = self.unavailable_tools();
+        {
+            let mut disabled = self.disabled_tools.write().unwrap_or_else(|e| e.into_inner());
+            if *disabled == unavailable {
+                return;
+            }
+            tracing::info!("Disabled tools: {:?}", unavailable.keys());
+            *disabled = unavailable;
+        }
+
+        let peer = self.peer.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let (Some(peer), Ok(runtime)) = (peer, tokio::runtime::Handle::try_current()) {
+            runtime.spawn(async move {
+                let _ = peer.notify_tool_list_changed().await;
+            });
+        }
+    }
+
+    /// Reload the library whenever pattern files change on disk, in the patterns directory or
+    /// a mounted one, until the returned watcher is dropped. Archives never change, so they
+    /// are not watched
+    pub fn watch(&self) -> notify::Result<Option<RecommendedWatcher>> {
+        let watcher = self.watch_dir(&self.patterns_dir)?;
+        self.watch_mounts();
+        Ok(watcher)
+    }
+
+    /// Replace the watchers of the mounted directories with ones for the configured mounts
+    fn watch_mounts(&self) {
+        let watchers = self
+            .config()
+            .mounts
+            .iter()
+            .filter_map(|mount| {
+                self.watch_dir(&mount.path)
+                    .inspect_err(|e| tracing::warn!("Not watching {:?}: {}", mount.path, e))
+                    .ok()
+                    .flatten()
+            })
+            .collect();
+        *self.mount_watchers.lock().unwrap_or_else(|e| e.into_inner()) = watchers;
+    }
+
+    /// Switch to the settings of a changed config file and reload the library with them.
+    /// Clients are told if their tool list changes, as when read-only mode is turned on.
+    /// Settings only read at startup are kept, with a warning when they changed
+    pub fn apply_config(&self, config: Config) {
+        let (config, restart) = self.config().live_update(config);
+        if !restart.is_empty() {
+            tracing::warn!("Restart the server to apply the changes to {}", restart.join(", "));
+        }
+        self.config.store(Arc::new(config));
+        self.watch_mounts();
+        tracing::info!("Config reloaded");
+        match self.reload() {
+            // Every session refreshes its tools when told about the reload
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("{}", e.message);
+                self.refresh_tools();
+            }
+        }
+    }
+
+    fn watch_dir(&self, dir: &Path) -> notify::Result<Option<RecommendedWatcher>> {
+        if archive::is_archive(dir) {
+            return Ok(None);
+        }
+        let runtime = tokio::runtime::Handle::current();
+        let patterns = self.clone();
+        watch::watch(dir, self.config.clone(), move || {
+            let _guard = runtime.enter();
+            if let Err(e) = patterns.reload() {
+                tracing::warn!("{}", e.message);
+            }
+        })
+        .map(Some)
+    }
+
+    /// The directories the library is loaded from: the patterns directory and the ones
+    /// found under the client's roots, or only those when configured to replace it
+    fn pattern_dirs(&self) -> Vec<PathBuf> {
+        let root_dirs = self.root_dirs.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if self.config().roots.replace && !root_dirs.is_empty() {
+            return root_dirs;
+        }
+        std::iter::once(self.patterns_dir.clone()).chain(root_dirs).collect()
+    }
+
+    /// Ask the client for its roots and reload the library if the pattern directories
+    /// found under them changed
+    async fn refresh_roots(&self, peer: Peer<RoleServer>) {
+        let supported = peer
+            .peer_info()
+            .is_some_and(|info| info.capabilities.roots.is_some());
+        if !self.config().roots.enabled || !supported || self.remote {
+            return;
+        }
+        let roots = match peer.list_roots().await {
+            Ok(result) => result.roots,
+            Err(e) => {
+                tracing::warn!("Failed to list the client's roots: {}", e);
+                return;
+            }
+        };
+
+        let patterns_dir = self.patterns_dir.canonicalize().ok();
+        let mut dirs: Vec<PathBuf> = roots
+            .iter()
+            .filter_map(|root| roots::path(&root.uri))
+            .flat_map(|root| roots::discover(&root, &self.config().roots.dirs))
+            .filter(|dir| dir.canonicalize().ok() != patterns_dir)
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        {
+            let mut root_dirs = self.root_dirs.write().unwrap_or_else(|e| e.into_inner());
+            if *root_dirs == dirs {
+                return;
+            }
+            tracing::info!("Pattern directories under the client's roots: {:?}", dirs);
+            *root_dirs = dirs.clone();
+        }
+
+        let watchers = dirs
+            .iter()
+            .filter_map(|dir| {
+                self.watch_dir(dir)
+                    .inspect_err(|e| tracing::warn!("Failed to watch {:?}: {}", dir, e))
+                    .ok()
+                    .flatten()
+            })
+            .collect();
+        *self.root_watchers.lock().unwrap_or_else(|e| e.into_inner()) = watchers;
+        if let Err(e) = self.reload() {
+            tracing::warn!("{}", e.message);
+        }
+    }
+}
+
+#[tool_router]
+impl Patterns {
+    pub fn new(config: Config) -> Self {
+        let patterns_dir = config
+            .patterns_dir
+            .clone()
+            .expect("callers check a patterns directory is configured");
+        let embedder: Option<Arc<dyn Embedder>> = config
+            .embeddings
+            .as_ref()
+            .map(|c| Arc::from(embeddings::embedder(c)));
+        let library = Library::load(&config, &patterns_dir, embedder.as_deref());
+        for diagnostic in diagnostics::run(&config, &patterns_dir, &library) {
+            log_diagnostic(&diagnostic);
+        }
+        let feedback = FeedbackStore::load(&patterns_dir);
+        let rate_limit = config.rate_limit.clone();
+        let patterns = Self {
+            config: Arc::new(ArcSwap::from_pointee(config)),
+            patterns_dir,
+            library: Arc::new(ArcSwap::from_pointee(Snapshot {
+                library: Arc::new(library),
+                generation: 0,
+            })),
+            feedback: Arc::new(ArcSwap::from_pointee(feedback)),
+            publish: Arc::default(),
+            embedder,
+            tool_router: Self::tool_router(),
+            recorder: None,
+            peer: Arc::default(),
+            subscriptions: Arc::default(),
+            disabled_tools: Arc::default(),
+            root_dirs: Arc::default(),
+            root_watchers: Arc::default(),
+            mount_watchers: Arc::default(),
+            reloads: broadcast::channel(RELOAD_BACKLOG).0,
+            remote: false,
+            closed: CancellationToken::new(),
+            drafts: Arc::default(),
+            access: Arc::default(),
+            tokens: None,
+            calls: TaskTracker::new(),
+            rate_limiter: Arc::new(RateLimiter::new(rate_limit)),
+            client: None,
+        };
+        patterns.refresh_tools();
+        patterns
+    }
+
+    /// A handler for another client connecting over the network, sharing the library
+    /// with this one but with its own peer, subscriptions and tool list
+    pub fn remote_session(&self) -> Self {
+        let session = Self {
+            peer: Arc::default(),
+            subscriptions: Arc::default(),
+            disabled_tools: Arc::default(),
+            remote: true,
+            closed: CancellationToken::new(),
+            drafts: Arc::default(),
+            access: Arc::default(),
+            ..self.clone()
+        };
+        session.refresh_tools();
+        session
+    }
+
+    /// Require the session's client to authenticate with one of the tokens when it
+    /// initializes, unless there are none
+    pub fn with_tokens(mut self, tokens: Vec<(String, Access)>) -> Self {
+        self.tokens = (!tokens.is_empty()).then(|| Arc::new(tokens));
+        self
+    }
+
+    /// Rate limit the session's tool calls as those of the client at `address`
+    pub fn with_client(mut self, address: IpAddr) -> Self {
+        self.client = Some(address);
+        self
+    }
+
+    /// The access a request was granted by its token: from its HTTP headers, checked by
+    /// the HTTP transports, or from the `_meta` of an initialize request
+    fn granted_access(&self, context: &RequestContext<RoleServer>) -> Result<Access, McpError> {
+        if let Some(parts) = context.extensions.get::<Parts>() {
+            return Ok(parts.extensions.get::<Access>().copied().unwrap_or_default());
+        }
+        let Some(tokens) = &self.tokens else {
+            return Ok(Access::Write);
+        };
+        let authorization = context.meta.get("authorization").and_then(|v| v.as_str());
+        http::access(tokens, authorization).ok_or_else(|| {
+            McpError::invalid_request(
+                "Unauthorized: send \"authorization\": \"Bearer <token>\" in the _meta of \
+                 the initialize request",
+                None,
+            )
+        })
+    }
+
+    /// End this session's background tasks, once its client has disconnected
+    pub fn close(&self) {
+        self.closed.cancel();
+    }
+
+    /// The number of patterns when the library can serve clients, otherwise why not: its
+    /// directory can't be read, or the server is shutting down
+    pub fn readiness(&self) -> Result<usize, String> {
+        if self.calls.is_closed() {
+            return Err("the server is shutting down".to_string());
+        }
+        if let Some(problem) = diagnostics::unreadable(&self.patterns_dir) {
+            return Err(problem.message);
+        }
+        Ok(self.library().patterns.len())
+    }
+
+    /// Refuse new tool calls and wait up to `grace` for the ones in progress to finish,
+    /// then for any reload still writing the index caches
+    pub async fn shutdown(&self, grace: Duration) {
+        self.calls.close();
+        if !self.calls.is_empty() {
+            tracing::info!("Waiting for {} tool calls to finish", self.calls.len());
+        }
+        if tokio::time::timeout(grace, self.calls.wait()).await.is_err() {
+            tracing::warn!(
+                "{} tool calls still running after {:?}, stopping anyway",
+                self.calls.len(),
+                grace
+            );
+        }
+        let publish = self.publish.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            drop(publish.lock().unwrap_or_else(|e| e.into_inner()));
+        })
+        .await;
+    }
+
+    /// Record every tool call made against this server
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(Arc::new(recorder));
+        self
+    }
+
+    /// Get all available patterns
+    #[tool(
+        description = "List available patterns, optionally filtered by created or updated date and sorted, paginated with limit and offset",
+        annotations(read_only_hint = true)
+    )]
+    fn list_patterns(
+        &self,
+        Parameters(ListPatternsRequest {
+            limit,
+            offset,
+            cursor,
+            sort_by,
+            order,
+            created_after,
+            created_before,
+            updated_after,
+            updated_before,
+            format,
+        }): Parameters<ListPatternsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let dates =
+            DateFilters::resolve([created_after, created_before, updated_after, updated_before])?;
+        let library = self.library();
+        let mut patterns: Vec<&Pattern> =
+            library.patterns.iter().filter(|p| dates.matches(p)).collect();
+        if let Some(sort_by) = sort_by {
+            sort_patterns(&mut patterns, |p| (*p, None), sort_by, order);
+        }
+
+        let page = Page::new(&self.config().limits, limit, offset)
+            .with_cursor(cursor.as_deref(), &patterns)?;
+        Ok(Self::list_summary("Available patterns", &patterns, &page, format))
+    }
+
+    /// Get every pattern used in a project
+    #[tool(
+        description = "List the patterns used in a given project",
+        annotations(read_only_hint = true)
+    )]
+    fn list_patterns_by_project(
+        &self,
+        Parameters(ProjectPatternsRequest {
+            project,
+            limit,
+            offset,
+            cursor,
+            format,
+        }): Parameters<ProjectPatternsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let patterns: Vec<&Pattern> = library
+            .patterns
+            .iter()
+            .filter(|p| p.used_in(&project))
+            .collect();
+        if patterns.is_empty() && format == OutputFormat::Markdown {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "No patterns found for project '{}'.",
+                project
+            ))]));
+        }
+
+        let page = Page::new(&self.config().limits, limit, offset)
+            .with_cursor(cursor.as_deref(), &patterns)?;
+        Ok(Self::list_summary(
+            &format!("Patterns used in '{}'", project),
+            &patterns,
+            &page,
+            format,
+        ))
+    }
+
+    /// Get every pattern whose code imports a dependency
+    #[tool(
+        description = "List the patterns whose code blocks import a dependency (a Rust crate, Python package, npm package or Go module), e.g. to find every pattern still demonstrating a deprecated library",
+        annotations(read_only_hint = true)
+    )]
+    fn patterns_using_dependency(
+        &self,
+        Parameters(DependencyPatternsRequest {
+            dependency,
+            limit,
+            offset,
+            cursor,
+            format,
+        }): Parameters<DependencyPatternsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let (name, patterns) = library.dependency(&dependency).unwrap_or((&dependency, Vec::new()));
+        if patterns.is_empty() && format == OutputFormat::Markdown {
+            let known = library.dependencies.keys().map(String::as_str);
+            let suggestions = search::complete(&dependency, known);
+            let mut message = format!("No patterns import '{}'.", dependency);
+            if !suggestions.is_empty() {
+                message.push_str(&format!(
+                    " Indexed dependencies with similar names: {}",
+                    suggestions[..suggestions.len().min(MAX_SUGGESTIONS)].join(", ")
+                ));
+            }
+            return Ok(CallToolResult::success(vec![Content::text(message)]));
+        }
+
+        let page = Page::new(&self.config().limits, limit, offset)
+            .with_cursor(cursor.as_deref(), &patterns)?;
+        Ok(Self::list_summary(
+            &format!("Patterns importing '{}'", name),
+            &patterns,
+            &page,
+            format,
+        ))
+    }
+
+    /// Count the patterns by category and framework, and the dependencies their code imports
+    #[tool(
+        description = "Summarize the library: how many patterns it holds, by category and framework, and which dependencies their code blocks import, most used first",
+        annotations(read_only_hint = true)
+    )]
+    fn library_stats(
+        &self,
+        Parameters(LibraryStatsRequest { format }): Parameters<LibraryStatsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let mut categories: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut frameworks: BTreeMap<&str, usize> = BTreeMap::new();
+        for pattern in &library.patterns {
+            *categories.entry(&pattern.metadata.category).or_default() += 1;
+            for framework in &pattern.metadata.frameworks {
+                *frameworks.entry(framework).or_default() += 1;
+            }
+        }
+        let mut dependencies: Vec<(&str, usize)> = library
+            .dependencies
+            .iter()
+            .map(|(name, patterns)| (name.as_str(), patterns.len()))
+            .collect();
+        dependencies.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let drafts = library.patterns.iter().filter(|p| p.metadata.draft).count();
+        let read_only = library.patterns.iter().filter(|p| p.read_only).count();
+
+        if format == OutputFormat::Json {
+            let counts = |entries: &[(&str, usize)]| {
+                entries
+                    .iter()
+                    .map(|(name, count)| serde_json::json!({ "name": name, "patterns": count }))
+                    .collect::<Vec<_>>()
+            };
+            return Ok(CallToolResult::structured(serde_json::json!({
+                "patterns": library.patterns.len(),
+                "drafts": drafts,
+                "read_only": read_only,
+                "generation": self.generation(),
+                "categories": categories,
+                "frameworks": frameworks,
+                "dependencies": counts(&dependencies),
+            })));
+        }
+
+        let list = |entries: Vec<(&str, usize)>| {
+            let listed: Vec<String> = entries
+                .iter()
+                .map(|(name, count)| format!("{} ({})", name, count))
+                .collect();
+            if listed.is_empty() { "none".to_string() } else { listed.join(", ") }
+        };
+        let mut message = format!(
+            "{} patterns ({} drafts, {} read-only), generation {}\n",
+            library.patterns.len(),
+            drafts,
+            read_only,
+            self.generation()
+        );
+        message.push_str(&format!("\nCategories: {}", list(categories.into_iter().collect())));
+        message.push_str(&format!("\nFrameworks: {}", list(frameworks.into_iter().collect())));
+        let more = dependencies.len().saturating_sub(STATS_DEPENDENCIES);
+        dependencies.truncate(STATS_DEPENDENCIES);
+        message.push_str(&format!("\nDependencies: {}", list(dependencies)));
+        if more > 0 {
+            message.push_str(&format!(
+                " and {} more, see patterns_using_dependency or the json format",
+                more
+            ));
+        }
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    /// Report whether everything the server depends on works
+    #[tool(
+        description = "Check the server's health: whether the patterns directory is readable and writable, the caches are valid, git history is available and the embedding model works. Each problem comes with the steps to fix it. Call this when tools fail unexpectedly or the library looks empty",
+        annotations(read_only_hint = true)
+    )]
+    fn server_status(
+        &self,
+        Parameters(ServerStatusRequest { format }): Parameters<ServerStatusRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let checks = diagnostics::run(&self.config(), &self.patterns_dir, &library);
+        let worst = checks.iter().map(|d| d.severity).max().unwrap_or(Severity::Ok);
+        if format == OutputFormat::Json {
+            return Ok(CallToolResult::structured(serde_json::json!({
+                "status": worst,
+                "version": env!("CARGO_PKG_VERSION"),
+                "read_only": self.config().read_only,
+                "patterns_dir": self.patterns_dir,
+                "patterns": library.patterns.len(),
+                "generation": self.generation(),
+                "checks": checks,
+            })));
+        }
+
+        let mut message = format!(
+            "Status: {} (grimoire-mcp {}), serving {} patterns from {}{}, generation {}\n",
+            worst,
+            env!("CARGO_PKG_VERSION"),
+            library.patterns.len(),
+            self.patterns_dir.display(),
+            if self.config().read_only { " read-only" } else { "" },
+            self.generation()
+        );
+        for check in &checks {
+            message.push_str(&format!("\n- {} {}: {}", check.severity, check.check, check.message));
+            if let Some(remedy) = &check.remedy {
+                message.push_str(&format!("\n  Fix: {}", remedy));
+            }
+        }
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    /// Search patterns based on input
+    #[tool(
+        description = "Search patterns by query, categories, framework, tags, project, namespace, author, maturity or difficulty, optionally excluding tags, categories or text. The query tolerates typos (or is a regular expression when regex is set) and results are ranked by BM25 relevance across name, tags and body. Set mode to semantic or hybrid to also match by meaning (needs embeddings). Set code_only to search only inside fenced code blocks and explain to see how scores break down. When nothing matches, the search retries with looser constraints and says which were dropped. Results can be sorted with sort_by/order and are paginated with limit and offset",
+        annotations(read_only_hint = true)
+    )]
+    pub(crate) fn search_patterns(
+        &self,
+        Parameters(PatternSearchRequest {
+            query,
+            category,
+            framework,
+            tag,
+            project,
+            namespace,
+            author,
+            maturity,
+            difficulty,
+            match_all_tags,
+            regex,
+            case_sensitive,
+            whole_word,
+            stemming,
+            relax,
+            exclude_tags,
+            exclude_category,
+            exclude_query,
+            created_after,
+            created_before,
+            created_within,
+            updated_after,
+            updated_before,
+            updated_within,
+            limit,
+            offset,
+            code_only,
+            snippet_length,
+            sort_by,
+            order,
+            explain,
+            format,
+            mode,
+        }): Parameters<PatternSearchRequest>,
+        deadline: Deadline,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        // Fold tag:, category:, framework:, project:, namespace:, author:, maturity: and
+        // difficulty: qualifiers into the filters
+        let (mut category, mut framework, mut tag, mut project) = (category, framework, tag, project);
+        let (mut namespace, mut author) = (namespace, author);
+        let (mut maturity, mut difficulty) = (maturity, difficulty);
+        let query = match query {
+            Some(q) if !regex => {
+                let (qualifiers, rest) = query::extract_qualifiers(&q);
+                tag.extend(qualifiers.tags);
+                category.extend(qualifiers.categories);
+                project.extend(qualifiers.projects);
+                namespace.extend(qualifiers.namespaces);
+                author.extend(qualifiers.authors);
+                maturity.extend(qualifiers.maturities);
+                difficulty.extend(qualifiers.difficulties);
+                framework.extend(qualifiers.frameworks);
+                Some(rest).filter(|r| !r.trim().is_empty())
+            }
+            query => query,
+        };
+
+        // Compare filters in the same normalized form as the loaded metadata
+        let category = normalize_all(&category);
+        let framework = normalize_all(&framework);
+        let tag = normalize_all(&tag);
+        let maturity = normalize_all(&maturity);
+        let difficulty = normalize_all(&difficulty);
+        let exclude_tags = normalize_all(&exclude_tags);
+        let exclude_category = normalize_all(&exclude_category);
+
+        let dates = DateFilters::resolve([
+            created_within.or(created_after),
+            created_before,
+            updated_within.or(updated_after),
+            updated_before,
+        ])?;
+
+        let index = if code_only {
+            &library.code_index
+        } else {
+            &library.index
+        };
+        // The text searched and excerpted for each pattern
+        let searchable = |p: &Pattern| {
+            if code_only {
+                search::code_blocks(&p.content)
+            } else {
+                p.content.clone()
+            }
+        };
+
+        let matcher = query
+            .as_deref()
+            .map(|q| {
+                let options = search::MatchOptions {
+                    case_sensitive,
+                    whole_word,
+                    stemming,
+                };
+                search::QueryMatcher::new(index, q, regex, options)
+            })
+            .transpose()
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        // Patterns passing the filters, apart from the ones a search that found nothing relaxed
+        let filter = |relaxed: &[Relaxed]| -> Vec<(usize, &Pattern)> {
+            library
+            .patterns
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| { // Search through the fields
+                (category.is_empty() || category.contains(&p.metadata.category))
+                    && (framework.is_empty()
+                        || relaxed.contains(&Relaxed::Framework)
+                        || framework.iter().any(|f| p.metadata.frameworks.contains(f)))
+                    && (tag.is_empty()
+                        || relaxed.contains(&Relaxed::Tags)
+                        || if match_all_tags {
+                            tag.iter().all(|t| p.metadata.tags.contains(t))
+                        } else {
+                            tag.iter().any(|t| p.metadata.tags.contains(t))
+                        })
+                    && (project.is_empty() || project.iter().any(|pr| p.used_in(pr)))
+                    && (namespace.is_empty() || namespace.iter().any(|n| p.in_namespace(n)))
+                    && (author.is_empty() || author.iter().any(|a| p.by_author(a)))
+                    && (maturity.is_empty()
+                        || p.metadata.maturity.as_ref().is_some_and(|m| maturity.contains(m)))
+                    && (difficulty.is_empty()
+                        || p.metadata.difficulty.as_ref().is_some_and(|d| difficulty.contains(d)))
+                    && dates.matches(p)
+                    && !exclude_category.contains(&p.metadata.category)
+                    && !exclude_tags.iter().any(|t| p.metadata.tags.contains(t))
+                    && exclude_query.as_ref().is_none_or(|q| {
+                        let searchable = format!("{} {}", p.metadata.pattern, p.content).to_lowercase();
+                        !searchable.contains(&q.to_lowercase())
+                    })
+            })
+            .collect()
+        };
+        let keyword_search = |relaxed: &[Relaxed], matcher: Option<&search::QueryMatcher>| {
+            let filtered = filter(relaxed);
+            let mut keyword_results: Vec<(usize, &Pattern, Option<f64>)> = Vec::new();
+            for (n, &(i, p)) in filtered.iter().enumerate() {
+                if n.is_multiple_of(cancel::CHECK_INTERVAL) {
+                    deadline.check(n, filtered.len(), "patterns searched")?;
+                }
+                match matcher {
+                    // Match query to the pattern name, frontmatter and content
+                    Some(m) => {
+                        let text = if code_only {
+                            searchable(p)
+                        } else {
+                            let fields: Vec<String> =
+                                p.search_fields().into_iter().map(|(_, text)| text).collect();
+                            fields.join(" ")
+                        };
+                        if let Some(score) = m.score(index, i, &text) {
+                            keyword_results.push((i, p, Some(score)));
+                        }
+                    }
+                    None => keyword_results.push((i, p, None)),
+                }
+            }
+            Ok::<_, McpError>((filtered, keyword_results))
+        };
+
+        let mut relaxed: Vec<Relaxed> = Vec::new();
+        let (mut filtered, mut keyword_results) = keyword_search(&relaxed, matcher.as_ref())?;
+        // Nothing found: drop the tag filter, then the framework filter, then match any
+        // query word loosely, until something turns up
+        let mut matcher = matcher;
+        if keyword_results.is_empty() && relax && mode == SearchMode::Keyword {
+            for step in [Relaxed::Tags, Relaxed::Framework, Relaxed::Query] {
+                match step {
+                    Relaxed::Tags if tag.is_empty() => continue,
+                    Relaxed::Framework if framework.is_empty() => continue,
+                    Relaxed::Query => {
+                        let Some(loose) = matcher
+                            .as_ref()
+                            .zip(query.as_deref())
+                            .and_then(|(m, q)| m.relaxed(index, q))
+                        else {
+                            continue;
+                        };
+                        matcher = Some(loose);
+                    }
+                    _ => {}
+                }
+                relaxed.push(step);
+                (filtered, keyword_results) = keyword_search(&relaxed, matcher.as_ref())?;
+                if !keyword_results.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        let mut results: Vec<(&Pattern, Option<f64>)> = match (mode, query.as_deref()) {
+            (SearchMode::Keyword, _) => keyword_results
+                .into_iter()
+                .map(|(_, p, score)| (p, score))
+                .collect(),
+            (SearchMode::Semantic, Some(query)) => self
+                .semantic_ranking(&library, query, &filtered, &deadline)?
+                .into_iter()
+                .map(|(i, score)| (&library.patterns[i], Some(score)))
+                .collect(),
+            (SearchMode::Hybrid, Some(query)) => {
+                let mut keyword_ranking = keyword_results;
+                keyword_ranking
+                    .sort_by(|(_, _, a), (_, _, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+                let keyword_ranking: Vec<usize> =
+                    keyword_ranking.into_iter().map(|(i, _, _)| i).collect();
+                let semantic_ranking: Vec<usize> = self
+                    .semantic_ranking(&library, query, &filtered, &deadline)?
+                    .into_iter()
+                    .map(|(i, _)| i)
+                    .collect();
+                search::reciprocal_rank_fusion(&[keyword_ranking, semantic_ranking])
+                    .into_iter()
+                    .map(|(i, score)| (&library.patterns[i], Some(score)))
+                    .collect()
+            }
+            (_, None) => {
+                return Err(McpError::invalid_params(
+                    "semantic and hybrid search need a query",
+                    None,
+                ));
+            }
+        };
+
+        if results.is_empty() && format == OutputFormat::Markdown {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "No patterns found.",
+            )]));
+        }
+        let relaxed_note = if relaxed.is_empty() || results.is_empty() {
+            String::new()
+        } else {
+            let dropped: Vec<String> =
+                relaxed.iter().map(|r| r.describe(&tag, &framework)).collect();
+            format!(
+                "_No patterns matched the search as given. Showing results after {}._\n\n",
+                dropped.join(", then ")
+            )
+        };
+
+        // Nudge scores by reported usefulness, curator priority and quality, then most
+        // relevant first
+        let feedback = self.feedback.load();
+        let quality_weight = self.config().ranking.quality_weight;
+        let now = SystemTime::now();
+        let quality = |p: &Pattern| quality::score(&library, p, now);
+        for (p, score) in results.iter_mut() {
+            if let Some(score) = score {
+                *score *= feedback.boost(&p.metadata.pattern)
+                    * search::priority_boost(p.metadata.priority)
+                    * quality::boost(quality(p).score, quality_weight);
+            }
+        }
+        results.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+        let mut hits = dedup_results(results);
+        if let Some(sort_by) = sort_by.filter(|s| *s != SortBy::Relevance || order.is_some()) {
+            sort_patterns(&mut hits, |h| (h.pattern, h.score), sort_by, order);
+        }
+        let page = Page::new(&self.config().limits, limit, offset);
+        let snippet_length = snippet_length.clamp(1, search::MAX_SNIPPET_LENGTH);
+        // Which fields the query matched, so a hit on a tag is told apart from one in the body
+        let matched_fields = |p: &Pattern| -> Vec<String> {
+            match &matcher {
+                Some(m) if !code_only => p
+                    .search_fields()
+                    .into_iter()
+                    .filter(|(_, text)| !m.match_ranges(text).is_empty())
+                    .map(|(field, _)| field)
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+        let excerpt = |p: &Pattern| match &matcher {
+            Some(m) => {
+                let text = searchable(p);
+                search::snippet(&text, &m.match_ranges(&text), snippet_length)
+            }
+            None => search::snippet(&p.content, &[], snippet_length),
+        };
+        if format == OutputFormat::Json {
+            let results: Vec<PatternSummary> = hits
+                .iter()
+                .skip(page.offset)
+                .take(page.limit)
+                .map(|hit| PatternSummary {
+                    score: hit.score,
+                    alternates: hit
+                        .alternates
+                        .iter()
+                        .map(|a| a.metadata.pattern.as_str())
+                        .collect(),
+                    matched_fields: matched_fields(hit.pattern),
+                    quality: explain.then(|| quality(hit.pattern).score),
+                    ..PatternSummary::new(hit.pattern, excerpt(hit.pattern))
+                })
+                .collect();
+            let mut response = page.json(&results, hits.len());
+            if !relaxed.is_empty() && !hits.is_empty() {
+                response["relaxed"] = relaxed.iter().map(|r| r.name()).collect();
+            }
+            return Ok(CallToolResult::structured(response));
+        }
+
+        let summary: Vec<String> = hits
+            .iter()
+            .skip(page.offset)
+            .take(page.limit)
+            .map(|SearchHit { pattern: p, score, alternates }| {
+                let quality = explain.then(|| quality(p));
+                let score = score
+                    .map(|s| match &quality {
+                        Some(quality) => {
+                            let feedback = feedback.boost(&p.metadata.pattern);
+                            let priority = search::priority_boost(p.metadata.priority);
+                            let boost = quality::boost(quality.score, quality_weight);
+                            format!(
+                                " (score: {:.2} = relevance {:.2} × feedback {:.2} × priority {:.2} × quality {:.2})",
+                                s,
+                                s / (feedback * priority * boost),
+                                feedback,
+                                priority,
+                                boost
+                            )
+                        }
+                        None => format!(" (score: {:.2})", s),
+                    })
+                    .unwrap_or_default();
+                let quality = quality
+                    .map(|q| format!("\n_Quality: {:.2} ({})_", q.score, q.breakdown()))
+                    .unwrap_or_default();
+                let alternates = if alternates.is_empty() {
+                    String::new()
+                } else {
+                    let copies: Vec<String> = alternates
+                        .iter()
+                        .map(|a| format!("{} ({})", a.qualified_name(), a.filepath.display()))
+                        .collect();
+                    format!("\n_Also matched as: {}_", copies.join(", "))
+                };
+                let fields = matched_fields(p);
+                let fields = if fields.is_empty() {
+                    String::new()
+                } else {
+                    format!("\n_Matched in: {}_", fields.join(", "))
+                };
+                let description = p
+                    .metadata
+                    .description
+                    .as_ref()
+                    .map(|description| format!(" - {}", description))
+                    .unwrap_or_default();
+                format!(
+                    "**{}**{}{}{}{}{}\n{}",
+                    p.qualified_name(),
+                    description,
+                    score,
+                    quality,
+                    alternates,
+                    fields,
+                    excerpt(p)
+                )
+            })
+            .collect();
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{}{}{}",
+            relaxed_note,
+            summary.join("\n\n"),
+            page.footer(hits.len())
+        ))]))
+    }
+
+    /// Search patterns by meaning rather than keywords
+    #[tool(
+        description = "Find patterns that are conceptually related to a natural language query, even when they use different words (e.g. 'retry with backoff' finds 'exponential retries'). Returns nearest neighbors with cosine similarity, paginated with limit and offset",
+        annotations(read_only_hint = true)
+    )]
+    fn semantic_search_patterns(
+        &self,
+        Parameters(SemanticSearchRequest {
+            query,
+            limit,
+            offset,
+        }): Parameters<SemanticSearchRequest>,
+        deadline: Deadline,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let candidates: Vec<(usize, &Pattern)> = library.patterns.iter().enumerate().collect();
+        let results: Vec<(&Pattern, Option<f64>)> = self
+            .semantic_ranking(&library, &query, &candidates, &deadline)?
+            .into_iter()
+            .map(|(i, score)| (&library.patterns[i], Some(score)))
+            .collect();
+
+        let hits = dedup_results(results);
+        let page = Page::new(&self.config().limits, limit, offset);
+        let summary: Vec<String> = hits
+            .iter()
+            .skip(page.offset)
+            .take(page.limit)
+            .map(|hit| {
+                let p = hit.pattern;
+                format!(
+                    "**{}** (similarity: {:.2})\n{}",
+                    p.metadata.pattern,
+                    hit.score.unwrap_or_default(),
+                    search::snippet(&p.content, &[], default_snippet_length())
+                )
+            })
+            .collect();
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{}{}",
+            summary.join("\n\n"),
+            page.footer(hits.len())
+        ))]))
+    }
+
+    /// Get the pattern based on the name
+    #[tool(
+        description = "Get the pattern based on the pattern name, falling back to the closest matching name. Use 'name#section' to fetch a single section. ${VAR:-default} placeholders for allowed environment variables and ![[name#section]] embeds are expanded and [[name#section]] references are listed. Patterns declaring arguments are templates: pass arguments to fill in their {{name}} placeholders. Set provenance to mark where each chunk came from, or card for a compact summary card",
+        annotations(read_only_hint = true)
+    )]
+    pub(crate) fn get_pattern(
+        &self,
+        Parameters(GetPatternRequest {
+            pattern_name,
+            section,
+            arguments,
+            provenance,
+            card,
+        }): Parameters<GetPatternRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let (name, anchor) = links::split_anchor(&pattern_name);
+        let anchor = section.as_deref().or(anchor);
+
+        let (pattern, note) = match library.find_pattern(name) {
+            // A plain name can be shared by patterns in other namespaces
+            Some(p) if split_namespace(name).0.is_none() => {
+                let others: Vec<String> = library
+                    .namesakes(name)
+                    .into_iter()
+                    .filter(|other| !std::ptr::eq(*other, p))
+                    .map(|other| other.qualified_name().into_owned())
+                    .collect();
+                let note = if others.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        "Showing '{}', also found as: {}. Ask for one of those names to get it \
+                         instead\n\n",
+                        p.qualified_name(),
+                        others.join(", ")
+                    )
+                };
+                (p, note)
+            }
+            Some(p) => (p, String::new()),
+            None => {
+                // Fall back to the closest name, so small typos still resolve
+                let closest = library
+                    .patterns
+                    .iter()
+                    .map(|p| {
+                        // Namespaced names are compared with the namespace
+                        let candidate = match split_namespace(name).0 {
+                            Some(_) => p.qualified_name(),
+                            None => Cow::Borrowed(p.metadata.pattern.as_str()),
+                        };
+                        (p, search::similarity(name, &candidate))
+                    })
+                    .filter(|(_, score)| *score >= search::FUZZY_THRESHOLD)
+                    .max_by(|(_, a), (_, b)| {
+                        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+                    });
+                match closest {
+                    Some((p, score)) => (
+                        p,
+                        format!(
+                            "Pattern '{}' not found, showing closest match '{}' (score: {:.2})\n\n",
+                            name, p.qualified_name(), score
+                        ),
+                    ),
+                    None => {
+                        return Ok(CallToolResult::success(vec![Content::text(format!(
+                            "Pattern '{}' not found.",
+                            name
+                        ))]));
+                    }
+                }
+            }
+        };
+
+        let mut note = note;
+        if let Some(base) = pattern
+            .metadata
+            .extends
+            .as_ref()
+            .filter(|base| library.find_pattern(base).is_none())
+        {
+            note.push_str(&format!(
+                "Base pattern '{}' not found, showing '{}' without inherited sections\n\n",
+                base, pattern.metadata.pattern
+            ));
+        }
+        let content = library.composed_body(pattern);
+        if card {
+            let card = PatternCard::new(pattern, &content);
+            return Ok(CallToolResult {
+                content: vec![Content::text(format!("{}{}", note, card.markdown()))],
+                structured_content: serde_json::to_value(&card).ok(),
+                is_error: Some(false),
+                meta: None,
+            });
+        }
+        let body = match anchor {
+            Some(anchor) => match links::extract_section(&content, anchor) {
+                Some(body) => body,
+                None => {
+                    return Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Section '{}' not found in pattern '{}'. Available sections: {}",
+                        anchor,
+                        pattern.metadata.pattern,
+                        links::section_anchors(&content).join(", ")
+                    ))]));
+                }
+            },
+            None => &content,
+        };
+
+        let mut visited = vec![match anchor {
+            Some(anchor) => format!("{}#{}", pattern.metadata.pattern, anchor),
+            None => pattern.metadata.pattern.clone(),
+        }];
+        let expanded = if provenance {
+            format!(
+                "{}\n{}\n{}",
+                provenance_marker(pattern, anchor, body),
+                library.expand_embeds_with_provenance(body, &mut visited).trim_end(),
+                PROVENANCE_END
+            )
+        } else {
+            library.expand_embeds(body, &mut visited)
+        };
+        let mut text = interpolate::expand(&expanded, &self.config().allowed_env);
+        let declared = &pattern.metadata.arguments;
+        match arguments {
+            Some(values) => {
+                text = pattern
+                    .fill_arguments(&text, &values)
+                    .map_err(|e| McpError::invalid_params(e, None))?;
+            }
+            None if !declared.is_empty() => {
+                let names: Vec<String> = declared
+                    .iter()
+                    .map(|a| match (&a.description, a.required) {
+                        (Some(d), true) => format!("{} (required): {}", a.name, d),
+                        (Some(d), false) => format!("{}: {}", a.name, d),
+                        (None, true) => format!("{} (required)", a.name),
+                        (None, false) => a.name.clone(),
+                    })
+                    .collect();
+                note.push_str(&format!(
+                    "Template with arguments, pass `arguments` to fill them in:\n- {}\n\n",
+                    names.join("\n- ")
+                ));
+            }
+            None => {}
+        }
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{}{}{}{}",
+            note,
+            text,
+            library.dependencies_summary(pattern),
+            library.references_summary(body)
+        ))]))
+    }
+
+    /// Create patterns by providing information
+    #[tool(
+        description = "Create patterns by providing, category, framework (optional), projects this pattern was used in, tags, a one-line description, and the content. Omitted category and framework fall back to configured defaults, and the configured default tags are added. The author falls back to the existing pattern's when overwriting, then to the configured default. Projects are also listed with today's date in a 'Used in' section. Overwriting an existing pattern needs the user's confirmation, asked by the client or given with confirm: true, and bumps its version (patch by default, or as given by bump). Look to existing patterns for examples on how this should look",
+        annotations(destructive_hint = true, idempotent_hint = false)
+    )]
+    async fn create_pattern(
+        &self,
+        Parameters(request): Parameters<CreatePatternRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let (result, _) = self.write_pattern(request, &context.peer).await?;
+        Ok(result)
+    }
+
+    /// Check the patterns a pattern named `name` would require and suggest exist, and that
+    /// requiring them doesn't make a cycle
+    fn check_links(
+        &self,
+        name: &str,
+        requires: &[String],
+        see_also: &[String],
+    ) -> Result<(), McpError> {
+        let library = self.library();
+        if requires.iter().chain(see_also).any(|n| n.eq_ignore_ascii_case(name)) {
+            return Err(McpError::invalid_params(
+                format!("Pattern '{}' can't require or refer to itself", name),
+                None,
+            ));
+        }
+        let unknown: Vec<&str> = requires
+            .iter()
+            .chain(see_also)
+            .filter(|n| library.find_pattern(n).is_none())
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(McpError::invalid_params(
+                format!(
+                    "Unknown patterns in requires or see_also: {}. Create them first or leave \
+                     them out",
+                    unknown.join(", ")
+                ),
+                None,
+            ));
+        }
+        for required in requires.iter().filter_map(|n| library.find_pattern(n)) {
+            // Cycles already in the library are pattern_path's to report
+            let Ok((chain, _)) = library.prerequisite_chain(required) else {
+                continue;
+            };
+            if chain.iter().any(|p| p.answers_to(name)) {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "'{}' already requires '{}', so requiring it would make a cycle",
+                        required.metadata.pattern, name
+                    ),
+                    None,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a new pattern file as create_pattern does. Also says whether the file was
+    /// written, as the user may decline to overwrite an existing one
+    async fn write_pattern(
+        &self,
+        CreatePatternRequest {
+            pattern_name,
+            category,
+            framework,
+            projects,
+            tag,
+            requires,
+            see_also,
+            description,
+            author,
+            content,
+            confirm,
+            bump,
+        }: CreatePatternRequest,
+        peer: &Peer<RoleServer>,
+    ) -> Result<(CallToolResult, bool), McpError> {
+        if archive::is_archive(&self.patterns_dir) {
+            return Err(McpError::invalid_request(
+                "The pattern library is a read-only archive",
+                None,
+            ));
+        }
+
+        // Validate Name
+        validate_pattern_name(&pattern_name).map_err(|e| McpError::invalid_params(e, None))?;
+        if content.len() > self.config().limits.max_content_bytes {
+            return Err(McpError::invalid_params(
+                format!(
+                    "Pattern content must be at most {} bytes",
+                    self.config().limits.max_content_bytes
+                ),
+                None,
+            ));
+        }
+
+        let projects = projects.unwrap_or_default();
+        let mut body = content;
+        if !projects.is_empty() {
+            let today = Local::now().date_naive();
+            for project in &projects {
+                body = add_usage_entry(&body, today, project, None);
+            }
+        }
+
+        let defaults = &self.config().defaults;
+        let category = defaults
+            .resolve("category", category, defaults.category.as_ref())
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let mut frameworks: Vec<String> =
+            framework.into_iter().filter_map(|f| normalize_optional(Some(f))).collect();
+        if frameworks.is_empty() {
+            let framework = defaults
+                .resolve("framework", None, defaults.framework.as_ref())
+                .map_err(|e| McpError::invalid_params(e, None))?;
+            frameworks.extend(framework);
+        }
+        let category = category.ok_or_else(|| {
+            McpError::invalid_params(
+                "category is required and no default category is configured",
+                None,
+            )
+        })?;
+        let mut tags = tag;
+        tags.extend(defaults.tags.iter().cloned());
+        let file_path = self.own_pattern_file(&pattern_name);
+        // Overwriting a pattern starts from its metadata, keeping what the request doesn't set,
+        // such as its ID, creation date, aliases and flashcards, and bumps its version
+        let existing = Pattern::load(&file_path).map(|existing| existing.metadata);
+        let overwritten = existing.is_some();
+        let version = match &existing {
+            Some(existing) => bump
+                .apply(existing.version.as_deref().unwrap_or(INITIAL_VERSION))
+                .map_err(|e| {
+                    McpError::invalid_params(format!("Pattern '{}': {}", pattern_name, e), None)
+                })?,
+            None => INITIAL_VERSION.to_string(),
+        };
+        let mut metadata = existing.unwrap_or_else(|| PatternMetadata {
+            pattern: pattern_name.clone(),
+            id: Some(new_id()),
+            created: Some(timestamp_now()),
+            updated: None,
+            version: None,
+            category: String::new(),
+            frameworks: Vec::new(),
+            description: None,
+            author: None,
+            maturity: None,
+            difficulty: None,
+            projects: Vec::new(),
+            tags: Vec::new(),
+            aliases: Vec::new(),
+            prerequisites: Vec::new(),
+            see_also: Vec::new(),
+            extends: None,
+            priority: 0,
+            draft: false,
+            publish: false,
+            published_url: None,
+            flashcards: Vec::new(),
+            arguments: Vec::new(),
+            extra: BTreeMap::new(),
+        });
+        if let Some(description) = description
+            .map(|d| d.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|d| !d.is_empty())
+        {
+            metadata.description = Some(description);
+        }
+        if let Some(requires) = requires {
+            metadata.prerequisites = requires;
+        }
+        if let Some(see_also) = see_also {
+            metadata.see_also = see_also;
+        }
+        self.check_links(&pattern_name, &metadata.prerequisites, &metadata.see_also)?;
+        metadata.author = defaults
+            .resolve("author", author, metadata.author.as_ref().or(defaults.author.as_ref()))
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        metadata.pattern = pattern_name.clone();
+        metadata.id.get_or_insert_with(new_id);
+        metadata.created.get_or_insert_with(timestamp_now);
+        metadata.version = Some(version.clone());
+        metadata.category = category;
+        metadata.frameworks = frameworks;
+        metadata.projects = projects;
+        metadata.tags = tags;
+        metadata.normalize();
+        let violations = self.config().validation.violations(&metadata);
+        if !violations.is_empty() {
+            return Err(McpError::invalid_params(
+                format!(
+                    "Pattern '{}' breaks the library's rules: {}",
+                    pattern_name,
+                    violations.join("; ")
+                ),
+                None,
+            ));
+        }
+        let pattern_content = rewrite_file(&file_path, &metadata, &body);
+
+        if file_path.exists() {
+            // The user decides when the client can ask them, otherwise the caller's confirm does
+            let question = format!(
+                "Pattern '{}' already exists at {:?}. Overwrite it?",
+                pattern_name, file_path
+            );
+            match Self::ask_confirmation(peer, question).await? {
+                Some(true) => {}
+                None if confirm => {}
+                Some(false) => {
+                    let message = format!("Pattern '{}' was not overwritten", pattern_name);
+                    return Ok((CallToolResult::success(vec![Content::text(message)]), false));
+                }
+                None => {
+                    let message = format!(
+                        "Pattern '{}' already exists at {:?}. Call again with confirm: true to overwrite it",
+                        pattern_name, file_path
+                    );
+                    return Ok((CallToolResult::success(vec![Content::text(message)]), false));
+                }
+            }
+        }
+
+        match fs::write(&file_path, pattern_content) {
+            Ok(_) => {
+                tracing::info!("Created pattern '{}' at {:?}", pattern_name, file_path);
+                let message = if overwritten {
+                    format!(
+                        "Pattern '{}' updated to version {} at {:?}",
+                        pattern_name, version, file_path
+                    )
+                } else {
+                    format!("Pattern '{}' created at {:?}", pattern_name, file_path)
+                };
+                Ok((CallToolResult::success(vec![Content::text(message)]), true))
+            }
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to create pattern: {}", e),
+                None,
+            )),
+        }
+    }
+
+    /// Store feedback on which returned patterns were useful
+    #[tool(
+        description = "Report which patterns returned by a search were actually useful for the task. This feedback tunes future search ranking",
+        annotations(destructive_hint = false, idempotent_hint = false)
+    )]
+    fn report_result_feedback(
+        &self,
+        Parameters(ResultFeedbackRequest {
+            query,
+            useful,
+            not_useful,
+        }): Parameters<ResultFeedbackRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        if useful.is_empty() && not_useful.is_empty() {
+            return Err(McpError::invalid_params(
+                "Provide at least one useful or not_useful pattern name",
+                None,
+            ));
+        }
+        let unknown: Vec<&String> = useful
+            .iter()
+            .chain(&not_useful)
+            .filter(|name| !library.patterns.iter().any(|p| &p.metadata.pattern == *name))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(McpError::invalid_params(
+                format!("Unknown patterns: {:?}", unknown),
+                None,
+            ));
+        }
+
+        let _publishing = self.publish.lock().unwrap_or_else(|e| e.into_inner());
+        let mut feedback = FeedbackStore::clone(&self.feedback.load());
+        match feedback.record(&useful, &not_useful) {
+            Ok(_) => {
+                self.feedback.store(Arc::new(feedback));
+                tracing::info!(
+                    "Result feedback for {:?}: useful {:?}, not useful {:?}",
+                    query,
+                    useful,
+                    not_useful
+                );
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Recorded feedback for {} patterns",
+                    useful.len() + not_useful.len()
+                ))]))
+            }
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to store feedback: {}", e),
+                None,
+            )),
+        }
+    }
+
+    /// Record that a pattern was used in a project
+    #[tool(
+        description = "Record that a pattern was used in a project. Adds the project to the pattern metadata and a dated entry to its 'Used in' section",
+        annotations(destructive_hint = false, idempotent_hint = false)
+    )]
+    fn record_pattern_usage(
+        &self,
+        Parameters(RecordPatternUsageRequest {
+            pattern_name,
+            project,
+            note,
+        }): Parameters<RecordPatternUsageRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        // Only patterns in the library, so the name can't point at a file outside it
+        let not_found =
+            || McpError::invalid_params(format!("Pattern '{}' not found.", pattern_name), None);
+        let found = library.find_pattern(&pattern_name).ok_or_else(not_found)?;
+        if found.read_only {
+            return Err(McpError::invalid_request(
+                format!("Pattern '{}' is in a read-only archive", pattern_name),
+                None,
+            ));
+        }
+        let file_path = found.filepath.clone();
+        let mut pattern = Pattern::load(&file_path).ok_or_else(not_found)?;
+
+        if !pattern.metadata.projects.contains(&project) {
+            pattern.metadata.projects.push(project.clone());
+        }
+        let body = add_usage_entry(
+            &pattern.content,
+            Local::now().date_naive(),
+            &project,
+            note.as_deref(),
+        );
+
+        match fs::write(&file_path, rewrite_file(&file_path, &pattern.metadata, &body)) {
+            Ok(_) => {
+                tracing::info!("Recorded usage of '{}' in '{}'", pattern_name, project);
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Recorded usage of '{}' in '{}'",
+                    pattern_name, project
+                ))]))
+            }
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to update pattern: {}", e),
+                None,
+            )),
+        }
+    }
+
+    /// Have the client's model describe a pattern, and store the description in its frontmatter
+    #[tool(
+        description = "Write a one-line description of a pattern into its frontmatter, generated by the client's own model through MCP sampling, so the library documents itself without a server-side API key. A pattern that already has a description keeps it unless overwrite is set",
+        annotations(destructive_hint = false, idempotent_hint = false)
+    )]
+    async fn summarize_pattern(
+        &self,
+        Parameters(SummarizePatternRequest {
+            pattern_name,
+            overwrite,
+        }): Parameters<SummarizePatternRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let pattern = library.find_pattern(&pattern_name).ok_or_else(|| {
+            McpError::invalid_params(format!("Pattern '{}' not found.", pattern_name), None)
+        })?;
+        if pattern.read_only {
+            return Err(McpError::invalid_request(
+                format!("Pattern '{}' is in a read-only archive", pattern_name),
+                None,
+            ));
+        }
+        if let (Some(description), false) = (&pattern.metadata.description, overwrite) {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "'{}' already has a description, set overwrite to replace it: {}",
+                pattern.metadata.pattern, description
+            ))]));
+        }
+
+        let body: String = without_usage_entries(&pattern.content)
+            .chars()
+            .take(SUMMARY_INPUT_CHARS)
+            .collect();
+        let request = CreateMessageRequestParam {
+            messages: vec![SamplingMessage {
+                role: Role::User,
+                content: Content::text(format!(
+                    "Pattern '{}' ({}):\n\n{}",
+                    pattern.metadata.pattern, pattern.metadata.category, body
+                )),
+            }],
+            model_preferences: Some(ModelPreferences {
+                hints: None,
+                cost_priority: Some(0.8),
+                speed_priority: Some(0.8),
+                intelligence_priority: Some(0.3),
+            }),
+            system_prompt: Some(
+                "Describe this software development pattern in one sentence of at most 25 words, \
+                 saying what it is for. Reply with the sentence only."
+                    .to_string(),
+            ),
+            include_context: None,
+            temperature: Some(0.2),
+            max_tokens: SUMMARY_MAX_TOKENS,
+            stop_sequences: None,
+            metadata: None,
+        };
+        let file_path = pattern.filepath.clone();
+        let name = pattern.metadata.pattern.clone();
+        drop(library);
+
+        let response = context.peer.create_message(request).await.map_err(|e| {
+            McpError::internal_error(format!("The client's model could not summarize: {}", e), None)
+        })?;
+        let description = response
+            .message
+            .content
+            .as_text()
+            .map(|t| clean_description(&t.text))
+            .filter(|d| !d.is_empty())
+            .ok_or_else(|| {
+                McpError::internal_error("The client's model returned no description", None)
+            })?;
+
+        // Read the file again, as it may have changed while the model was answering
+        let mut pattern = Pattern::load(&file_path).ok_or_else(|| {
+            McpError::invalid_params(format!("Pattern '{}' not found.", name), None)
+        })?;
+        pattern.metadata.description = Some(description.clone());
+        match fs::write(&file_path, rewrite_file(&file_path, &pattern.metadata, &pattern.content)) {
+            Ok(_) => {
+                tracing::info!("Described '{}' with {}: {}", name, response.model, description);
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Described '{}': {}",
+                    name, description
+                ))]))
+            }
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to update pattern: {}", e),
+                None,
+            )),
+        }
+    }
+
+    /// Build up a pattern in session memory, section by section
+    #[tool(
+        description = "Write a pattern over several calls without touching disk: start a draft with its metadata, append sections, revise or remove them, and preview the rendered file. Nothing is written until commit_draft, so an abandoned draft leaves no half-finished file. Drafts live only as long as the session",
+        annotations(destructive_hint = false, idempotent_hint = false)
+    )]
+    fn draft_in_session(
+        &self,
+        Parameters(DraftInSessionRequest {
+            pattern_name,
+            action,
+            heading,
+            content,
+            category,
+            framework,
+            projects,
+            tag,
+        }): Parameters<DraftInSessionRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        validate_pattern_name(&pattern_name).map_err(|e| McpError::invalid_params(e, None))?;
+        let mut drafts = self.drafts.lock().unwrap_or_else(|e| e.into_inner());
+        if action == DraftAction::Start {
+            if drafts.contains_key(&pattern_name) {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "A draft of '{}' is already in progress, revise it or discard it first",
+                        pattern_name
+                    ),
+                    None,
+                ));
+            }
+            let mut draft = SessionDraft {
+                intro: content.unwrap_or_default(),
+                ..Default::default()
+            };
+            draft.set_metadata(category, framework, projects, tag);
+            drafts.insert(pattern_name.clone(), draft);
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "Started a draft of '{}'. Append sections, then commit_draft to write it",
+                pattern_name
+            ))]));
+        }
+
+        let Some(draft) = drafts.get_mut(&pattern_name) else {
+            return Err(McpError::invalid_params(
+                format!("No draft of '{}' in this session, start one first", pattern_name),
+                None,
+            ));
+        };
+        let heading = heading.map(|h| h.trim().trim_start_matches('#').trim().to_string());
+        let message = match (action, heading) {
+            (DraftAction::Start, _) => unreachable!("handled above"),
+            (DraftAction::Append, Some(heading)) if !heading.is_empty() => {
+                if draft.section(&heading).is_some() {
+                    return Err(McpError::invalid_params(
+                        format!("The draft already has a '{}' section, revise it instead", heading),
+                        None,
+                    ));
+                }
+                draft.sections.push((heading.clone(), content.unwrap_or_default()));
+                format!("Appended '{}' to '{}' ({})", heading, pattern_name, draft.outline())
+            }
+            (DraftAction::Append, _) | (DraftAction::Remove, None) => {
+                return Err(McpError::invalid_params("heading is required", None));
+            }
+            (DraftAction::Revise, Some(heading)) => {
+                let index = draft.section(&heading).ok_or_else(|| {
+                    McpError::invalid_params(
+                        format!("No '{}' section, {}", heading, draft.outline()),
+                        None,
+                    )
+                })?;
+                draft.sections[index].1 = content.unwrap_or_default();
+                format!("Revised '{}' in '{}'", draft.sections[index].0, pattern_name)
+            }
+            (DraftAction::Revise, None) => {
+                if let Some(content) = content {
+                    draft.intro = content;
+                }
+                draft.set_metadata(category, framework, projects, tag);
+                format!("Revised the intro and metadata of '{}'", pattern_name)
+            }
+            (DraftAction::Remove, Some(heading)) => {
+                let index = draft.section(&heading).ok_or_else(|| {
+                    McpError::invalid_params(
+                        format!("No '{}' section, {}", heading, draft.outline()),
+                        None,
+                    )
+                })?;
+                let (heading, _) = draft.sections.remove(index);
+                format!("Removed '{}' from '{}' ({})", heading, pattern_name, draft.outline())
+            }
+            (DraftAction::Preview, _) => {
+                let metadata = PatternMetadata {
+                    pattern: pattern_name.clone(),
+                    id: None,
+                    created: None,
+                    updated: None,
+                    version: None,
+                    category: draft.category.clone().unwrap_or_default(),
+                    frameworks: draft.frameworks.clone(),
+                    description: None,
+                    author: None,
+                    maturity: None,
+                    difficulty: None,
+                    projects: draft.projects.clone(),
+                    tags: draft.tags.clone(),
+                    aliases: Vec::new(),
+                    prerequisites: Vec::new(),
+                    see_also: Vec::new(),
+                    extends: None,
+                    priority: 0,
+                    draft: false,
+                    publish: false,
+                    published_url: None,
+                    flashcards: Vec::new(),
+                    arguments: Vec::new(),
+                    extra: BTreeMap::new(),
+                };
+                format!(
+                    "Preview of '{}' (an ID is assigned, defaults filled in and projects listed under 'Used in' on commit):\n\n{}",
+                    pattern_name,
+                    render_pattern(&metadata, &draft.body())
+                )
+            }
+            (DraftAction::Discard, _) => {
+                drafts.remove(&pattern_name);
+                format!("Discarded the draft of '{}'", pattern_name)
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    /// Write a session draft to disk
+    #[tool(
+        description = "Write a pattern drafted with draft_in_session to disk, as create_pattern would, and end the draft. Overwriting an existing pattern needs the user's confirmation, asked by the client or given with confirm: true",
+        annotations(destructive_hint = true, idempotent_hint = false)
+    )]
+    async fn commit_draft(
+        &self,
+        Parameters(CommitDraftRequest {
+            pattern_name,
+            confirm,
+        }): Parameters<CommitDraftRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let draft = self
+            .drafts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&pattern_name)
+            .cloned()
+            .ok_or_else(|| {
+                McpError::invalid_params(
+                    format!("No draft of '{}' in this session", pattern_name),
+                    None,
+                )
+            })?;
+        let request = CreatePatternRequest {
+            pattern_name: pattern_name.clone(),
+            category: draft.category.clone(),
+            framework: draft.frameworks.clone(),
+            projects: Some(draft.projects.clone()),
+            tag: draft.tags.clone(),
+            requires: None,
+            see_also: None,
+            description: None,
+            author: None,
+            content: draft.body(),
+            confirm,
+            bump: VersionBump::default(),
+        };
+        let (result, written) = self.write_pattern(request, &context.peer).await?;
+        if written {
+            self.drafts
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&pattern_name);
+        }
+        Ok(result)
+    }
+
+    /// Append a quick note to a topic's scratch pattern
+    #[tool(
+        description = "Quickly capture an unpolished note or learning mid-session. The note is appended with a timestamp to a per-topic draft scratch pattern (scratch-<topic>), which is created if needed and can later be promoted into a full pattern",
+        annotations(destructive_hint = false, idempotent_hint = false)
+    )]
+    fn capture_note(
+        &self,
+        Parameters(CaptureNoteRequest { topic, note, tag }): Parameters<CaptureNoteRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        if archive::is_archive(&self.patterns_dir) {
+            return Err(McpError::invalid_request(
+                "The pattern library is a read-only archive",
+                None,
+            ));
+        }
+        let topic_slug = links::slugify(&topic);
+        if topic_slug.is_empty() || note.trim().is_empty() {
+            return Err(McpError::invalid_params(
+                "topic and note must not be empty",
+                None,
+            ));
+        }
+        let name = format!("scratch-{}", topic_slug);
+        validate_pattern_name(&name).map_err(|e| McpError::invalid_params(e, None))?;
+
+        let file_path = self.pattern_file(&name);
+        let (mut metadata, body, created) = match Pattern::load(&file_path) {
+            Some(pattern) => (pattern.metadata, pattern.content, false),
+            None => {
+                let category = self
+                    .config()
+                    .defaults
+                    .category
+                    .clone()
+                    .unwrap_or_else(|| SCRATCH_CATEGORY.to_string());
+                let metadata = PatternMetadata {
+                    pattern: name.clone(),
+                    id: Some(new_id()),
+                    created: Some(timestamp_now()),
+                    updated: None,
+                    version: None,
+                    category,
+                    frameworks: Vec::new(),
+                    description: None,
+                    author: self.config().defaults.author.clone(),
+                    maturity: None,
+                    difficulty: None,
+                    projects: Vec::new(),
+                    tags: vec![SCRATCH_CATEGORY.to_string()],
+                    aliases: Vec::new(),
+                    prerequisites: Vec::new(),
+                    see_also: Vec::new(),
+                    extends: None,
+                    priority: 0,
+                    draft: true,
+                    publish: false,
+                    published_url: None,
+                    flashcards: Vec::new(),
+                    arguments: Vec::new(),
+                    extra: BTreeMap::new(),
+                };
+                (metadata, format!("# Notes on {}", topic.trim()), true)
+            }
+        };
+        metadata.tags.extend(tag);
+        metadata.normalize();
+
+        let body = format!(
+            "{}\n\n## {}\n\n{}",
+            body.trim_end(),
+            Local::now().format("%Y-%m-%d %H:%M"),
+            note.trim()
+        );
+        if body.len() > self.config().limits.max_content_bytes {
+            return Err(McpError::invalid_params(
+                format!(
+                    "Scratch pattern '{}' would exceed {} bytes, promote or trim it first",
+                    name, self.config().limits.max_content_bytes
+                ),
+                None,
+            ));
+        }
+
+        match fs::write(&file_path, rewrite_file(&file_path, &metadata, &body)) {
+            Ok(_) => {
+                tracing::info!("Captured a note in '{}'", name);
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "{} note to draft pattern '{}' at {:?}",
+                    if created { "Created and added" } else { "Added" },
+                    name,
+                    file_path
+                ))]))
+            }
+            Err(e) => Err(McpError::internal_error(
+                format!("Failed to capture note: {}", e),
+                None,
+            )),
+        }
+    }
+
+    /// Reload the library from disk without interrupting searches
+    #[tool(
+        description = "Reload every pattern from disk and rebuild the search indexes, e.g. after editing pattern files by hand. The current library keeps serving requests until the new one is fully built and validated. Reports how long the reload took and which patterns were added, removed or changed",
+        annotations(destructive_hint = false, idempotent_hint = true)
+    )]
+    fn reload_patterns(&self) -> Result<CallToolResult, McpError> {
+        let Reload {
+            generation,
+            count,
+            elapsed,
+            added,
+            removed,
+            changed,
+            ..
+        } = self.reload()?;
+
+        let mut message = format!(
+            "Reloaded {} patterns in {} ms (generation {}): {} added, {} removed, {} changed",
+            count,
+            elapsed.as_millis(),
+            generation,
+            added.len(),
+            removed.len(),
+            changed.len()
+        );
+        for (sign, names) in [("+", &added), ("-", &removed), ("~", &changed)] {
+            for name in names {
+                message.push_str(&format!("\n{} {}", sign, name));
+            }
+        }
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    /// Rename or remove patterns that clash by name or ID
+    #[tool(
+        description = "Find patterns sharing a name or ID, e.g. after syncing patterns created on different machines, and plan how to resolve each clash: exact duplicate copies are removed, other clashing patterns get a new ID or a unique name. The oldest pattern in each clash is kept as is. Shows the plan until called again with confirm: true",
+        annotations(destructive_hint = true, idempotent_hint = true)
+    )]
+    fn resolve_id_conflicts(
+        &self,
+        Parameters(ResolveIdConflictsRequest { confirm }): Parameters<ResolveIdConflictsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let conflicts = library.conflicts();
+        if conflicts.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "No patterns share a name or ID.",
+            )]));
+        }
+
+        // What to do with each clashing file: None to remove it, or its new metadata
+        let mut plan: BTreeMap<&Path, Option<PatternMetadata>> = BTreeMap::new();
+        let mut report = Vec::new();
+        let mut taken: BTreeSet<String> = library
+            .patterns
+            .iter()
+            .map(|p| p.metadata.pattern.clone())
+            .collect();
+        for conflict in &conflicts {
+            let keep = conflict.patterns[0];
+            report.push(format!(
+                "{} patterns share the {} '{}', keeping {:?}",
+                conflict.patterns.len(),
+                conflict.kind,
+                conflict.key,
+                keep.filepath
+            ));
+            for pattern in &conflict.patterns[1..] {
+                if pattern.read_only {
+                    report.push(format!("- {:?} is read-only, left as is", pattern.filepath));
+                    continue;
+                }
+                let entry = plan
+                    .entry(&pattern.filepath)
+                    .or_insert_with(|| Some(pattern.metadata.clone()));
+                let Some(metadata) = entry else { continue };
+                if render_pattern(&pattern.metadata, &pattern.content)
+                    == render_pattern(&keep.metadata, &keep.content)
+                {
+                    *entry = None;
+                    report.push(format!("- remove duplicate copy {:?}", pattern.filepath));
+                    continue;
+                }
+                match conflict.kind {
+                    ConflictKind::Id => {
+                        metadata.id = Some(derived_id(pattern));
+                        report.push(format!(
+                            "- give {:?} the new id {}",
+                            pattern.filepath,
+                            metadata.id.as_deref().unwrap_or_default()
+                        ));
+                    }
+                    ConflictKind::Name => {
+                        // The end of a ULID is random, so a short piece of it makes a unique suffix.
+                        // It comes from the derived ID, as a hand-written one may be short or
+                        // hold characters names can't
+                        metadata.id.get_or_insert_with(|| derived_id(pattern));
+                        let id = derived_id(pattern);
+                        let suffix = id[id.len() - 6..].to_lowercase();
+                        let base = format!("{}-{}", pattern.metadata.pattern, suffix);
+                        let mut name = base.clone();
+                        for n in 2.. {
+                            if !taken.contains(&name)
+                                && !renamed_path(&pattern.filepath, &name).exists()
+                            {
+                                break;
+                            }
+                            name = format!("{}-{}", base, n);
+                        }
+                        taken.insert(name.clone());
+                        report.push(format!(
+                            "- rename {:?} to '{}' (links to '{}' keep pointing at {:?})",
+                            pattern.filepath, name, conflict.key, keep.filepath
+                        ));
+                        metadata.pattern = name;
+                    }
+                }
+            }
+        }
 
-    /// Load patterns from the provided directory
-    fn load_all_patterns() -> Vec<Pattern> {
-        let patterns_dir =
-            std::env::var(ENV_PATTERNS_DIR).expect("PATTERNS_DIR environment variable MUST be set");
-        let patterns_dir = PathBuf::from(patterns_dir);
+        if !confirm {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "{}\n\nCall resolve_id_conflicts again with confirm: true to apply it.",
+                report.join("\n")
+            ))]));
+        }
 
-        fs::read_dir(&patterns_dir)
-            .ok()
-            .into_iter()
-            .flatten()              // Extract good ReadDir
-            .flat_map(|e| e.ok())   // Convet Result<DireEntry, Err> to DirEntry
-            .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
-            .filter_map(|e| Self::load_patterns(&e.path()))
-            .collect()
-    }
-    /// Validate the pattern name during creation
-    fn validate_pattern_name(name: &str) -> Result<(), McpError> {
-        if name.is_empty() || name.len() > 100 {
-            return Err(McpError::invalid_params(
-                "Pattern must be 1-100 characters",
-                None,
-            ));
+        let mut failed = Vec::new();
+        for (path, metadata) in plan {
+            let result = match metadata {
+                None => fs::remove_file(path),
+                Some(metadata) => {
+                    let Some(pattern) = library.patterns.iter().find(|p| p.filepath == path) else {
+                        continue;
+                    };
+                    let target = if metadata.pattern == pattern.metadata.pattern {
+                        path.to_path_buf()
+                    } else {
+                        renamed_path(path, &metadata.pattern)
+                    };
+                    if target != path && target.exists() {
+                        failed.push(format!("- {:?}: {:?} already exists", path, target));
+                        continue;
+                    }
+                    let content = rewrite_file(path, &metadata, &pattern.content);
+                    fs::write(&target, content).and_then(|_| {
+                        if target != path {
+                            fs::remove_file(path)
+                        } else {
+                            Ok(())
+                        }
+                    })
+                }
+            };
+            if let Err(e) = result {
+                failed.push(format!("- {:?}: {}", path, e));
+            }
         }
-        if name
-            .chars()
-            .any(|c| !c.is_alphanumeric() && c != '-' && c != '_')
-        {
-            return Err(McpError::invalid_params(
-                "Pattern name can only contain alphanumeric, dash and underscore characters",
+        if !failed.is_empty() {
+            return Err(McpError::internal_error(
+                format!("Failed to resolve some conflicts:\n{}", failed.join("\n")),
                 None,
             ));
         }
-
-        Ok(())
+        tracing::info!("Resolved {} name and ID conflicts", conflicts.len());
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Resolved {} conflicts:\n{}",
+            conflicts.len(),
+            report.join("\n")
+        ))]))
     }
 
-}
+    /// Move every pattern in a category to another one
+    #[tool(
+        description = "Rename a category across every pattern in it. Changes affecting many patterns are only previewed until called again with confirm: true and the affected_count from the preview",
+        annotations(destructive_hint = true, idempotent_hint = true)
+    )]
+    fn rename_category(
+        &self,
+        Parameters(RenameCategoryRequest {
+            from,
+            to,
+            confirm,
+            affected_count,
+        }): Parameters<RenameCategoryRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let (from, to) = (normalize(&from), normalize(&to));
+        if to.is_empty() {
+            return Err(McpError::invalid_params("to must not be empty", None));
+        }
+        let affected: Vec<&Pattern> = library
+            .patterns
+            .iter()
+            .filter(|p| p.metadata.category == from && !p.read_only)
+            .collect();
+        if affected.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "No writable patterns in category '{}'.",
+                from
+            ))]));
+        }
+        let operation = format!("Renaming category '{}' to '{}'", from, to);
+        if let Some(preview) = self.confirm_bulk(&operation, &affected, confirm, affected_count)? {
+            return Ok(preview);
+        }
 
-#[tool_router]
-impl Patterns {
-    pub fn new() -> Self {
-        Self {
-            patterns: Arc::new(Self::load_all_patterns()),
-            tool_router: Self::tool_router(),
+        let mut failed: Vec<String> = Vec::new();
+        for pattern in &affected {
+            let written = Pattern::load(&pattern.filepath)
+                .ok_or_else(|| "no longer a valid pattern".to_string())
+                .and_then(|mut current| {
+                    current.metadata.category = to.clone();
+                    let content = rewrite_file(&pattern.filepath, &current.metadata, &current.content);
+                    fs::write(&pattern.filepath, content).map_err(|e| e.to_string())
+                });
+            if let Err(e) = written {
+                failed.push(format!("- {}: {}", pattern.metadata.pattern, e));
+            }
+        }
+
+        let mut message = format!(
+            "Moved {} of {} patterns from '{}' to '{}'",
+            affected.len() - failed.len(),
+            affected.len(),
+            from,
+            to
+        );
+        tracing::info!("{}", message);
+        if !failed.is_empty() {
+            tracing::warn!("Failed to move {} patterns to '{}'", failed.len(), to);
+            message.push_str(&format!("\nFailed:\n{}", failed.join("\n")));
         }
+        Ok(CallToolResult::success(vec![Content::text(message)]))
     }
 
-    /// Get all available patterns
-    #[tool(description = "List all available patterns")]
-    fn list_patterns(&self) -> Result<CallToolResult, McpError> {
-        let summary: Vec<String> = self
+    /// Find patterns to read alongside a given one
+    #[tool(
+        description = "List the patterns most related to a given pattern, based on shared tags, the same framework, overlapping projects and, when embeddings are configured, semantic similarity. Useful for finding what else to read alongside a pattern",
+        annotations(read_only_hint = true)
+    )]
+    fn related_patterns(
+        &self,
+        Parameters(RelatedPatternsRequest {
+            pattern_name,
+            limit,
+        }): Parameters<RelatedPatternsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let (target_index, target) = library
+            .patterns
+            .iter()
+            .enumerate()
+            .find(|(_, p)| p.answers_to(&pattern_name))
+            .ok_or_else(|| {
+                McpError::invalid_params(format!("Pattern '{}' not found.", pattern_name), None)
+            })?;
+        let target_vector = library.embeddings.get(target_index);
+
+        let mut related: Vec<(&Pattern, f64, Vec<String>)> = library
             .patterns
             .iter()
-            .map(|p| format!("- {} ({})", p.metadata.pattern, p.metadata.category))
+            .enumerate()
+            .filter(|(_, p)| !p.is_same_as(target))
+            .filter_map(|(i, p)| {
+                let mut score = 0.0;
+                let mut reasons = Vec::new();
+
+                let shared_tags: Vec<&str> = p
+                    .metadata
+                    .tags
+                    .iter()
+                    .filter(|t| target.metadata.tags.contains(t))
+                    .map(String::as_str)
+                    .collect();
+                if !shared_tags.is_empty() {
+                    score += RELATED_TAG_WEIGHT * overlap(&p.metadata.tags, &target.metadata.tags);
+                    reasons.push(format!("shared tags: {}", shared_tags.join(", ")));
+                }
+                let shared_frameworks: Vec<&str> = p
+                    .metadata
+                    .frameworks
+                    .iter()
+                    .filter(|f| target.metadata.frameworks.contains(f))
+                    .map(String::as_str)
+                    .collect();
+                if !shared_frameworks.is_empty() {
+                    score += RELATED_FRAMEWORK_WEIGHT;
+                    reasons.push(format!("same framework: {}", shared_frameworks.join(", ")));
+                }
+                let shared_projects: Vec<&str> = p
+                    .metadata
+                    .projects
+                    .iter()
+                    .filter(|pr| target.used_in(pr))
+                    .map(String::as_str)
+                    .collect();
+                if !shared_projects.is_empty() {
+                    score += RELATED_PROJECT_WEIGHT
+                        * overlap(&p.metadata.projects, &target.metadata.projects);
+                    reasons.push(format!("also used in: {}", shared_projects.join(", ")));
+                }
+                if let (Some(a), Some(b)) = (target_vector, library.embeddings.get(i)) {
+                    let similarity = embeddings::cosine_similarity(a, b);
+                    score += RELATED_EMBEDDING_WEIGHT * similarity.max(0.0);
+                    reasons.push(format!("similarity {:.2}", similarity));
+                }
+
+                (score > 0.0).then_some((p, score, reasons))
+            })
             .collect();
+        if related.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "No patterns related to '{}' found.",
+                target.metadata.pattern
+            ))]));
+        }
+        related.sort_by(|(_, a, _), (_, b, _)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
 
+        let page = Page::new(&self.config().limits, limit, None);
+        let lines: Vec<String> = related
+            .iter()
+            .take(page.limit)
+            .map(|(p, score, reasons)| {
+                format!(
+                    "- **{}** ({}, score: {:.2}): {}",
+                    p.qualified_name(),
+                    p.metadata.category,
+                    score,
+                    reasons.join("; ")
+                )
+            })
+            .collect();
         Ok(CallToolResult::success(vec![Content::text(format!(
-            "Available patterns:\n{}",
-            summary.join("\n")
+            "Patterns related to '{}':\n{}",
+            target.metadata.pattern,
+            lines.join("\n")
         ))]))
     }
 
-    /// Search patterns based on input
-    #[tool(description = "Search patterns by query, category, framework or tag")]
-    fn search_patterns(
+    /// Get the reading order of a pattern's prerequisites
+    #[tool(
+        description = "Given a goal pattern, return the ordered chain of prerequisite patterns to read first, following each pattern's 'prerequisites' metadata. Every pattern comes after its own prerequisites and cycles are reported",
+        annotations(read_only_hint = true)
+    )]
+    fn pattern_path(
         &self,
-        Parameters(PatternSearchRequest {
-            query,
-            category,
-            framework,
-            tag,
-        }): Parameters<PatternSearchRequest>,
+        Parameters(PatternPathRequest { pattern_name }): Parameters<PatternPathRequest>,
     ) -> Result<CallToolResult, McpError> {
-        let results: Vec<&Pattern> = self
-            .patterns
+        let library = self.library();
+        let goal = library.find_pattern(&pattern_name).ok_or_else(|| {
+            McpError::invalid_params(format!("Pattern '{}' not found.", pattern_name), None)
+        })?;
+        let (chain, missing) = library.prerequisite_chain(goal).map_err(|cycle| {
+            McpError::invalid_params(
+                format!("Prerequisites form a cycle: {}", cycle.join(" -> ")),
+                None,
+            )
+        })?;
+
+        let steps: Vec<String> = chain
             .iter()
-            .filter(|p| { // Search through the fields
-                category.as_ref().is_none_or(|c| &p.metadata.category == c)
-                    && framework
-                        .as_ref()
-                        .is_none_or(|f| p.metadata.framework.as_ref() == Some(f))
-                    && tag.as_ref().is_none_or(|t| p.metadata.tags.contains(t))
-                    && query.as_ref().is_none_or(|q| { 
-                    // Match query to the pattern name and content
-                        let searchable =
-                            format!("{} {}", p.metadata.pattern, p.content).to_lowercase();
-                        searchable.contains(&q.to_lowercase())
-                    })
-            })
+            .enumerate()
+            .map(|(i, p)| format!("{}. {} ({})", i + 1, p.metadata.pattern, p.metadata.category))
             .collect();
+        let missing = if missing.is_empty() {
+            String::new()
+        } else {
+            format!("\n\nMissing prerequisites: {}", missing.join(", "))
+        };
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Reading order for '{}':\n{}{}",
+            goal.metadata.pattern,
+            steps.join("\n"),
+            missing
+        ))]))
+    }
 
-        if results.is_empty() {
-            return Ok(CallToolResult::success(vec![Content::text(
-                "No patterns found.",
-            )]));
-        }
+    /// Get a pattern and everything it requires in one response
+    #[tool(
+        description = "Get a pattern together with every pattern it requires, following 'requires' (or 'prerequisites') metadata transitively. Required patterns come first, each after its own requirements, and the requested pattern last. Embeds and placeholders are expanded as in get_pattern, and cycles and missing patterns are reported",
+        annotations(read_only_hint = true)
+    )]
+    fn get_pattern_with_dependencies(
+        &self,
+        Parameters(PatternDependenciesRequest { pattern_name }): Parameters<
+            PatternDependenciesRequest,
+        >,
+    ) -> Result<CallToolResult, McpError> {
+        let library = self.library();
+        let pattern = library.find_pattern(&pattern_name).ok_or_else(|| {
+            McpError::invalid_params(format!("Pattern '{}' not found.", pattern_name), None)
+        })?;
+        let (chain, missing) = library.prerequisite_chain(pattern).map_err(|cycle| {
+            McpError::invalid_params(
+                format!("Required patterns form a cycle: {}", cycle.join(" -> ")),
+                None,
+            )
+        })?;
 
-        let summary: Vec<String> = results
+        let config = self.config();
+        let sections: Vec<String> = chain
             .iter()
             .map(|p| {
-                format!(
-                    "**{}**\n{}",
-                    p.metadata.pattern,
-                    &p.content[..200.min(p.content.len())]
-                )
+                let mut visited = vec![p.metadata.pattern.clone()];
+                let body = library.expand_embeds(&library.composed_body(p), &mut visited);
+                let body = interpolate::expand(&body, &config.allowed_env);
+                format!("# {}\n\n{}", p.qualified_name(), body.trim_end())
             })
             .collect();
+        let header = match chain.len() - 1 {
+            0 => format!("'{}' requires no other patterns", pattern.metadata.pattern),
+            n => format!(
+                "'{}' with the {} patterns it requires, in reading order",
+                pattern.metadata.pattern, n
+            ),
+        };
+        let missing = if missing.is_empty() {
+            String::new()
+        } else {
+            format!("\n\nMissing required patterns: {}", missing.join(", "))
+        };
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{}\n\n{}{}",
+            header,
+            sections.join("\n\n---\n\n"),
+            missing
+        ))]))
+    }
 
-        Ok(CallToolResult::success(vec![Content::text(
-            summary.join("\n\n"),
-        )]))
+    /// Where a tool may write the export file named `file`: in the configured export
+    /// directory, and only by plain file name, so clients can't write anywhere else
+    fn export_path(&self, file: &str) -> Result<PathBuf, McpError> {
+        let Some(dir) = self.config().export_dir.clone() else {
+            return Err(McpError::invalid_request(
+                "Writing exports is disabled, set export_dir in grimoire.toml to allow it, or \
+                 leave out output to get the content back",
+                None,
+            ));
+        };
+        let file_name = Path::new(file)
+            .file_name()
+            .filter(|n| n.to_string_lossy() == file)
+            .ok_or_else(|| {
+                McpError::invalid_params("output must be a plain file name, without a path", None)
+            })?;
+        Ok(dir.join(file_name))
     }
 
-    /// Get the pattern based on the name
-    #[tool(description = "Get the pattern based on the pattern name")]
-    fn get_pattern(
+    /// Turn patterns into Anki flashcards
+    #[tool(
+        description = "Export patterns as Anki-importable flashcards (TSV). Each pattern becomes a name/summary card and a summary/key code block card, or its own 'flashcards' frontmatter Q/A pairs. Filter by categories or tags, and optionally write to a file in the configured export directory",
+        annotations(destructive_hint = false, idempotent_hint = true)
+    )]
+    fn export_flashcards(
         &self,
-        Parameters(GetPatternRequest { pattern_name }): Parameters<GetPatternRequest>,
+        Parameters(ExportFlashcardsRequest {
+            category,
+            tag,
+            output,
+        }): Parameters<ExportFlashcardsRequest>,
+        deadline: Deadline,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
-        let pattern = self
+        if output.is_some() {
+            self.check_writable(&context)?;
+        }
+        let library = self.library();
+        let category = normalize_all(&category);
+        let tag = normalize_all(&tag);
+        let patterns: Vec<&Pattern> = library
             .patterns
             .iter()
-            .find(|p| p.metadata.pattern == pattern_name);
+            .filter(|p| category.is_empty() || category.contains(&p.metadata.category))
+            .filter(|p| tag.is_empty() || tag.iter().any(|t| p.metadata.tags.contains(t)))
+            .collect();
+        let mut cards: Vec<flashcards::Card> = Vec::new();
+        for (n, p) in patterns.iter().enumerate() {
+            if n.is_multiple_of(cancel::CHECK_INTERVAL) {
+                deadline.check(n, patterns.len(), "patterns exported")?;
+            }
+            cards.extend(flashcards::cards_for(
+                &p.metadata.pattern,
+                &p.content,
+                &p.metadata.flashcards,
+                &p.metadata.tags,
+            ));
+        }
+        if cards.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "No flashcards to export.",
+            )]));
+        }
 
-        match pattern {
-            Some(p) => Ok(CallToolResult::success(vec![Content::text(&p.content)])),
-            None => Ok(CallToolResult::success(vec![Content::text(format!(
-                "Pattern '{}' not found.",
-                pattern_name
-            ))])),
+        let tsv = flashcards::to_tsv(&cards);
+        let path = output.map(|file| self.export_path(&file)).transpose()?;
+        match path {
+            Some(path) => match fs::write(&path, tsv) {
+                Ok(_) => {
+                    tracing::info!("Exported {} flashcards to {:?}", cards.len(), path);
+                    Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Exported {} flashcards from {} patterns to {:?}. Import the file in Anki with File > Import",
+                        cards.len(),
+                        patterns.len(),
+                        path
+                    ))]))
+                }
+                Err(e) => Err(McpError::internal_error(
+                    format!("Failed to write flashcards: {}", e),
+                    None,
+                )),
+            },
+            None => Ok(CallToolResult::success(vec![Content::text(tsv)])),
         }
     }
 
-    /// Create patterns by providing information
+    /// Rebuild the frontmatter of a pattern file the loader could not parse
     #[tool(
-        description = "Create patterns by providing, category, framework, projects this pattern was used in, tags, and the content. Look to existing patterns for examples on how this should look"
+        description = "Repair a pattern file with missing or invalid frontmatter. Salvages valid frontmatter entries, infers the name from the first heading and guesses tags, then shows the repaired document. Call again with confirm set to write it",
+        annotations(destructive_hint = true, idempotent_hint = true)
     )]
-    fn create_pattern(
+    fn repair_pattern(
         &self,
-        Parameters(CreatePatternRequest {
-            pattern_name,
-            category,
-            framework,
-            projects,
-            tag,
-            content,
-        }): Parameters<CreatePatternRequest>,
+        Parameters(RepairPatternRequest { file, confirm }): Parameters<RepairPatternRequest>,
     ) -> Result<CallToolResult, McpError> {
-        let projects_str = projects
-            .map(|p| format!("projects: [{}]\n", p.join(", ")))
+        let library = self.library();
+        if archive::is_archive(&self.patterns_dir) {
+            return Err(McpError::invalid_request(
+                "The pattern library is a read-only archive",
+                None,
+            ));
+        }
+        // Only plain file names, so the tool can't reach outside the patterns directory
+        let file_name = Path::new(&file)
+            .file_name()
+            .filter(|n| n.to_string_lossy() == file)
+            .ok_or_else(|| {
+                McpError::invalid_params("file must be a file name in the patterns directory", None)
+            })?;
+        // A name given without an accepted extension is looked up with each of them in turn
+        let mut file_path = self.patterns_dir.join(file_name);
+        if self.config().pattern_format(&file_path).is_none() {
+            let candidates: Vec<PathBuf> = self
+                .config()
+                .pattern_extensions()
+                .iter()
+                .map(|ext| self.patterns_dir.join(format!("{}.{}", file, ext)))
+                .collect();
+            if let Some(found) = candidates.into_iter().find(|path| path.exists()) {
+                file_path = found;
+            } else if file_path.extension().is_none() {
+                file_path = self.new_pattern_file(&file);
+            }
+        }
+
+        let content = fs::read_to_string(&file_path).map_err(|e| {
+            McpError::invalid_params(format!("Cannot read {:?}: {}", file_path, e), None)
+        })?;
+        if Pattern::parse(&content, &file_path).is_some() {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "{:?} is already a valid pattern, nothing to repair.",
+                file_path
+            ))]));
+        }
+
+        let mut known_tags: Vec<String> = library
+            .patterns
+            .iter()
+            .flat_map(|p| p.metadata.tags.iter().cloned())
+            .collect();
+        known_tags.sort();
+        known_tags.dedup();
+        let stem = file_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_default();
+        let mut recovered = repair::recover(&content, &stem, &known_tags);
 
-        let tags_str = if tag.is_empty() {
-            String::new()
-        } else {
-            format!("tags: [{}]\n", tag.join(", "))
+        let category = recovered.category.take().unwrap_or_else(|| {
+            let category = self
+                .config()
+                .defaults
+                .category
+                .clone()
+                .unwrap_or_else(|| FALLBACK_CATEGORY.to_string());
+            recovered
+                .notes
+                .push(format!("no category found, using '{}'", category));
+            category
+        });
+        let id = recovered.id.take().unwrap_or_else(|| {
+            recovered.notes.push("no id found, assigned a new one".to_string());
+            new_id()
+        });
+        let mut metadata = PatternMetadata {
+            pattern: recovered.pattern.unwrap_or(stem),
+            id: Some(id),
+            created: recovered.created,
+            updated: Some(timestamp_now()),
+            version: recovered.version,
+            category,
+            frameworks: recovered.frameworks,
+            description: recovered.description,
+            author: recovered.author,
+            maturity: recovered.maturity,
+            difficulty: recovered.difficulty,
+            projects: recovered.projects,
+            tags: recovered.tags,
+            aliases: recovered.aliases,
+            prerequisites: recovered.prerequisites,
+            see_also: recovered.see_also,
+            extends: None,
+            priority: recovered.priority,
+            draft: recovered.draft,
+            publish: recovered.publish,
+            published_url: recovered.published_url,
+            flashcards: Vec::new(),
+            arguments: Vec::new(),
+            extra: BTreeMap::new(),
         };
+        metadata.normalize();
+        let repaired = render_pattern(&metadata, &recovered.body);
+        if Pattern::parse(&repaired, &file_path).is_none() {
+            return Err(McpError::internal_error(
+                format!("Could not produce a valid pattern from {:?}", file_path),
+                None,
+            ));
+        }
 
-        // Validate Name
-        Self::validate_pattern_name(&pattern_name)?;
-
-        let pattern_content = format!(
-            r#"---
-pattern: {}
-category: {}
-framework: {}
-{}{}---
-
-{}
-"#,
-            pattern_name, category, framework, projects_str, tags_str, content
-        );
-
-        let patterns_dir =
-            std::env::var(ENV_PATTERNS_DIR).expect("PATTERNS_DIR environment variable MUST be set");
-        let file_path = PathBuf::from(patterns_dir).join(format!("{}.md", pattern_name));
+        let notes: Vec<String> = recovered.notes.iter().map(|n| format!("- {}", n)).collect();
+        if !confirm {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "Proposed repair of {:?}:\n{}\n\n{}\nCall repair_pattern again with confirm: true to write it.",
+                file_path,
+                notes.join("\n"),
+                repaired
+            ))]));
+        }
 
-        match fs::write(&file_path, pattern_content) {
-            Ok(_) => Ok(CallToolResult::success(vec![Content::text(format!(
-                "Pattern '{}' created at {:?}",
-                pattern_name, file_path
-            ))])),
+        match fs::write(&file_path, repaired) {
+            Ok(_) => {
+                tracing::info!("Repaired pattern '{}' at {:?}", metadata.pattern, file_path);
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Repaired pattern '{}' at {:?}\n{}",
+                    metadata.pattern,
+                    file_path,
+                    notes.join("\n")
+                ))]))
+            }
             Err(e) => Err(McpError::internal_error(
-                format!("Failed to create pattern: {}", e),
+                format!("Failed to write repaired pattern: {}", e),
                 None,
             )),
         }
     }
 }
 
-#[tool_handler]
+/// Each tool call gets a deadline from the client's cancellation token and the configured timeout
+impl FromContextPart<ToolCallContext<'_, Patterns>> for Deadline {
+    fn from_context_part(context: &mut ToolCallContext<'_, Patterns>) -> Result<Self, McpError> {
+        Ok(Deadline::new(
+            context.request_context.ct.clone(),
+            context.service.config().limits.request_timeout(),
+        ))
+    }
+}
+
 impl ServerHandler for Patterns {
     /// Provide server information and capabilities
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            protocol_version: PROTOCOL_VERSIONS[0].clone(),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_tool_list_changed()
+                .enable_prompts()
+                .enable_resources()
+                .enable_resources_list_changed()
+                .enable_resources_subscribe()
+                .enable_logging()
+                .enable_completions()
+                .build(),
             server_info: Implementation::from_build_env(),
             instructions: Some(
     "I manage a library of software development patterns stored as markdown files with YAML frontmatter.
@@ -306,9 +4346,28 @@ impl ServerHandler for Patterns {
 
     Available operations:
     - list_patterns: Get overview of all available patterns
-    - search_patterns: Find patterns by text, category, framework, or tags
+    - search_patterns: Find patterns by text, categories, framework, tags, or project
+    - semantic_search_patterns: Find conceptually related patterns when keywords don't match
+    - list_patterns_by_project: Get every pattern used in a given codebase
+    - patterns_using_dependency: Find the patterns whose code imports a crate or package
+    - library_stats: Count patterns by category and framework, and the dependencies they import
+    - server_status: Check the server's health, with steps to fix any problem
     - get_pattern: Retrieve full content of a specific pattern
     - create_pattern: Add new patterns with proper metadata
+    - record_pattern_usage: Record that a pattern was used in a project
+    - summarize_pattern: Have your model write a one-line description into a pattern's frontmatter
+    - reload_patterns: Pick up pattern files edited on disk without restarting
+    - rename_category: Move every pattern in a category to a new category name
+    - capture_note: Jot down a quick note in a per-topic draft scratch pattern
+    - draft_in_session: Write a pattern section by section in memory, previewing it as you go
+    - commit_draft: Write a pattern drafted with draft_in_session to disk
+    - report_result_feedback: Report which search results were useful, improving future ranking
+    - related_patterns: Find patterns worth reading alongside a given one
+    - pattern_path: Get the ordered chain of prerequisite patterns to read before a goal pattern
+    - get_pattern_with_dependencies: Get a pattern and every pattern it requires in one response
+    - export_flashcards: Export patterns as Anki flashcards for study
+    - repair_pattern: Recover a pattern file whose frontmatter is missing or invalid
+    - resolve_id_conflicts: Rename or remove patterns sharing a name or ID after syncing machines
 
     Patterns include categories like 'rust', 'aws', 'web' and frameworks like 'axum', 'lambda'.
     Each pattern contains implementation details, best practices, and usage examples.
@@ -318,11 +4377,414 @@ impl ServerHandler for Patterns {
         }
     }
 
-    async fn initialize(
+    /// Dispatch tool calls to the router, recording them when enabled
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Some(reason) = self
+            .disabled_tools
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(request.name.as_ref())
+        {
+            return Err(McpError::invalid_request(
+                format!("Tool '{}' is not available: {}", request.name, reason),
+                None,
+            ));
+        }
+        // HTTP clients send their token with every request, which may not be the one
+        // they initialized with
+        let writes = self.mutates(&request.name);
+        if writes
+            && context.extensions.get::<Parts>().is_some()
+            && self.granted_access(&context)? == Access::Read
+        {
+            return Err(McpError::invalid_request(
+                format!("Tool '{}' is not available: {}", request.name, READ_ONLY_ACCESS),
+                None,
+            ));
+        }
+        if self.calls.is_closed() {
+            return Err(McpError::invalid_request("The server is shutting down", None));
+        }
+        // HTTP clients may come from a different address with every request
+        let client = context
+            .extensions
+            .get::<Parts>()
+            .and_then(|parts| parts.extensions.get::<ConnectInfo<ClientAddress>>())
+            .map(|ConnectInfo(ClientAddress(address))| address.ip())
+            .or(self.client);
+        if let Some(client) = client
+            && let Err(wait) = self.rate_limiter.check(client, writes)
+        {
+            return Err(McpError::invalid_request(
+                format!(
+                    "Rate limit exceeded for {}, try again in {} seconds",
+                    client,
+                    wait.as_secs() + 1
+                ),
+                None,
+            ));
+        }
+        // Counted until the call returns, so shutdown waits for it
+        let _call = self.calls.token();
+        let recorded_request = self.recorder.as_ref().map(|_| request.clone());
+        // Structured content came with 2025-06-18, older clients read the text content
+        let structured_output = speaks(&context.peer, &ProtocolVersion::V_2025_06_18);
+        let tcc = ToolCallContext::new(self, request, context);
+        let mut result = self.tool_router.call(tcc).await;
+
+        if let (Some(recorder), Some(request)) = (&self.recorder, recorded_request) {
+            recorder.record(&request, &result);
+        }
+        // Which library generation answered, so clients can tell when results may be stale
+        if let Ok(result) = &mut result {
+            if !structured_output {
+                result.structured_content = None;
+            }
+            result
+                .meta
+                .get_or_insert_default()
+                .0
+                .insert(GENERATION_META.to_string(), self.generation().into());
+        }
+        result
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let disabled = self.disabled_tools.read().unwrap_or_else(|e| e.into_inner());
+        let annotations = speaks(&context.peer, &ProtocolVersion::V_2025_03_26);
+        let output_schemas = speaks(&context.peer, &ProtocolVersion::V_2025_06_18);
+        Ok(ListToolsResult::with_all_items(
+            self.tool_router
+                .list_all()
+                .into_iter()
+                .filter(|tool| !disabled.contains_key(tool.name.as_ref()))
+                .map(|mut tool| {
+                    // Left out for clients on revisions that predate them
+                    if !annotations {
+                        tool.annotations = None;
+                    }
+                    if !output_schemas {
+                        tool.output_schema = None;
+                    }
+                    tool
+                })
+                .collect(),
+        ))
+    }
+
+    /// Every pattern as a `grimoire://pattern/<name>` resource
+    async fn list_resources(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let library = self.library();
+        let (patterns, next_cursor) = cursor_page(
+            library.patterns.iter().collect(),
+            |p| p.qualified_name(),
+            request,
+            self.config().limits.list_page_size,
+        )?;
+        Ok(ListResourcesResult {
+            next_cursor,
+            resources: patterns.into_iter().map(pattern_resource).collect(),
+        })
+    }
+
+    /// Every pattern in the prompt category as an MCP prompt
+    async fn list_prompts(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        let library = self.library();
+        let category = normalize(self.config().prompt_category());
+        let (patterns, next_cursor) = cursor_page(
+            library
+                .patterns
+                .iter()
+                .filter(|p| p.metadata.category == category)
+                .collect(),
+            |p| p.qualified_name(),
+            request,
+            self.config().limits.list_page_size,
+        )?;
+        Ok(ListPromptsResult {
+            next_cursor,
+            prompts: patterns.into_iter().map(pattern_prompt).collect(),
+        })
+    }
+
+    /// The prompt pattern's body with its arguments filled in, as a user message
+    async fn get_prompt(
+        &self,
+        GetPromptRequestParam { name, arguments }: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        let library = self.library();
+        let category = normalize(self.config().prompt_category());
+        let pattern = library
+            .find_pattern(&name)
+            .filter(|p| p.metadata.category == category)
+            .ok_or_else(|| McpError::invalid_params(format!("No prompt named '{}'", name), None))?;
+
+        let values: BTreeMap<String, String> = arguments
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(value) => (key, value),
+                value => (key, value.to_string()),
+            })
+            .collect();
+        let mut visited = vec![pattern.metadata.pattern.clone()];
+        let body = library.expand_embeds(&library.composed_body(pattern), &mut visited);
+        let body = interpolate::expand(&body, &self.config().allowed_env);
+        let text = pattern
+            .fill_arguments(&body, &values)
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(GetPromptResult {
+            description: Some(pattern_description(pattern)),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+        })
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult::with_all_items(vec![
+            resource_template(
+                format!("{}{{name}}", PATTERN_URI_PREFIX),
+                "pattern",
+                "A pattern by name or alias, as markdown with its frontmatter in _meta",
+            ),
+            resource_template(
+                format!("{}{{category}}", CATEGORY_URI_PREFIX),
+                "category",
+                "Every pattern in a category, one line each with its resource URI",
+            ),
+            resource_template(
+                format!("{}{{tag}}", TAG_URI_PREFIX),
+                "tag",
+                "Every pattern with a tag, one line each with its resource URI",
+            ),
+        ]))
+    }
+
+    /// The pattern body as markdown, with its frontmatter in the contents' `_meta`,
+    /// or the list of patterns in a category or with a tag
+    async fn read_resource(
+        &self,
+        ReadResourceRequestParam { uri }: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let library = self.library();
+        if let Some(listing) = Listing::parse(&uri) {
+            let text = listing.render(&library).ok_or_else(|| {
+                McpError::resource_not_found(
+                    format!("No patterns with {}", listing.describe()),
+                    None,
+                )
+            })?;
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri,
+                    mime_type: Some(MARKDOWN_MIME_TYPE.to_string()),
+                    text,
+                    meta: None,
+                }],
+            });
+        }
+
+        let pattern = uri
+            .strip_prefix(PATTERN_URI_PREFIX)
+            .and_then(|name| library.find_pattern(name))
+            .ok_or_else(|| {
+                McpError::resource_not_found(format!("No pattern resource at '{}'", uri), None)
+            })?;
+
+        let mut visited = vec![pattern.metadata.pattern.clone()];
+        let body = library.expand_embeds(&library.composed_body(pattern), &mut visited);
+        let frontmatter = match serde_json::to_value(&pattern.metadata) {
+            Ok(serde_json::Value::Object(frontmatter)) => Some(Meta(frontmatter)),
+            _ => None,
+        };
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri,
+                mime_type: Some(MARKDOWN_MIME_TYPE.to_string()),
+                text: interpolate::expand(&body, &self.config().allowed_env),
+                meta: frontmatter,
+            }],
+        })
+    }
+
+    /// Suggest categories, tags, frameworks, projects and pattern names from the loaded
+    /// patterns, for prompt arguments and resource template variables
+    async fn complete(
         &self,
-        _request: InitializeRequestParam,
+        CompleteRequestParam { argument, .. }: CompleteRequestParam,
         _context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        let library = self.library();
+        let matches = search::complete(&argument.value, completion_candidates(&library, &argument.name));
+        let total = matches.len();
+        let values: Vec<String> = matches
+            .into_iter()
+            .take(CompletionInfo::MAX_VALUES)
+            .map(str::to_string)
+            .collect();
+        Ok(CompleteResult {
+            completion: CompletionInfo {
+                has_more: Some(total > values.len()),
+                total: Some(total as u32),
+                values,
+            },
+        })
+    }
+
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
     ) -> Result<InitializeResult, McpError> {
-        Ok(self.get_info())
+        let access = self.granted_access(&context)?;
+        *self.access.write().unwrap_or_else(|e| e.into_inner()) = access;
+        let protocol_version = negotiate(&request.protocol_version);
+        tracing::info!(
+            "Client {} asked for MCP {}, answering with {}",
+            request.client_info.name,
+            request.protocol_version,
+            protocol_version
+        );
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+        *self.peer.write().unwrap_or_else(|e| e.into_inner()) = Some(context.peer.clone());
+        let reloads = self.reloads.subscribe();
+        tokio::spawn(self.clone().follow_reloads(context.peer.clone(), reloads));
+        if !self.remote {
+            logging::connect(context.peer);
+        }
+        // Tools relying on client capabilities, such as sampling, depend on who connected
+        self.refresh_tools();
+        Ok(InitializeResult {
+            protocol_version,
+            ..self.get_info()
+        })
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        let patterns = self.clone();
+        tokio::spawn(async move { patterns.refresh_roots(context.peer).await });
+    }
+
+    async fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) {
+        let patterns = self.clone();
+        tokio::spawn(async move { patterns.refresh_roots(context.peer).await });
+    }
+
+    /// Send `notifications/resources/updated` when this resource changes on disk
+    async fn subscribe(
+        &self,
+        SubscribeRequestParam { uri }: SubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        if !uri.starts_with(PATTERN_URI_PREFIX) && Listing::parse(&uri).is_none() {
+            return Err(McpError::resource_not_found(
+                format!("No resource at '{}'", uri),
+                None,
+            ));
+        }
+        self.subscriptions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(uri);
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        UnsubscribeRequestParam { uri }: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.subscriptions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&uri);
+        Ok(())
+    }
+
+    async fn set_level(
+        &self,
+        SetLevelRequestParam { level }: SetLevelRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        if self.remote {
+            return Err(McpError::invalid_request(
+                "Server logs are only sent to local clients",
+                None,
+            ));
+        }
+        logging::set_level(context.peer, level);
+        tracing::info!("Client log level set to {:?}", level);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_bumps() {
+        let cases = [
+            ("1.2.3", VersionBump::Patch, "1.2.4"),
+            ("1.2.3", VersionBump::Minor, "1.3.0"),
+            ("1.2.3", VersionBump::Major, "2.0.0"),
+            ("v0.9.9", VersionBump::Minor, "0.10.0"),
+            (" 1.0.0 ", VersionBump::Patch, "1.0.1"),
+            ("1.0.0+build.5", VersionBump::Patch, "1.0.1"),
+        ];
+        for (version, bump, expected) in cases {
+            assert_eq!(bump.apply(version).as_deref(), Ok(expected), "{} {:?}", version, bump);
+        }
+    }
+
+    #[test]
+    fn pre_releases_bump_to_their_release() {
+        let cases = [
+            ("2.0.0-beta.1", VersionBump::Major, "2.0.0"),
+            ("2.0.0-beta.1", VersionBump::Minor, "2.0.0"),
+            ("2.0.0-beta.1", VersionBump::Patch, "2.0.0"),
+            ("2.1.0-rc.1", VersionBump::Major, "3.0.0"),
+            ("2.1.0-rc.1", VersionBump::Minor, "2.1.0"),
+            ("2.0.1-rc.1", VersionBump::Minor, "2.1.0"),
+            ("2.0.1-rc.1+sha.abc", VersionBump::Patch, "2.0.1"),
+        ];
+        for (version, bump, expected) in cases {
+            assert_eq!(bump.apply(version).as_deref(), Ok(expected), "{} {:?}", version, bump);
+        }
+    }
+
+    #[test]
+    fn invalid_versions_are_refused() {
+        for version in ["", "1", "1.2", "1.2.3.4", "1.x.3", "1..3", "+1.2.3", "1.+2.3", "1.2.3-"] {
+            let error = VersionBump::Patch.apply(version).unwrap_err();
+            assert!(error.contains("is not MAJOR.MINOR.PATCH"), "{}: {}", version, error);
+        }
+        let max = format!("1.2.{}", u64::MAX);
+        let error = VersionBump::Patch.apply(&max).unwrap_err();
+        assert!(error.ends_with("can't be raised any further"), "{}", error);
+        assert_eq!(VersionBump::Minor.apply(&max).as_deref(), Ok("1.3.0"));
     }
 }
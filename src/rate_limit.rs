@@ -0,0 +1,104 @@
+//! Per-client rate limits on tool calls over the network transports
+//!
+//! Each client address gets two token buckets, one for every tool call and one
+//! for calls to tools that write, refilled continuously up to a minute's worth.
+//! A call is only let through when both buckets have a token to spare.
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use grimoire_core::config::RateLimit;
+
+/// Clients tracked before those with full buckets, who lose nothing by being
+/// forgotten, are dropped
+const MAX_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(per_minute: u32, now: Instant) -> Self {
+        Self {
+            tokens: per_minute as f64,
+            updated: now,
+        }
+    }
+
+    /// Add the tokens earned since the last update
+    fn refill(&mut self, per_minute: u32, now: Instant) {
+        let earned = now.duration_since(self.updated).as_secs_f64() * per_minute as f64 / 60.0;
+        self.tokens = (self.tokens + earned).min(per_minute as f64);
+        self.updated = now;
+    }
+
+    /// How long until the bucket has a token, zero when it has one now
+    fn wait(&self, per_minute: u32) -> Duration {
+        if per_minute == 0 || self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) * 60.0 / per_minute as f64)
+    }
+}
+
+/// Buckets of every tool call and of writes, per client address
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    clients: Mutex<HashMap<IpAddr, (Bucket, Bucket)>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            clients: Mutex::default(),
+        }
+    }
+
+    /// Take a call, and a write when `write` is set, from the client's allowance.
+    /// Otherwise how long the client has to wait
+    pub fn check(&self, client: IpAddr, write: bool) -> Result<(), Duration> {
+        let RateLimit {
+            calls_per_minute,
+            writes_per_minute,
+        } = self.limit;
+        if calls_per_minute == 0 && (writes_per_minute == 0 || !write) {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if clients.len() >= MAX_CLIENTS {
+            clients.retain(|_, (calls, writes)| {
+                calls.refill(calls_per_minute, now);
+                writes.refill(writes_per_minute, now);
+                calls.tokens < calls_per_minute as f64 || writes.tokens < writes_per_minute as f64
+            });
+        }
+        let (calls, writes) = clients.entry(client).or_insert_with(|| {
+            (Bucket::full(calls_per_minute, now), Bucket::full(writes_per_minute, now))
+        });
+        calls.refill(calls_per_minute, now);
+        writes.refill(writes_per_minute, now);
+
+        let wait = calls.wait(calls_per_minute);
+        let wait = if write { wait.max(writes.wait(writes_per_minute)) } else { wait };
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        if calls_per_minute > 0 {
+            calls.tokens -= 1.0;
+        }
+        if write && writes_per_minute > 0 {
+            writes.tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+//! Recording of tool calls for debugging, and replaying recorded sessions
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, Result};
+use rmcp::{
+    ErrorData as McpError, ServiceExt,
+    model::{CallToolRequestParam, CallToolResult, JsonObject},
+    service::ServiceError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use grimoire_core::config::Config;
+
+use crate::patterns::Patterns;
+
+/// Keys whose values are always redacted from recordings
+const DEFAULT_REDACTED_KEYS: &[&str] = &["token", "password", "secret", "api_key", "authorization"];
+const REDACTED: &str = "[REDACTED]";
+/// Placeholder written in place of the patterns directory, so recordings replay against any library
+const PATTERNS_DIR_PLACEHOLDER: &str = "$PATTERNS_DIR";
+
+/// A single recorded tool call
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Exchange {
+    pub seq: usize,
+    pub tool: String,
+    #[serde(default)]
+    pub arguments: Option<JsonObject>,
+    #[serde(default)]
+    pub response: Option<Value>,
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+/// Rules applied to recorded requests and responses before they are written out
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    keys: Vec<String>,
+    patterns_dir: Option<String>,
+}
+
+impl Redactor {
+    pub fn new(extra_keys: &[String], patterns_dir: Option<&Path>) -> Self {
+        let keys = DEFAULT_REDACTED_KEYS
+            .iter()
+            .map(|k| k.to_string())
+            .chain(extra_keys.iter().map(|k| k.to_lowercase()))
+            .collect();
+
+        Self {
+            keys,
+            patterns_dir: patterns_dir.map(|d| d.display().to_string()),
+        }
+    }
+
+    /// Redact a JSON value in place
+    pub fn apply(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, v) in map.iter_mut() {
+                    let key = key.to_lowercase();
+                    if self.keys.iter().any(|k| key.contains(k.as_str())) {
+                        *v = Value::String(REDACTED.to_string());
+                    } else {
+                        self.apply(v);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|v| self.apply(v)),
+            Value::String(s) => {
+                if let Some(dir) = self.patterns_dir.as_deref().filter(|d| !d.is_empty()) {
+                    *s = s.replace(dir, PATTERNS_DIR_PLACEHOLDER);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Writes every tool call to a numbered JSON file in the recording directory
+#[derive(Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+    seq: AtomicUsize,
+    redactor: Redactor,
+}
+
+impl Recorder {
+    pub fn new(dir: PathBuf, redactor: Redactor) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create recording directory {:?}", dir))?;
+
+        Ok(Self {
+            dir,
+            seq: AtomicUsize::new(1),
+            redactor,
+        })
+    }
+
+    /// Record a tool request alongside its result
+    pub fn record(
+        &self,
+        request: &CallToolRequestParam,
+        result: &Result<CallToolResult, McpError>,
+    ) {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst);
+        let (response, error) = match result {
+            Ok(r) => (serde_json::to_value(r).ok(), None),
+            Err(e) => (None, serde_json::to_value(e).ok()),
+        };
+
+        let mut exchange = serde_json::json!(Exchange {
+            seq,
+            tool: request.name.to_string(),
+            arguments: request.arguments.clone(),
+            response,
+            error,
+        });
+        self.redactor.apply(&mut exchange);
+
+        let file_path = self.dir.join(format!("{:04}-{}.json", seq, request.name));
+        let written = serde_json::to_string_pretty(&exchange)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| fs::write(&file_path, json).map_err(anyhow::Error::from));
+        if let Err(e) = written {
+            tracing::warn!("Failed to record tool call to {:?}: {}", file_path, e);
+        }
+    }
+}
+
+/// Load all recorded exchanges from a directory, in recording order
+fn load_session(dir: &Path) -> Result<Vec<Exchange>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read recording directory {:?}", dir))?
+        .flat_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    files
+        .iter()
+        .map(|path| {
+            let json = fs::read_to_string(path)?;
+            serde_json::from_str(&json).with_context(|| format!("Invalid recording {:?}", path))
+        })
+        .collect()
+}
+
+/// Re-execute a recorded session against the current pattern library and report differences
+pub async fn replay(dir: &Path, config: Config, redactor: Redactor) -> Result<()> {
+    let session = load_session(dir)?;
+
+    // Run the server in-process and talk to it over an in-memory pipe
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    let server = tokio::spawn(async move {
+        match Patterns::new(config).serve(server_io).await {
+            Ok(service) => {
+                let _ = service.waiting().await;
+            }
+            Err(e) => tracing::error!("replay server error: {:?}", e),
+        }
+    });
+    let client = ().serve(client_io).await?;
+
+    let mut differences = 0;
+    for exchange in &session {
+        let result = client
+            .call_tool(CallToolRequestParam {
+                name: exchange.tool.clone().into(),
+                arguments: exchange.arguments.clone(),
+            })
+            .await;
+
+        let (mut response, mut error) = match result {
+            Ok(r) => (serde_json::to_value(r).ok(), None),
+            Err(ServiceError::McpError(e)) => (None, serde_json::to_value(e).ok()),
+            Err(e) => return Err(e).context("Replay connection failed"),
+        };
+        for value in [&mut response, &mut error].into_iter().flatten() {
+            redactor.apply(value);
+        }
+
+        if response == exchange.response && error == exchange.error {
+            println!("{:04} {}: ok", exchange.seq, exchange.tool);
+        } else {
+            differences += 1;
+            println!("{:04} {}: DIFFERS", exchange.seq, exchange.tool);
+            println!(
+                "  recorded: {}",
+                serde_json::to_string(&exchange.response.as_ref().or(exchange.error.as_ref()))?
+            );
+            println!(
+                "  replayed: {}",
+                serde_json::to_string(&response.as_ref().or(error.as_ref()))?
+            );
+        }
+    }
+
+    client.cancel().await?;
+    server.abort();
+
+    println!(
+        "Replayed {} tool calls, {} differ",
+        session.len(),
+        differences
+    );
+    if differences > 0 {
+        anyhow::bail!(
+            "{} of {} replayed tool calls differ from the recording",
+            differences,
+            session.len()
+        );
+    }
+    Ok(())
+}
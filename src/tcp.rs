@@ -0,0 +1,115 @@
+//! Plain TCP transport, for tooling that can't speak HTTP
+//!
+//! Each connection is an MCP session framed as newline-delimited JSON, exactly
+//! like stdio, handled by a session of the shared [`Patterns`] service. With
+//! auth tokens configured, clients send theirs as `"authorization": "Bearer
+//! <token>"` in the `_meta` of their initialize request. Messages longer than
+//! the configured limit end the connection.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context as TaskContext, Poll, ready},
+};
+
+use anyhow::{Context, Result};
+use grimoire_core::config::Access;
+use rmcp::ServiceExt;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpListener,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::patterns::Patterns;
+
+/// A stream that fails reading once a line runs past `max` bytes, so a client can't make
+/// the server buffer an endless message
+struct LineLimit<S> {
+    inner: S,
+    /// Bytes read since the last newline
+    line: usize,
+    max: usize,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for LineLimit<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        for byte in &buf.filled()[before..] {
+            this.line = if *byte == b'\n' { 0 } else { this.line + 1 };
+        }
+        if this.line > this.max {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("message longer than {} bytes", this.max),
+            )));
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for LineLimit<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Accept connections on `listen` until the listener fails or `shutdown` is cancelled,
+/// serving each one its own session to clients presenting one of the tokens, if there are any.
+/// Lines longer than `max_message` bytes end the connection
+pub async fn serve(
+    patterns: Patterns,
+    listen: &str,
+    tokens: Vec<(String, Access)>,
+    max_message: usize,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let patterns = patterns.with_tokens(tokens);
+    let listener = TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+    tracing::info!("Serving MCP over TCP at {}", listener.local_addr()?);
+    let ct = CancellationToken::new();
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => accepted.context("TCP server failed")?,
+            () = shutdown.cancelled() => return Ok(()),
+        };
+        let session = patterns.remote_session().with_client(peer.ip());
+        let stream = LineLimit {
+            inner: stream,
+            line: 0,
+            max: max_message,
+        };
+        let ct = ct.child_token();
+        tokio::spawn(async move {
+            tracing::info!("TCP client {} connected", peer);
+            match session.clone().serve_with_ct(stream, ct).await {
+                Ok(running) => {
+                    let _ = running.waiting().await;
+                }
+                Err(e) => tracing::warn!("TCP session from {} failed to initialize: {}", peer, e),
+            }
+            session.close();
+            tracing::info!("TCP client {} disconnected, session closed", peer);
+        });
+    }
+}
@@ -0,0 +1,94 @@
+//! Watching the patterns directory, and the config file, for changes made
+//! outside the server
+//!
+//! Editors often write a file in several steps (truncate, write, rename), so
+//! events are collected until the directory has been quiet for a moment and
+//! then reported as a single change.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
+    thread,
+    time::Duration,
+};
+
+use arc_swap::ArcSwap;
+use grimoire_core::config::Config;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long the directory must be quiet before a change is reported
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// Whether an event touches a pattern file in `dir`, rather than the server's own cache
+/// files. A watched directory may itself be a project's `.grimoire` folder
+fn touches_patterns(event: &Event, dir: &Path, config: &Config) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event.paths.iter().any(|path| {
+            config.pattern_format(path).is_some()
+                && !path
+                    .strip_prefix(dir)
+                    .unwrap_or(path)
+                    .components()
+                    .any(|c| c.as_os_str() == ".grimoire")
+        })
+}
+
+/// Call `on_change` whenever pattern files under `dir`, as the current config has them, are
+/// created, edited or deleted. Watching stops when the returned watcher is dropped
+pub fn watch(
+    dir: &Path,
+    config: Arc<ArcSwap<Config>>,
+    on_change: impl Fn() + Send + 'static,
+) -> notify::Result<RecommendedWatcher> {
+    let root = dir.to_path_buf();
+    let relevant = move |event: &Event| touches_patterns(event, &root, &config.load());
+    debounced(dir, RecursiveMode::Recursive, relevant, on_change)
+}
+
+/// Call `on_change` whenever the file at `path` is written, created or replaced. Its
+/// directory is watched instead of the file, which editors often replace with a new one
+pub fn watch_file(
+    path: &Path,
+    on_change: impl Fn() + Send + 'static,
+) -> notify::Result<RecommendedWatcher> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = path.file_name().map(|n| n.to_os_string());
+    let relevant = move |event: &Event| {
+        !matches!(event.kind, EventKind::Access(_))
+            && event.paths.iter().any(|p| p.file_name() == name.as_deref())
+    };
+    debounced(&dir, RecursiveMode::NonRecursive, relevant, on_change)
+}
+
+/// Watch `dir`, calling `on_change` once things settle after events `relevant` accepts
+fn debounced(
+    dir: &Path,
+    mode: RecursiveMode,
+    relevant: impl Fn(&Event) -> bool + Send + 'static,
+    on_change: impl Fn() + Send + 'static,
+) -> notify::Result<RecommendedWatcher> {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(dir, mode)?;
+
+    thread::spawn(move || {
+        while let Ok(event) = rx.recv() {
+            let mut changed = event.is_ok_and(|e| relevant(&e));
+            loop {
+                match rx.recv_timeout(SETTLE_TIME) {
+                    Ok(event) => changed |= event.is_ok_and(|e| relevant(&e)),
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            if changed {
+                on_change();
+            }
+        }
+    });
+
+    Ok(watcher)
+}